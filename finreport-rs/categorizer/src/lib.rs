@@ -1 +1,2 @@
 pub mod categorize;
+pub mod rules;
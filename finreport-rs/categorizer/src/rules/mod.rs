@@ -0,0 +1,266 @@
+//! Deterministic categorization. A rule names a category and criteria on a
+//! booking: the payee, the IBAN of the other party, the SEPA creditor and
+//! mandate IDs, an amount range, the transaction type and a regex over the
+//! remittance info. Every criterion a rule sets has to match; of the
+//! matching rules the one with the highest priority wins, the older one on
+//! a tie. Only bookings no rule matches are left for the model.
+
+use regex::{Regex, RegexBuilder};
+use std::cmp::Reverse;
+use std::fmt::{Display, Formatter};
+
+/// What has to hold for a rule to match. Text criteria ignore case; IBANs
+/// and creditor IDs also ignore spaces.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Criteria {
+    /// Part of the payee name.
+    pub payee: Option<String>,
+    pub iban: Option<String>,
+    pub creditor_id: Option<String>,
+    pub mandate_id: Option<String>,
+    /// Lowest amount, inclusive; debits are negative.
+    pub min_amount: Option<f64>,
+    /// Highest amount, inclusive.
+    pub max_amount: Option<f64>,
+    pub transaction_type: Option<String>,
+    /// Regular expression searched in the remittance info.
+    pub remittance_pattern: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub id: i32,
+    pub priority: i32,
+    pub criteria: Criteria,
+}
+
+/// The facts of a booking rules look at.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Booking<'a> {
+    pub payee: &'a str,
+    pub iban: &'a str,
+    pub creditor_id: &'a str,
+    pub mandate_id: &'a str,
+    pub amount: f64,
+    pub transaction_type: &'a str,
+    pub remittance_info: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleError {
+    /// A rule without criteria would match every booking.
+    NoCriteria,
+    EmptyAmountRange { min: f64, max: f64 },
+    InvalidPattern(String),
+}
+
+impl Display for RuleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleError::NoCriteria => write!(f, "the rule has no criteria"),
+            RuleError::EmptyAmountRange { min, max } => {
+                write!(f, "the minimum amount {min} is above the maximum {max}")
+            }
+            RuleError::InvalidPattern(message) => {
+                write!(f, "the remittance pattern does not compile: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+impl Criteria {
+    /// Criteria as stored: trimmed, empty ones unset, IBANs and creditor IDs
+    /// upper case without spaces. Fails for criteria that cannot match
+    /// sensibly.
+    pub fn normalized(self) -> Result<Criteria, RuleError> {
+        let text = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let criteria = Criteria {
+            payee: text(self.payee),
+            iban: text(self.iban).map(|v| compact(&v)),
+            creditor_id: text(self.creditor_id).map(|v| compact(&v)),
+            mandate_id: text(self.mandate_id),
+            min_amount: self.min_amount,
+            max_amount: self.max_amount,
+            transaction_type: text(self.transaction_type),
+            remittance_pattern: text(self.remittance_pattern),
+        };
+        if criteria == Criteria::default() {
+            return Err(RuleError::NoCriteria);
+        }
+        if let (Some(min), Some(max)) = (criteria.min_amount, criteria.max_amount)
+            && min > max
+        {
+            return Err(RuleError::EmptyAmountRange { min, max });
+        }
+        if let Some(pattern) = &criteria.remittance_pattern {
+            regex(pattern)?;
+        }
+        Ok(criteria)
+    }
+}
+
+/// Rules ready to match, in the order they are tried.
+#[derive(Debug, Default)]
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    rule: Rule,
+    payee: Option<String>,
+    remittance: Option<Regex>,
+}
+
+impl RuleSet {
+    /// Add a rule; rules whose criteria do not hold are refused.
+    pub fn add(&mut self, rule: Rule) -> Result<(), RuleError> {
+        let criteria = rule.criteria.clone().normalized()?;
+        let compiled = CompiledRule {
+            payee: criteria.payee.as_ref().map(|p| p.to_lowercase()),
+            remittance: criteria.remittance_pattern.as_deref().map(regex).transpose()?,
+            rule: Rule { criteria, ..rule },
+        };
+        let key = |rule: &Rule| (Reverse(rule.priority), rule.id);
+        let position = self.rules.partition_point(|r| key(&r.rule) < key(&compiled.rule));
+        self.rules.insert(position, compiled);
+        Ok(())
+    }
+
+    /// The rule that categorizes `booking`, if any.
+    pub fn matching(&self, booking: &Booking) -> Option<&Rule> {
+        let payee = booking.payee.to_lowercase();
+        let iban = compact(booking.iban);
+        let creditor_id = compact(booking.creditor_id);
+        self.rules
+            .iter()
+            .find(|r| {
+                let c = &r.rule.criteria;
+                r.payee.as_ref().is_none_or(|p| payee.contains(p.as_str()))
+                    && c.iban.as_ref().is_none_or(|i| *i == iban)
+                    && c.creditor_id.as_ref().is_none_or(|i| *i == creditor_id)
+                    && c.mandate_id.as_ref().is_none_or(|m| m.eq_ignore_ascii_case(booking.mandate_id.trim()))
+                    && c.min_amount.is_none_or(|min| booking.amount >= min)
+                    && c.max_amount.is_none_or(|max| booking.amount <= max)
+                    && c.transaction_type
+                        .as_ref()
+                        .is_none_or(|t| t.eq_ignore_ascii_case(booking.transaction_type.trim()))
+                    && r.remittance.as_ref().is_none_or(|re| re.is_match(booking.remittance_info))
+            })
+            .map(|r| &r.rule)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+fn regex(pattern: &str) -> Result<Regex, RuleError> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| RuleError::InvalidPattern(e.to_string()))
+}
+
+fn compact(value: &str) -> String {
+    value.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::rules::{Booking, Criteria, Rule, RuleError, RuleSet};
+
+    fn rule(id: i32, priority: i32, criteria: Criteria) -> Rule {
+        Rule { id, priority, criteria }
+    }
+
+    #[test]
+    fn test_highest_priority_matching_rule_wins() {
+        let mut rules = RuleSet::default();
+        let rent = Criteria {
+            iban: Some("de89 3704 0044 0532 0130 00".to_string()),
+            max_amount: Some(-500.0),
+            ..Default::default()
+        };
+        let broadcasting = Criteria {
+            creditor_id: Some("DE27ZZZ00000041843".to_string()),
+            ..Default::default()
+        };
+        let shopping = Criteria {
+            payee: Some("Rewe".to_string()),
+            remittance_pattern: Some(r"markt\s+\d+".to_string()),
+            ..Default::default()
+        };
+        let any_debit = Criteria {
+            transaction_type: Some("lastschrift".to_string()),
+            ..Default::default()
+        };
+        rules.add(rule(4, 0, any_debit)).unwrap();
+        rules.add(rule(1, 10, rent)).unwrap();
+        rules.add(rule(2, 10, broadcasting)).unwrap();
+        rules.add(rule(3, 5, shopping)).unwrap();
+
+        let rent = Booking {
+            iban: "DE89370400440532013000",
+            amount: -950.0,
+            transaction_type: "Überweisung",
+            ..Default::default()
+        };
+        assert_eq!(rules.matching(&rent).map(|r| r.id), Some(1));
+        let refund = Booking { amount: 950.0, ..rent };
+        assert_eq!(rules.matching(&refund), None);
+
+        let debit = Booking {
+            payee: "REWE Markt GmbH",
+            creditor_id: "DE27 ZZZ0 0000 0418 43",
+            transaction_type: "Lastschrift",
+            remittance_info: "REWE MARKT 1234 BERLIN",
+            amount: -18.36,
+            ..Default::default()
+        };
+        assert_eq!(rules.matching(&debit).map(|r| r.id), Some(2));
+        let debit = Booking { creditor_id: "", ..debit };
+        assert_eq!(rules.matching(&debit).map(|r| r.id), Some(3));
+        let debit = Booking { remittance_info: "REWE", ..debit };
+        assert_eq!(rules.matching(&debit).map(|r| r.id), Some(4));
+    }
+
+    #[test]
+    fn test_invalid_rules_are_refused() {
+        let mut rules = RuleSet::default();
+        let blank = Criteria {
+            payee: Some("  ".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(rules.add(rule(1, 0, blank)), Err(RuleError::NoCriteria));
+        let range = Criteria {
+            min_amount: Some(10.0),
+            max_amount: Some(-10.0),
+            ..Default::default()
+        };
+        assert!(matches!(rules.add(rule(2, 0, range)), Err(RuleError::EmptyAmountRange { .. })));
+        let pattern = Criteria {
+            remittance_pattern: Some("(".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(rules.add(rule(3, 0, pattern)), Err(RuleError::InvalidPattern(_))));
+        assert!(rules.is_empty());
+    }
+
+    #[test]
+    fn test_extreme_priorities_keep_their_order() {
+        let mut rules = RuleSet::default();
+        let debit = || Criteria {
+            transaction_type: Some("lastschrift".to_string()),
+            ..Default::default()
+        };
+        rules.add(rule(1, i32::MIN, debit())).unwrap();
+        rules.add(rule(2, 0, debit())).unwrap();
+        rules.add(rule(3, i32::MAX, debit())).unwrap();
+        rules.add(rule(4, i32::MIN, debit())).unwrap();
+        let ids: Vec<i32> = rules.rules.iter().map(|r| r.rule.id).collect();
+        assert_eq!(ids, [3, 2, 1, 4]);
+    }
+}
@@ -17,11 +17,17 @@ pub async fn get_accounts(
     Ok(balances_response)
 }
 
+/// All transactions of an account and the number of API pages it took.
+pub struct FetchedTransactions {
+    pub transactions: Vec<Transaction>,
+    pub pages: u32,
+}
+
 pub async fn get_account_transactions(
     session: Session,
     client_settings: Settings,
     account: &Account,
-) -> AccountClientResult<Vec<Transaction>> {
+) -> AccountClientResult<FetchedTransactions> {
     let Settings { url, .. } = client_settings;
 
     let account_client = AccountClient::new(session, build_client(), url);
@@ -34,16 +40,23 @@ pub async fn get_account_transactions(
     let values = transactions_response.values;
     let total_transactions = transactions_response.paging.matches;
     let page_size = values.len();
+    let mut pages = 1;
 
     all_transactions.extend(values);
 
-    for index in (page_size as i32..total_transactions).step_by(page_size) {
-        let response = account_client
-            .get_account_transactions(&account_id, index as u32)
-            .await?;
-        all_transactions.extend(response.values);
+    if page_size > 0 {
+        for index in (page_size as i32..total_transactions).step_by(page_size) {
+            let response = account_client
+                .get_account_transactions(account_id, index as u32)
+                .await?;
+            all_transactions.extend(response.values);
+            pages += 1;
 
-        tracing::info!(%account_id, index, total = total_transactions, "fetching transactions");
+            tracing::info!(%account_id, index, total = total_transactions, "fetching transactions");
+        }
     }
-    Ok(all_transactions)
+    Ok(FetchedTransactions {
+        transactions: all_transactions,
+        pages,
+    })
 }
@@ -23,6 +23,10 @@ pub enum Relation {
     AccountBalance,
     #[sea_orm(has_many = "super::account_transactions::Entity")]
     AccountTransactions,
+    #[sea_orm(has_many = "super::daily_balances::Entity")]
+    DailyBalances,
+    #[sea_orm(has_many = "super::data_findings::Entity")]
+    DataFindings,
 }
 
 impl Related<super::account_balance::Entity> for Entity {
@@ -37,4 +41,16 @@ impl Related<super::account_transactions::Entity> for Entity {
     }
 }
 
+impl Related<super::daily_balances::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DailyBalances.def()
+    }
+}
+
+impl Related<super::data_findings::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DataFindings.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -21,6 +21,7 @@ pub struct Model {
     pub creditor_mandate_id: String,
     pub remittance_info: String,
     pub transaction_type: String,
+    pub counterparty_iban: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -33,6 +34,24 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Account,
+    #[sea_orm(has_many = "super::attachments::Entity")]
+    Attachments,
+    #[sea_orm(has_many = "super::mandate_category_conflicts::Entity")]
+    MandateCategoryConflicts,
+    #[sea_orm(has_many = "super::paypal_transactions::Entity")]
+    PaypalTransactions,
+    #[sea_orm(has_one = "super::transaction_categories::Entity")]
+    TransactionCategories,
+    #[sea_orm(has_one = "super::transaction_counterparties::Entity")]
+    TransactionCounterparties,
+    #[sea_orm(has_one = "super::transaction_notes::Entity")]
+    TransactionNotes,
+    #[sea_orm(has_one = "super::transaction_remittance::Entity")]
+    TransactionRemittance,
+    #[sea_orm(has_many = "super::transaction_splits::Entity")]
+    TransactionSplits,
+    #[sea_orm(has_many = "super::transaction_tags::Entity")]
+    TransactionTags,
 }
 
 impl Related<super::account::Entity> for Entity {
@@ -41,4 +60,58 @@ impl Related<super::account::Entity> for Entity {
     }
 }
 
+impl Related<super::attachments::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Attachments.def()
+    }
+}
+
+impl Related<super::mandate_category_conflicts::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MandateCategoryConflicts.def()
+    }
+}
+
+impl Related<super::paypal_transactions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PaypalTransactions.def()
+    }
+}
+
+impl Related<super::transaction_categories::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TransactionCategories.def()
+    }
+}
+
+impl Related<super::transaction_counterparties::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TransactionCounterparties.def()
+    }
+}
+
+impl Related<super::transaction_notes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TransactionNotes.def()
+    }
+}
+
+impl Related<super::transaction_remittance::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TransactionRemittance.def()
+    }
+}
+
+impl Related<super::transaction_splits::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TransactionSplits.def()
+    }
+}
+
+impl Related<super::transaction_tags::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TransactionTags.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -5,16 +5,42 @@ use sea_orm::entity::prelude::*;
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "categories")]
 pub struct Model {
-    #[sea_orm(primary_key, auto_increment = false)]
+    #[sea_orm(primary_key)]
     pub id: i32,
-    pub category: Option<String>,
-    pub subcategory: Option<String>,
+    pub category: String,
+    pub subcategory: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::categorization_rules::Entity")]
+    CategorizationRules,
+    #[sea_orm(has_many = "super::mandate_categories::Entity")]
+    MandateCategories,
+    #[sea_orm(has_many = "super::mandate_category_conflicts::Entity")]
+    MandateCategoryConflicts,
     #[sea_orm(has_many = "super::transaction_categories::Entity")]
     TransactionCategories,
+    #[sea_orm(has_many = "super::transaction_splits::Entity")]
+    TransactionSplits,
+}
+
+impl Related<super::categorization_rules::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CategorizationRules.def()
+    }
+}
+
+impl Related<super::mandate_categories::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MandateCategories.def()
+    }
+}
+
+impl Related<super::mandate_category_conflicts::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MandateCategoryConflicts.def()
+    }
 }
 
 impl Related<super::transaction_categories::Entity> for Entity {
@@ -23,4 +49,10 @@ impl Related<super::transaction_categories::Entity> for Entity {
     }
 }
 
+impl Related<super::transaction_splits::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TransactionSplits.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -12,6 +12,29 @@ pub struct Model {
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::categories::Entity",
+        from = "Column::CategoryId",
+        to = "super::categories::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Restrict"
+    )]
+    Categories,
+    #[sea_orm(has_many = "super::mandate_category_conflicts::Entity")]
+    MandateCategoryConflicts,
+}
+
+impl Related<super::categories::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Categories.def()
+    }
+}
+
+impl Related<super::mandate_category_conflicts::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MandateCategoryConflicts.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -5,7 +5,25 @@ pub mod prelude;
 pub mod account;
 pub mod account_balance;
 pub mod account_transactions;
+pub mod attachments;
 pub mod categories;
+pub mod categorization_rules;
+pub mod counterparties;
+pub mod counterparty_rules;
+pub mod daily_balances;
+pub mod data_findings;
+pub mod import_run_accounts;
+pub mod import_runs;
+pub mod internal_transfers;
 pub mod mandate_categories;
+pub mod mandate_category_conflicts;
+pub mod paypal_transactions;
+pub mod saved_views;
+pub mod tags;
 pub mod transaction_categories;
-pub mod transactions;
+pub mod transaction_counterparties;
+pub mod transaction_history;
+pub mod transaction_notes;
+pub mod transaction_remittance;
+pub mod transaction_splits;
+pub mod transaction_tags;
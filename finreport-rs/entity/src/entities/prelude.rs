@@ -3,7 +3,25 @@
 pub use super::account::Entity as Account;
 pub use super::account_balance::Entity as AccountBalance;
 pub use super::account_transactions::Entity as AccountTransactions;
+pub use super::attachments::Entity as Attachments;
 pub use super::categories::Entity as Categories;
+pub use super::categorization_rules::Entity as CategorizationRules;
+pub use super::counterparties::Entity as Counterparties;
+pub use super::counterparty_rules::Entity as CounterpartyRules;
+pub use super::daily_balances::Entity as DailyBalances;
+pub use super::data_findings::Entity as DataFindings;
+pub use super::import_run_accounts::Entity as ImportRunAccounts;
+pub use super::import_runs::Entity as ImportRuns;
+pub use super::internal_transfers::Entity as InternalTransfers;
 pub use super::mandate_categories::Entity as MandateCategories;
+pub use super::mandate_category_conflicts::Entity as MandateCategoryConflicts;
+pub use super::paypal_transactions::Entity as PaypalTransactions;
+pub use super::saved_views::Entity as SavedViews;
+pub use super::tags::Entity as Tags;
 pub use super::transaction_categories::Entity as TransactionCategories;
-pub use super::transactions::Entity as Transactions;
+pub use super::transaction_counterparties::Entity as TransactionCounterparties;
+pub use super::transaction_history::Entity as TransactionHistory;
+pub use super::transaction_notes::Entity as TransactionNotes;
+pub use super::transaction_remittance::Entity as TransactionRemittance;
+pub use super::transaction_splits::Entity as TransactionSplits;
+pub use super::transaction_tags::Entity as TransactionTags;
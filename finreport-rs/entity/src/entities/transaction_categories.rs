@@ -7,7 +7,8 @@ use sea_orm::entity::prelude::*;
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub reference: String,
-    pub category_id: Option<i32>,
+    pub category_id: i32,
+    #[sea_orm(column_type = "Text", nullable)]
     pub reasoning: Option<String>,
     #[sea_orm(column_type = "Double", nullable)]
     pub confidence: Option<f64>,
@@ -15,33 +16,33 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::account_transactions::Entity",
+        from = "Column::Reference",
+        to = "super::account_transactions::Column::Reference",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    AccountTransactions,
     #[sea_orm(
         belongs_to = "super::categories::Entity",
         from = "Column::CategoryId",
         to = "super::categories::Column::Id",
         on_update = "NoAction",
-        on_delete = "NoAction"
+        on_delete = "Restrict"
     )]
     Categories,
-    #[sea_orm(
-        belongs_to = "super::transactions::Entity",
-        from = "Column::Reference",
-        to = "super::transactions::Column::Reference",
-        on_update = "NoAction",
-        on_delete = "NoAction"
-    )]
-    Transactions,
 }
 
-impl Related<super::categories::Entity> for Entity {
+impl Related<super::account_transactions::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::Categories.def()
+        Relation::AccountTransactions.def()
     }
 }
 
-impl Related<super::transactions::Entity> for Entity {
+impl Related<super::categories::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::Transactions.def()
+        Relation::Categories.def()
     }
 }
 
@@ -3,6 +3,22 @@ pub use sea_orm_migration::prelude::*;
 mod m20220101_000001_account;
 mod m20250609_193042_account_balances;
 mod m20250609_221755_account_transactions;
+mod m20261019_090000_paypal_transactions;
+mod m20261019_100000_categories;
+mod m20261019_110000_transaction_history;
+mod m20261019_120000_import_runs;
+mod m20261019_130000_daily_balances;
+mod m20261019_140000_data_findings;
+mod m20261019_150000_transaction_search;
+mod m20261019_160000_saved_views;
+mod m20261019_170000_transaction_splits;
+mod m20261019_180000_tags_notes;
+mod m20261019_190000_internal_transfers;
+mod m20261019_200000_counterparties;
+mod m20261019_210000_transaction_remittance;
+mod m20261019_220000_attachments;
+mod m20261019_230000_categorization_rules;
+mod m20261020_000000_mandate_category_conflicts;
 
 pub struct Migrator;
 
@@ -13,6 +29,63 @@ impl MigratorTrait for Migrator {
             Box::new(m20220101_000001_account::Migration),
             Box::new(m20250609_193042_account_balances::Migration),
             Box::new(m20250609_221755_account_transactions::Migration),
+            Box::new(m20261019_090000_paypal_transactions::Migration),
+            Box::new(m20261019_100000_categories::Migration),
+            Box::new(m20261019_110000_transaction_history::Migration),
+            Box::new(m20261019_120000_import_runs::Migration),
+            Box::new(m20261019_130000_daily_balances::Migration),
+            Box::new(m20261019_140000_data_findings::Migration),
+            Box::new(m20261019_150000_transaction_search::Migration),
+            Box::new(m20261019_160000_saved_views::Migration),
+            Box::new(m20261019_170000_transaction_splits::Migration),
+            Box::new(m20261019_180000_tags_notes::Migration),
+            Box::new(m20261019_190000_internal_transfers::Migration),
+            Box::new(m20261019_200000_counterparties::Migration),
+            Box::new(m20261019_210000_transaction_remittance::Migration),
+            Box::new(m20261019_220000_attachments::Migration),
+            Box::new(m20261019_230000_categorization_rules::Migration),
+            Box::new(m20261020_000000_mandate_category_conflicts::Migration),
         ]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{Migrator, MigratorTrait};
+    use sea_orm_migration::sea_orm::{ConnectionTrait, Database, Statement};
+
+    /// Apply all migrations to an empty database, check the seeded taxonomy,
+    /// roll everything back and apply again.
+    async fn round_trip(url: &str) {
+        let conn = Database::connect(url).await.unwrap();
+        Migrator::fresh(&conn).await.unwrap();
+
+        let row = conn
+            .query_one(Statement::from_string(
+                conn.get_database_backend(),
+                "SELECT COUNT(*) AS n FROM categories",
+            ))
+            .await
+            .unwrap()
+            .unwrap();
+        let seeded: i64 = row.try_get("", "n").unwrap();
+        assert!(seeded > 0);
+
+        Migrator::down(&conn, None).await.unwrap();
+        Migrator::up(&conn, None).await.unwrap();
+    }
+
+    #[async_std::test]
+    async fn test_migrations_sqlite() {
+        round_trip("sqlite::memory:").await;
+    }
+
+    /// Needs a scratch database in `TEST_POSTGRES_URL`; all its tables are
+    /// dropped. Run by `just test-matrix`.
+    #[async_std::test]
+    #[ignore = "needs TEST_POSTGRES_URL"]
+    async fn test_migrations_postgres() {
+        let url = std::env::var("TEST_POSTGRES_URL").expect("TEST_POSTGRES_URL is not set");
+        round_trip(&url).await;
+    }
+}
@@ -46,7 +46,7 @@ impl MigrationTrait for Migration {
 }
 
 #[derive(DeriveIden)]
-enum AccountTransactions {
+pub enum AccountTransactions {
     Table,
     Id,
     Reference,
@@ -61,4 +61,5 @@ enum AccountTransactions {
     CreditorMandateId,
     RemittanceInfo,
     TransactionType,
+    CounterpartyIban,
 }
@@ -0,0 +1,65 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20250609_221755_account_transactions::AccountTransactions;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PaypalTransactions::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PaypalTransactions::Id))
+                    .col(string_uniq(PaypalTransactions::TransactionCode).not_null())
+                    .col(date(PaypalTransactions::Date).not_null())
+                    .col(string(PaypalTransactions::Name))
+                    .col(string(PaypalTransactions::Type))
+                    .col(string(PaypalTransactions::Status))
+                    .col(string(PaypalTransactions::Currency))
+                    .col(double(PaypalTransactions::Gross))
+                    .col(double(PaypalTransactions::Fee))
+                    .col(double(PaypalTransactions::Net))
+                    .col(string(PaypalTransactions::Email))
+                    .col(string(PaypalTransactions::ItemTitle))
+                    .col(string(PaypalTransactions::RelatedTransactionCode))
+                    .col(string_null(PaypalTransactions::MatchedReference))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-paypal-transactions-matched-reference")
+                            .from(PaypalTransactions::Table, PaypalTransactions::MatchedReference)
+                            .to(AccountTransactions::Table, AccountTransactions::Reference)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PaypalTransactions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PaypalTransactions {
+    Table,
+    Id,
+    TransactionCode,
+    Date,
+    Name,
+    Type,
+    Status,
+    Currency,
+    Gross,
+    Fee,
+    Net,
+    Email,
+    ItemTitle,
+    RelatedTransactionCode,
+    MatchedReference,
+}
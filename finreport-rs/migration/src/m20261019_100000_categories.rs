@@ -0,0 +1,148 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20250609_221755_account_transactions::AccountTransactions;
+
+/// Taxonomy the categorizer prompt is built from; seeded so that
+/// `transaction_categories` can reference it right away.
+const CATEGORIES_JSON: &str = include_str!("../../../prompts/categories.json");
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Categories::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Categories::Id))
+                    .col(string(Categories::Category))
+                    .col(string(Categories::Subcategory))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-categories-category-subcategory")
+                    .table(Categories::Table)
+                    .col(Categories::Category)
+                    .col(Categories::Subcategory)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(TransactionCategories::Table)
+                    .if_not_exists()
+                    .col(string(TransactionCategories::Reference).primary_key())
+                    .col(integer(TransactionCategories::CategoryId))
+                    .col(text_null(TransactionCategories::Reasoning))
+                    .col(double_null(TransactionCategories::Confidence))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-transaction-categories-reference")
+                            .from(TransactionCategories::Table, TransactionCategories::Reference)
+                            .to(AccountTransactions::Table, AccountTransactions::Reference)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-transaction-categories-category-id")
+                            .from(TransactionCategories::Table, TransactionCategories::CategoryId)
+                            .to(Categories::Table, Categories::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(MandateCategories::Table)
+                    .if_not_exists()
+                    .col(string(MandateCategories::CreditorMandateId).primary_key())
+                    .col(integer(MandateCategories::CategoryId))
+                    .col(string_null(MandateCategories::Description))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-mandate-categories-category-id")
+                            .from(MandateCategories::Table, MandateCategories::CategoryId)
+                            .to(Categories::Table, Categories::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        seed_categories(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MandateCategories::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(TransactionCategories::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Categories::Table).to_owned())
+            .await
+    }
+}
+
+async fn seed_categories(manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+    let taxonomy: Vec<serde_json::Value> = serde_json::from_str(CATEGORIES_JSON)
+        .map_err(|e| DbErr::Migration(format!("invalid prompts/categories.json: {e}")))?;
+
+    let mut insert = Query::insert();
+    insert
+        .into_table(Categories::Table)
+        .columns([Categories::Category, Categories::Subcategory])
+        .on_conflict(
+            OnConflict::columns([Categories::Category, Categories::Subcategory])
+                .do_nothing()
+                .to_owned(),
+        );
+    for entry in &taxonomy {
+        let category = entry["category"].as_str().ok_or_else(|| {
+            DbErr::Migration(format!("category without name in categories.json: {entry}"))
+        })?;
+        let subcategories = entry["subcategories"].as_array().cloned().unwrap_or_default();
+        for subcategory in subcategories.iter().filter_map(|s| s.as_str()) {
+            insert.values_panic([category.into(), subcategory.into()]);
+        }
+    }
+    manager.exec_stmt(insert).await
+}
+
+#[derive(DeriveIden)]
+enum Categories {
+    Table,
+    Id,
+    Category,
+    Subcategory,
+}
+
+#[derive(DeriveIden)]
+enum TransactionCategories {
+    Table,
+    Reference,
+    CategoryId,
+    Reasoning,
+    Confidence,
+}
+
+#[derive(DeriveIden)]
+enum MandateCategories {
+    Table,
+    CreditorMandateId,
+    CategoryId,
+    Description,
+}
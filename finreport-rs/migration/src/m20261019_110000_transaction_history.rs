@@ -0,0 +1,55 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// Append-only log of field changes on `account_transactions`. There is no
+/// foreign key on `reference`: the trail has to outlive deleted bookings.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TransactionHistory::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TransactionHistory::Id))
+                    .col(string(TransactionHistory::Reference))
+                    .col(timestamp_with_time_zone(TransactionHistory::ChangedAt))
+                    .col(string(TransactionHistory::Source))
+                    .col(string(TransactionHistory::Field))
+                    .col(text_null(TransactionHistory::OldValue))
+                    .col(text_null(TransactionHistory::NewValue))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-transaction-history-reference")
+                    .table(TransactionHistory::Table)
+                    .col(TransactionHistory::Reference)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TransactionHistory::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TransactionHistory {
+    Table,
+    Id,
+    Reference,
+    ChangedAt,
+    Source,
+    Field,
+    OldValue,
+    NewValue,
+}
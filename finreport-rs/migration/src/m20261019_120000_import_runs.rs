@@ -0,0 +1,103 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// One row per importer run or session bootstrap. A run without
+/// `finished_at` is still running (or crashed); `error` is set on failure.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ImportRuns::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ImportRuns::Id))
+                    .col(string(ImportRuns::Kind))
+                    .col(string(ImportRuns::Connector))
+                    .col(timestamp_with_time_zone(ImportRuns::StartedAt))
+                    .col(timestamp_with_time_zone_null(ImportRuns::FinishedAt))
+                    .col(integer(ImportRuns::PagesFetched).default(0))
+                    .col(text_null(ImportRuns::Error))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-import-runs-started-at")
+                    .table(ImportRuns::Table)
+                    .col(ImportRuns::StartedAt)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ImportRunAccounts::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ImportRunAccounts::Id))
+                    .col(integer(ImportRunAccounts::ImportRunId))
+                    .col(string(ImportRunAccounts::AccountId))
+                    .col(integer(ImportRunAccounts::Fetched))
+                    .col(integer(ImportRunAccounts::Inserted))
+                    .col(integer(ImportRunAccounts::Updated))
+                    .col(integer(ImportRunAccounts::Failed))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-import-run-accounts-import-run-id")
+                            .from(ImportRunAccounts::Table, ImportRunAccounts::ImportRunId)
+                            .to(ImportRuns::Table, ImportRuns::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-import-run-accounts-import-run-id")
+                    .table(ImportRunAccounts::Table)
+                    .col(ImportRunAccounts::ImportRunId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ImportRunAccounts::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(ImportRuns::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ImportRuns {
+    Table,
+    Id,
+    Kind,
+    Connector,
+    StartedAt,
+    FinishedAt,
+    PagesFetched,
+    Error,
+}
+
+#[derive(DeriveIden)]
+enum ImportRunAccounts {
+    Table,
+    Id,
+    ImportRunId,
+    AccountId,
+    Fetched,
+    Inserted,
+    Updated,
+    Failed,
+}
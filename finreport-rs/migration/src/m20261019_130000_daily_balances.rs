@@ -0,0 +1,62 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20220101_000001_account::Account;
+
+/// End-of-day balances reconstructed from the bookings, next to the stored
+/// snapshot of that day if there is one. Rebuilt per account by the
+/// reconciliation job.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DailyBalances::Table)
+                    .if_not_exists()
+                    .col(pk_auto(DailyBalances::Id))
+                    .col(string(DailyBalances::AccountId))
+                    .col(date(DailyBalances::Date))
+                    .col(double(DailyBalances::Amount))
+                    .col(double_null(DailyBalances::Snapshot))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-daily-balances-account-id")
+                            .from(DailyBalances::Table, DailyBalances::AccountId)
+                            .to(Account::Table, Account::AccountId)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-daily-balances-account-id-date")
+                    .table(DailyBalances::Table)
+                    .col(DailyBalances::AccountId)
+                    .col(DailyBalances::Date)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DailyBalances::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DailyBalances {
+    Table,
+    Id,
+    AccountId,
+    Date,
+    Amount,
+    Snapshot,
+}
@@ -0,0 +1,77 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20220101_000001_account::Account;
+
+/// Data-quality findings awaiting or after review: probable duplicate
+/// transactions and date ranges whose bookings do not explain the balance
+/// change. `fingerprint` identifies a finding across detection runs, so a
+/// reviewed finding is not raised again.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DataFindings::Table)
+                    .if_not_exists()
+                    .col(pk_auto(DataFindings::Id))
+                    .col(string_uniq(DataFindings::Fingerprint))
+                    .col(string(DataFindings::Kind))
+                    .col(string(DataFindings::AccountId))
+                    .col(string_null(DataFindings::Reference))
+                    .col(string_null(DataFindings::OtherReference))
+                    .col(date(DataFindings::DateFrom))
+                    .col(date(DataFindings::DateTo))
+                    .col(double_null(DataFindings::Amount))
+                    .col(double_null(DataFindings::Similarity))
+                    .col(string(DataFindings::Status))
+                    .col(timestamp_with_time_zone(DataFindings::DetectedAt))
+                    .col(timestamp_with_time_zone_null(DataFindings::ResolvedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-data-findings-account-id")
+                            .from(DataFindings::Table, DataFindings::AccountId)
+                            .to(Account::Table, Account::AccountId)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-data-findings-status")
+                    .table(DataFindings::Table)
+                    .col(DataFindings::Status)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DataFindings::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DataFindings {
+    Table,
+    Id,
+    Fingerprint,
+    Kind,
+    AccountId,
+    Reference,
+    OtherReference,
+    DateFrom,
+    DateTo,
+    Amount,
+    Similarity,
+    Status,
+    DetectedAt,
+    ResolvedAt,
+}
@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+/// Indexes for searching transactions on Postgres: German full-text search
+/// and trigram similarity over the remittance info and the parties. The
+/// expression must match `webapp::search::DOCUMENT` for the planner to use
+/// them. SQLite searches with LIKE and gets no index.
+///
+/// pg_trgm goes into its own schema: `migrate fresh` drops every type in the
+/// current one, including those of extensions.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const DOCUMENT: &str =
+    "remittance_info || ' ' || remitter || ' ' || creditor || ' ' || deptor";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DbBackend::Postgres {
+            return Ok(());
+        }
+        let db = manager.get_connection();
+        db.execute_unprepared("CREATE SCHEMA IF NOT EXISTS extensions")
+            .await?;
+        db.execute_unprepared("CREATE EXTENSION IF NOT EXISTS pg_trgm SCHEMA extensions")
+            .await?;
+        db.execute_unprepared(&format!(
+            "CREATE INDEX IF NOT EXISTS \"idx-transactions-search-fts\" \
+             ON account_transactions USING gin (to_tsvector('german', {DOCUMENT}))"
+        ))
+        .await?;
+        db.execute_unprepared(&format!(
+            "CREATE INDEX IF NOT EXISTS \"idx-transactions-search-trgm\" \
+             ON account_transactions USING gin (({DOCUMENT}) extensions.gin_trgm_ops)"
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DbBackend::Postgres {
+            return Ok(());
+        }
+        let db = manager.get_connection();
+        db.execute_unprepared("DROP INDEX IF EXISTS \"idx-transactions-search-trgm\"")
+            .await?;
+        db.execute_unprepared("DROP INDEX IF EXISTS \"idx-transactions-search-fts\"")
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// Named filter expressions ("smart folders") per user. `owner` is the user
+/// name the authenticating proxy passes on; single-user installs share one
+/// owner. `filter` uses the syntax of `webapp::filter`.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SavedViews::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SavedViews::Id))
+                    .col(string(SavedViews::Owner))
+                    .col(string(SavedViews::Name))
+                    .col(text(SavedViews::Filter))
+                    .col(timestamp_with_time_zone(SavedViews::CreatedAt))
+                    .col(timestamp_with_time_zone(SavedViews::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-saved-views-owner-name")
+                    .table(SavedViews::Table)
+                    .col(SavedViews::Owner)
+                    .col(SavedViews::Name)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SavedViews::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SavedViews {
+    Table,
+    Id,
+    Owner,
+    Name,
+    Filter,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,80 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20250609_221755_account_transactions::AccountTransactions;
+
+/// Parts of a transaction with their own category, note and tag, e.g. the
+/// groceries, household items and gift of one supermarket debit. The parts
+/// of a transaction sum to its amount; when a transaction has parts they
+/// replace its row in `transaction_categories`.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TransactionSplits::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TransactionSplits::Id))
+                    .col(string(TransactionSplits::Reference))
+                    .col(integer(TransactionSplits::Position))
+                    .col(double(TransactionSplits::Amount))
+                    .col(integer(TransactionSplits::CategoryId))
+                    .col(text_null(TransactionSplits::Note))
+                    .col(string_null(TransactionSplits::Tag))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-transaction-splits-reference")
+                            .from(TransactionSplits::Table, TransactionSplits::Reference)
+                            .to(AccountTransactions::Table, AccountTransactions::Reference)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-transaction-splits-category-id")
+                            .from(TransactionSplits::Table, TransactionSplits::CategoryId)
+                            .to(Categories::Table, Categories::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-transaction-splits-reference-position")
+                    .table(TransactionSplits::Table)
+                    .col(TransactionSplits::Reference)
+                    .col(TransactionSplits::Position)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TransactionSplits::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TransactionSplits {
+    Table,
+    Id,
+    Reference,
+    Position,
+    Amount,
+    CategoryId,
+    Note,
+    Tag,
+}
+
+#[derive(DeriveIden)]
+enum Categories {
+    Table,
+    Id,
+}
@@ -0,0 +1,115 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20250609_221755_account_transactions::AccountTransactions;
+
+/// Free labels like `vacation-2025` or `tax-relevant`, orthogonal to the
+/// category taxonomy, and a free-text note per transaction. Tag names are
+/// stored lower case.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Tags::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Tags::Id))
+                    .col(string_uniq(Tags::Name))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(TransactionTags::Table)
+                    .if_not_exists()
+                    .col(string(TransactionTags::Reference))
+                    .col(integer(TransactionTags::TagId))
+                    .primary_key(
+                        Index::create()
+                            .col(TransactionTags::Reference)
+                            .col(TransactionTags::TagId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-transaction-tags-reference")
+                            .from(TransactionTags::Table, TransactionTags::Reference)
+                            .to(AccountTransactions::Table, AccountTransactions::Reference)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-transaction-tags-tag-id")
+                            .from(TransactionTags::Table, TransactionTags::TagId)
+                            .to(Tags::Table, Tags::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-transaction-tags-tag-id")
+                    .table(TransactionTags::Table)
+                    .col(TransactionTags::TagId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(TransactionNotes::Table)
+                    .if_not_exists()
+                    .col(string(TransactionNotes::Reference).primary_key())
+                    .col(text(TransactionNotes::Note))
+                    .col(timestamp_with_time_zone(TransactionNotes::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-transaction-notes-reference")
+                            .from(TransactionNotes::Table, TransactionNotes::Reference)
+                            .to(AccountTransactions::Table, AccountTransactions::Reference)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TransactionNotes::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(TransactionTags::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Tags::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Tags {
+    Table,
+    Id,
+    Name,
+}
+
+#[derive(DeriveIden)]
+enum TransactionTags {
+    Table,
+    Reference,
+    TagId,
+}
+
+#[derive(DeriveIden)]
+enum TransactionNotes {
+    Table,
+    Reference,
+    Note,
+    UpdatedAt,
+}
@@ -0,0 +1,77 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20250609_221755_account_transactions::AccountTransactions;
+
+/// Money moved between two of our own accounts: the debit on one and the
+/// credit on the other. Both legs are excluded from income and expense
+/// reports. An `unlinked` pair was rejected by a user and is not matched
+/// again. The IBAN of the other party of a booking is stored from now on,
+/// as it is the strongest hint for a transfer; older rows keep it empty.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AccountTransactions::Table)
+                    .add_column(string(AccountTransactions::CounterpartyIban).default(""))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(InternalTransfers::Table)
+                    .if_not_exists()
+                    .col(pk_auto(InternalTransfers::Id))
+                    .col(string_uniq(InternalTransfers::OutgoingReference))
+                    .col(string_uniq(InternalTransfers::IncomingReference))
+                    .col(string(InternalTransfers::Status))
+                    .col(string(InternalTransfers::Source))
+                    .col(timestamp_with_time_zone(InternalTransfers::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-internal-transfers-outgoing-reference")
+                            .from(InternalTransfers::Table, InternalTransfers::OutgoingReference)
+                            .to(AccountTransactions::Table, AccountTransactions::Reference)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-internal-transfers-incoming-reference")
+                            .from(InternalTransfers::Table, InternalTransfers::IncomingReference)
+                            .to(AccountTransactions::Table, AccountTransactions::Reference)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(InternalTransfers::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AccountTransactions::Table)
+                    .drop_column(AccountTransactions::CounterpartyIban)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum InternalTransfers {
+    Table,
+    Id,
+    OutgoingReference,
+    IncomingReference,
+    Status,
+    Source,
+    CreatedAt,
+}
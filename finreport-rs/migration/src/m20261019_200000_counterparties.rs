@@ -0,0 +1,128 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20250609_221755_account_transactions::AccountTransactions;
+
+/// Canonical payees. A counterparty has rules that claim bookings by the
+/// IBAN or SEPA creditor ID of the other party, by a regex over its name or
+/// by an alias, a name normalized by the webapp resolver; the same rule
+/// can belong to one counterparty only. Each booking points at the
+/// counterparty its rules resolved to.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Counterparties::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Counterparties::Id))
+                    .col(string_uniq(Counterparties::Name))
+                    .col(timestamp_with_time_zone(Counterparties::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(CounterpartyRules::Table)
+                    .if_not_exists()
+                    .col(pk_auto(CounterpartyRules::Id))
+                    .col(integer(CounterpartyRules::CounterpartyId))
+                    .col(string(CounterpartyRules::Kind))
+                    .col(string(CounterpartyRules::Pattern))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-counterparty-rules-counterparty-id")
+                            .from(CounterpartyRules::Table, CounterpartyRules::CounterpartyId)
+                            .to(Counterparties::Table, Counterparties::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-counterparty-rules-kind-pattern")
+                    .table(CounterpartyRules::Table)
+                    .col(CounterpartyRules::Kind)
+                    .col(CounterpartyRules::Pattern)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(TransactionCounterparties::Table)
+                    .if_not_exists()
+                    .col(string(TransactionCounterparties::Reference).primary_key())
+                    .col(integer(TransactionCounterparties::CounterpartyId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-transaction-counterparties-reference")
+                            .from(TransactionCounterparties::Table, TransactionCounterparties::Reference)
+                            .to(AccountTransactions::Table, AccountTransactions::Reference)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-transaction-counterparties-counterparty-id")
+                            .from(TransactionCounterparties::Table, TransactionCounterparties::CounterpartyId)
+                            .to(Counterparties::Table, Counterparties::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-transaction-counterparties-counterparty-id")
+                    .table(TransactionCounterparties::Table)
+                    .col(TransactionCounterparties::CounterpartyId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TransactionCounterparties::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(CounterpartyRules::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Counterparties::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Counterparties {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum CounterpartyRules {
+    Table,
+    Id,
+    CounterpartyId,
+    Kind,
+    Pattern,
+}
+
+#[derive(DeriveIden)]
+enum TransactionCounterparties {
+    Table,
+    Reference,
+    CounterpartyId,
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20250609_221755_account_transactions::AccountTransactions;
+
+/// The fields parsed from the remittance info of a booking: the SEPA
+/// references, the purpose, the ultimate creditor and, for card payments, the
+/// day of the payment and the amount in the original currency. Bookings
+/// imported before this table existed are parsed by the next import.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TransactionRemittance::Table)
+                    .if_not_exists()
+                    .col(string(TransactionRemittance::Reference).primary_key())
+                    .col(string_null(TransactionRemittance::EndToEndReference))
+                    .col(string_null(TransactionRemittance::MandateReference))
+                    .col(string_null(TransactionRemittance::CreditorId))
+                    .col(text(TransactionRemittance::Purpose))
+                    .col(string_null(TransactionRemittance::UltimateCreditor))
+                    .col(date_null(TransactionRemittance::CardDate))
+                    .col(double_null(TransactionRemittance::FxAmount))
+                    .col(string_null(TransactionRemittance::FxCurrency))
+                    .col(double_null(TransactionRemittance::FxRate))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-transaction-remittance-reference")
+                            .from(TransactionRemittance::Table, TransactionRemittance::Reference)
+                            .to(AccountTransactions::Table, AccountTransactions::Reference)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TransactionRemittance::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TransactionRemittance {
+    Table,
+    Reference,
+    EndToEndReference,
+    MandateReference,
+    CreditorId,
+    Purpose,
+    UltimateCreditor,
+    CardDate,
+    FxAmount,
+    FxCurrency,
+    FxRate,
+}
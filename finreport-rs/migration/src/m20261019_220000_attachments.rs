@@ -0,0 +1,74 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20250609_221755_account_transactions::AccountTransactions;
+
+/// Files attached to bookings, such as receipts and invoices. The content
+/// lives in a blob store under its SHA-256, so a file attached twice is
+/// stored once; this table keeps the name and type it was uploaded with.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Attachments::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Attachments::Id))
+                    .col(string(Attachments::Reference))
+                    .col(string_len(Attachments::Sha256, 64))
+                    .col(string(Attachments::FileName))
+                    .col(string(Attachments::ContentType))
+                    .col(big_integer(Attachments::Size))
+                    .col(timestamp_with_time_zone(Attachments::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-attachments-reference")
+                            .from(Attachments::Table, Attachments::Reference)
+                            .to(AccountTransactions::Table, AccountTransactions::Reference)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-attachments-reference")
+                    .table(Attachments::Table)
+                    .col(Attachments::Reference)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-attachments-sha256")
+                    .table(Attachments::Table)
+                    .col(Attachments::Sha256)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Attachments::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Attachments {
+    Table,
+    Id,
+    Reference,
+    Sha256,
+    FileName,
+    ContentType,
+    Size,
+    CreatedAt,
+}
@@ -0,0 +1,75 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// Rules that categorize transactions before the model is asked, e.g. the
+/// rent by the landlord's IBAN or the Rundfunkbeitrag by its creditor ID.
+/// Every criterion that is set has to match; among matching rules the one
+/// with the highest `priority` wins. Amounts are signed, so a range for a
+/// debit is negative.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CategorizationRules::Table)
+                    .if_not_exists()
+                    .col(pk_auto(CategorizationRules::Id))
+                    .col(string(CategorizationRules::Name))
+                    .col(integer(CategorizationRules::Priority).default(0))
+                    .col(integer(CategorizationRules::CategoryId))
+                    .col(string_null(CategorizationRules::Payee))
+                    .col(string_null(CategorizationRules::Iban))
+                    .col(string_null(CategorizationRules::CreditorId))
+                    .col(string_null(CategorizationRules::MandateId))
+                    .col(double_null(CategorizationRules::MinAmount))
+                    .col(double_null(CategorizationRules::MaxAmount))
+                    .col(string_null(CategorizationRules::TransactionType))
+                    .col(string_null(CategorizationRules::RemittancePattern))
+                    .col(timestamp_with_time_zone(CategorizationRules::CreatedAt))
+                    .col(timestamp_with_time_zone(CategorizationRules::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-categorization-rules-category-id")
+                            .from(CategorizationRules::Table, CategorizationRules::CategoryId)
+                            .to(Categories::Table, Categories::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CategorizationRules::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CategorizationRules {
+    Table,
+    Id,
+    Name,
+    Priority,
+    CategoryId,
+    Payee,
+    Iban,
+    CreditorId,
+    MandateId,
+    MinAmount,
+    MaxAmount,
+    TransactionType,
+    RemittancePattern,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Categories {
+    Table,
+    Id,
+}
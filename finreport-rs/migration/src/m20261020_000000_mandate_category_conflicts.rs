@@ -0,0 +1,94 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20250609_221755_account_transactions::AccountTransactions;
+
+/// Debits a user put into another category than the one their mandate maps
+/// to in `mandate_categories`. The mapping stays until the conflict is
+/// resolved, either by remapping the mandate to the user's category or by
+/// keeping it and the debit as an exception.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MandateCategoryConflicts::Table)
+                    .if_not_exists()
+                    .col(pk_auto(MandateCategoryConflicts::Id))
+                    .col(string(MandateCategoryConflicts::CreditorMandateId))
+                    .col(string(MandateCategoryConflicts::Reference))
+                    .col(integer(MandateCategoryConflicts::CategoryId))
+                    .col(string(MandateCategoryConflicts::Status))
+                    .col(timestamp_with_time_zone(MandateCategoryConflicts::DetectedAt))
+                    .col(timestamp_with_time_zone_null(MandateCategoryConflicts::ResolvedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-mandate-category-conflicts-mandate")
+                            .from(
+                                MandateCategoryConflicts::Table,
+                                MandateCategoryConflicts::CreditorMandateId,
+                            )
+                            .to(MandateCategories::Table, MandateCategories::CreditorMandateId)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-mandate-category-conflicts-reference")
+                            .from(MandateCategoryConflicts::Table, MandateCategoryConflicts::Reference)
+                            .to(AccountTransactions::Table, AccountTransactions::Reference)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-mandate-category-conflicts-category-id")
+                            .from(MandateCategoryConflicts::Table, MandateCategoryConflicts::CategoryId)
+                            .to(Categories::Table, Categories::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-mandate-category-conflicts-mandate")
+                    .table(MandateCategoryConflicts::Table)
+                    .col(MandateCategoryConflicts::CreditorMandateId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MandateCategoryConflicts::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MandateCategoryConflicts {
+    Table,
+    Id,
+    CreditorMandateId,
+    Reference,
+    CategoryId,
+    Status,
+    DetectedAt,
+    ResolvedAt,
+}
+
+#[derive(DeriveIden)]
+enum MandateCategories {
+    Table,
+    CreditorMandateId,
+}
+
+#[derive(DeriveIden)]
+enum Categories {
+    Table,
+    Id,
+}
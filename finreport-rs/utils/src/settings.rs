@@ -17,3 +17,40 @@ pub struct Settings {
 }
 
 
+
+/// Subset of [`Settings`] for tools that only talk to the database and have no
+/// business holding Comdirect credentials.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DatabaseSettings {
+    pub database_url: SecretString,
+}
+
+/// Where attachments are stored and how large they may be. Files go to
+/// `attachment_dir` unless an S3-compatible endpoint such as MinIO is set,
+/// in which case the bucket, access key and secret key are required too.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AttachmentSettings {
+    #[serde(default = "default_attachment_dir")]
+    pub attachment_dir: String,
+    /// Largest accepted upload in bytes.
+    #[serde(default = "default_attachment_max_bytes")]
+    pub attachment_max_bytes: usize,
+    pub attachment_s3_endpoint: Option<String>,
+    pub attachment_s3_bucket: Option<String>,
+    #[serde(default = "default_attachment_s3_region")]
+    pub attachment_s3_region: String,
+    pub attachment_s3_access_key: Option<String>,
+    pub attachment_s3_secret_key: Option<SecretString>,
+}
+
+fn default_attachment_dir() -> String {
+    "attachments".to_string()
+}
+
+fn default_attachment_max_bytes() -> usize {
+    10 * 1024 * 1024
+}
+
+fn default_attachment_s3_region() -> String {
+    "us-east-1".to_string()
+}
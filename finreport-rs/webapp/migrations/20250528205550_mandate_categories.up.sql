@@ -1,6 +0,0 @@
-create table mandate_categories
-(
-    creditor_mandate_id varchar primary key,
-    category_id         int not null,
-    description         varchar
-);
@@ -1,3 +0,0 @@
--- Add up migration script here
-alter table categories
-    add constraint unique_category_subcategory unique (category, subcategory);
\ No newline at end of file
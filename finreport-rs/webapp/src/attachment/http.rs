@@ -0,0 +1,117 @@
+use crate::attachment::{AttachmentError, Attachments};
+use crate::service::ServiceError;
+use actix_web::body::{to_bytes_limited, BodyStream};
+use actix_web::http::header::{self, ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+use entity::entities::attachments;
+use sea_orm::DatabaseConnection;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::error;
+
+#[derive(Deserialize)]
+pub struct UploadQuery {
+    /// Name to show and download the file as; the path is dropped.
+    file_name: String,
+}
+
+/// `POST /transactions/{reference}/attachments?file_name=receipt.pdf` with
+/// the file as the request body and its type as `Content-Type`. Answers
+/// `201` with the attachment as JSON, `413` for files over the limit and
+/// `415` for anything but a PDF or an image.
+#[post("/transactions/{reference}/attachments")]
+pub async fn upload(
+    req: HttpRequest,
+    conn: web::Data<DatabaseConnection>,
+    attachments: web::Data<Attachments>,
+    reference: web::Path<String>,
+    query: web::Query<UploadQuery>,
+    payload: web::Payload,
+) -> impl Responder {
+    let too_large = |size: usize| AttachmentError::TooLarge {
+        size,
+        limit: attachments.max_bytes,
+    };
+    let declared_length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if let Some(length) = declared_length.filter(|length| *length > attachments.max_bytes) {
+        return error_response(too_large(length));
+    }
+    let content = match to_bytes_limited(BodyStream::new(payload), attachments.max_bytes).await {
+        Ok(Ok(content)) => content,
+        Ok(Err(e)) => return HttpResponse::BadRequest().body(e.to_string()),
+        Err(_) => return error_response(too_large(attachments.max_bytes + 1)),
+    };
+    let declared_type = req.headers().get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+    match attachments.attach(&conn, &reference, &query.file_name, declared_type, &content).await {
+        Ok(attachment) => HttpResponse::Created().json(to_json(&attachment)),
+        Err(e) => error_response(e),
+    }
+}
+
+/// `GET /attachments/{id}`, the file with the type and name it was attached
+/// with. Browsers show PDFs and images inline.
+#[get("/attachments/{id}")]
+pub async fn download(
+    conn: web::Data<DatabaseConnection>,
+    attachments: web::Data<Attachments>,
+    id: web::Path<i32>,
+) -> impl Responder {
+    match attachments.open(&conn, *id).await {
+        Ok((attachment, content)) => HttpResponse::Ok()
+            .content_type(attachment.content_type)
+            .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+            .insert_header((header::ETAG, format!("\"{}\"", attachment.sha256)))
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Inline,
+                parameters: vec![DispositionParam::Filename(attachment.file_name)],
+            })
+            .body(content),
+        Err(e) => error_response(e),
+    }
+}
+
+/// `DELETE /attachments/{id}`; answers `204`.
+#[delete("/attachments/{id}")]
+pub async fn remove(
+    conn: web::Data<DatabaseConnection>,
+    attachments: web::Data<Attachments>,
+    id: web::Path<i32>,
+) -> impl Responder {
+    match attachments.remove(&conn, *id).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn to_json(attachment: &attachments::Model) -> serde_json::Value {
+    json!({
+        "id": attachment.id,
+        "reference": attachment.reference,
+        "fileName": attachment.file_name,
+        "contentType": attachment.content_type,
+        "size": attachment.size,
+        "sha256": attachment.sha256,
+        "createdAt": attachment.created_at.to_rfc3339(),
+        "url": format!("/attachments/{}", attachment.id),
+    })
+}
+
+fn error_response(e: AttachmentError) -> HttpResponse {
+    match e {
+        AttachmentError::TooLarge { .. } => HttpResponse::PayloadTooLarge().body(e.to_string()),
+        AttachmentError::UnsupportedType(_) => HttpResponse::UnsupportedMediaType().body(e.to_string()),
+        AttachmentError::Empty => HttpResponse::BadRequest().body(e.to_string()),
+        AttachmentError::Db(ServiceError::AttachmentNotFound(_))
+        | AttachmentError::Db(ServiceError::InvalidTransaction { .. }) => {
+            HttpResponse::NotFound().body(e.to_string())
+        }
+        e => {
+            error!(%e, "[attachment] request failed");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
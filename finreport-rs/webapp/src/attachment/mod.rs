@@ -0,0 +1,268 @@
+//! Files attached to transactions: receipts for warranty claims, invoices for
+//! the tax return, proofs for reimbursements. The content is addressed by its
+//! SHA-256 and kept in a [`Store`], on local disk or in an S3-compatible
+//! bucket, so a file attached to several bookings is stored once; the
+//! database holds the name, type and size each attachment was made with.
+
+pub mod http;
+mod store;
+
+pub use store::{LocalStore, S3Store, Store};
+
+use crate::service::{AttachmentInput, ChangeSource, Mutation, Query, ServiceError};
+use entity::entities::attachments;
+use sea_orm::DbConn;
+use sha2::{Digest, Sha256};
+use std::fmt::{Display, Formatter};
+use utils::settings::AttachmentSettings;
+
+/// Type of a file whose content starts with the given bytes. PDFs and the
+/// image formats of scanners and phone cameras; anything else is refused.
+const MAGIC: [(&[u8], &str); 6] = [
+    (b"%PDF-", "application/pdf"),
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"RIFF", "image/webp"),
+];
+
+/// HEIC photos have their brand after the box size.
+const HEIC_BRANDS: [&[u8]; 4] = [b"ftypheic", b"ftypheix", b"ftypmif1", b"ftypmsf1"];
+
+/// Longest file name kept, in characters.
+const MAX_FILE_NAME: usize = 200;
+
+#[derive(Debug)]
+pub enum AttachmentError {
+    Db(ServiceError),
+    Io(std::io::Error),
+    /// The S3 endpoint failed or answered with an error status.
+    Storage(String),
+    /// Content the store does not have, though an attachment points at it.
+    MissingContent(String),
+    Empty,
+    TooLarge {
+        size: usize,
+        limit: usize,
+    },
+    /// Content that is not an accepted type, or not the declared one.
+    UnsupportedType(String),
+    InvalidSettings(String),
+}
+
+impl Display for AttachmentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttachmentError::Db(e) => write!(f, "{e}"),
+            AttachmentError::Io(e) => write!(f, "attachment storage failed: {e}"),
+            AttachmentError::Storage(message) => write!(f, "attachment storage failed: {message}"),
+            AttachmentError::MissingContent(sha256) => write!(f, "attachment content {sha256} is missing"),
+            AttachmentError::Empty => write!(f, "the file is empty"),
+            AttachmentError::TooLarge { size, limit } => {
+                write!(f, "the file has {size} bytes, at most {limit} are accepted")
+            }
+            AttachmentError::UnsupportedType(message) => write!(f, "unsupported file type: {message}"),
+            AttachmentError::InvalidSettings(message) => write!(f, "invalid attachment settings: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for AttachmentError {}
+
+impl From<ServiceError> for AttachmentError {
+    fn from(value: ServiceError) -> Self {
+        AttachmentError::Db(value)
+    }
+}
+
+impl From<std::io::Error> for AttachmentError {
+    fn from(value: std::io::Error) -> Self {
+        AttachmentError::Io(value)
+    }
+}
+
+impl From<reqwest::Error> for AttachmentError {
+    fn from(value: reqwest::Error) -> Self {
+        AttachmentError::Storage(value.to_string())
+    }
+}
+
+/// The store and the upload limit.
+pub struct Attachments {
+    pub store: Store,
+    /// Largest accepted file in bytes.
+    pub max_bytes: usize,
+}
+
+impl Attachments {
+    pub fn from_settings(settings: &AttachmentSettings) -> Result<Self, AttachmentError> {
+        Ok(Attachments {
+            store: Store::from_settings(settings)?,
+            max_bytes: settings.attachment_max_bytes,
+        })
+    }
+
+    /// Store `content` and attach it to the transaction `reference`. The
+    /// type is taken from the content; a `declared_type` other than
+    /// `application/octet-stream` has to agree with it.
+    pub async fn attach(
+        &self,
+        conn: &DbConn,
+        reference: &str,
+        file_name: &str,
+        declared_type: Option<&str>,
+        content: &[u8],
+    ) -> Result<attachments::Model, AttachmentError> {
+        if content.is_empty() {
+            return Err(AttachmentError::Empty);
+        }
+        if content.len() > self.max_bytes {
+            return Err(AttachmentError::TooLarge {
+                size: content.len(),
+                limit: self.max_bytes,
+            });
+        }
+        let content_type = content_type(content, declared_type)?;
+        let sha256 = hex::encode(Sha256::digest(content));
+        self.store.put(&sha256, content).await?;
+        let input = AttachmentInput {
+            reference: reference.to_string(),
+            sha256: sha256.clone(),
+            file_name: file_name_of(file_name, content_type),
+            content_type: content_type.to_string(),
+            size: content.len() as i64,
+        };
+        match Mutation::add_attachment(conn, input, ChangeSource::User).await {
+            Ok(attachment) => Ok(attachment),
+            Err(e) => {
+                self.delete_unused(conn, &sha256).await?;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// The attachment `id` with its content.
+    pub async fn open(
+        &self,
+        conn: &DbConn,
+        id: i32,
+    ) -> Result<(attachments::Model, Vec<u8>), AttachmentError> {
+        let attachment = Query::find_attachment(conn, id).await?;
+        let content = self.store.get(&attachment.sha256).await?;
+        Ok((attachment, content))
+    }
+
+    /// Detach the attachment `id`; its content goes with the last attachment
+    /// pointing at it.
+    pub async fn remove(&self, conn: &DbConn, id: i32) -> Result<attachments::Model, AttachmentError> {
+        let attachment = Mutation::delete_attachment(conn, id, ChangeSource::User).await?;
+        self.delete_unused(conn, &attachment.sha256).await?;
+        Ok(attachment)
+    }
+
+    async fn delete_unused(&self, conn: &DbConn, sha256: &str) -> Result<(), AttachmentError> {
+        if !Query::attachment_content_in_use(conn, sha256).await? {
+            self.store.delete(sha256).await?;
+        }
+        Ok(())
+    }
+}
+
+/// The accepted type `content` has. Clients often send a generic type, so
+/// only a specific `declared` type has to match.
+fn content_type(content: &[u8], declared: Option<&str>) -> Result<&'static str, AttachmentError> {
+    let sniffed = MAGIC
+        .iter()
+        .find(|(magic, content_type)| {
+            // RIFF holds audio and video too.
+            let webp = *content_type != "image/webp" || content.get(8..12) == Some(b"WEBP");
+            content.starts_with(magic) && webp
+        })
+        .map(|(_, content_type)| *content_type)
+        .or_else(|| HEIC_BRANDS.iter().any(|brand| content.get(4..12) == Some(brand)).then_some("image/heic"))
+        .ok_or_else(|| AttachmentError::UnsupportedType("expected a PDF or an image".to_string()))?;
+    let declared = declared.map(|d| d.split(';').next().unwrap_or_default().trim().to_lowercase());
+    match declared.as_deref() {
+        None | Some("" | "application/octet-stream") => Ok(sniffed),
+        Some("image/jpg") if sniffed == "image/jpeg" => Ok(sniffed),
+        Some(declared) if declared == sniffed => Ok(sniffed),
+        Some(declared) => Err(AttachmentError::UnsupportedType(format!(
+            "declared as {declared}, but the content is {sniffed}"
+        ))),
+    }
+}
+
+/// The last path component of an uploaded name, shortened; named after the
+/// type when nothing is left.
+fn file_name_of(name: &str, content_type: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name.chars().filter(|c| !c.is_control()).take(MAX_FILE_NAME).collect();
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        let extension = content_type.rsplit('/').next().unwrap_or("bin");
+        return format!("attachment.{extension}");
+    }
+    name.to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::attachment::{content_type, file_name_of, AttachmentError, Attachments, LocalStore, Store};
+    use crate::service::test::{account, db, transaction};
+    use crate::service::{ChangeSource, Mutation, Query};
+
+    const PDF: &[u8] = b"%PDF-1.7\n%receipt";
+
+    #[test]
+    fn test_content_type_from_content() {
+        assert_eq!(content_type(PDF, None).unwrap(), "application/pdf");
+        assert_eq!(content_type(PDF, Some("application/octet-stream")).unwrap(), "application/pdf");
+        assert_eq!(content_type(b"\xff\xd8\xff\xe0rest", Some("image/jpg")).unwrap(), "image/jpeg");
+        assert_eq!(content_type(b"RIFF\0\0\0\0WEBPVP8 ", None).unwrap(), "image/webp");
+        assert_eq!(content_type(b"\0\0\0\x18ftypheic", None).unwrap(), "image/heic");
+        let mismatch = content_type(PDF, Some("image/png; charset=binary"));
+        assert!(matches!(mismatch, Err(AttachmentError::UnsupportedType(_))));
+        assert!(content_type(b"RIFF\0\0\0\0WAVEfmt ", None).is_err());
+        assert!(content_type(b"<html>", Some("text/html")).is_err());
+        assert_eq!(file_name_of("C:\\Scans\\../quittung.pdf", "application/pdf"), "quittung.pdf");
+        assert_eq!(file_name_of("", "image/png"), "attachment.png");
+    }
+
+    #[tokio::test]
+    async fn test_attach_stores_content_once() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        for reference in ["r1", "r2"] {
+            let input = transaction(reference, "A1", -20.0);
+            Mutation::upsert_transaction(&db, input, ChangeSource::Import).await.unwrap();
+        }
+        let dir = std::env::temp_dir().join(format!("attachments-{}", uuid::Uuid::new_v4()));
+        let attachments = Attachments {
+            store: Store::Local(LocalStore::new(&dir)),
+            max_bytes: 64,
+        };
+
+        let first = attachments.attach(&db, "r1", "receipt.pdf", None, PDF).await.unwrap();
+        let second = attachments.attach(&db, "r2", "copy.pdf", Some("application/pdf"), PDF).await.unwrap();
+        assert_eq!(first.sha256, second.sha256);
+        let blob = dir.join(&first.sha256[..2]).join(&first.sha256);
+        assert!(blob.exists());
+        let (attachment, content) = attachments.open(&db, second.id).await.unwrap();
+        assert_eq!((attachment.file_name.as_str(), content.as_slice()), ("copy.pdf", PDF));
+        let by_reference = Query::attachments_by_reference(&db).await.unwrap();
+        assert_eq!(by_reference["r1"].len(), 1);
+
+        let unknown = attachments.attach(&db, "nope", "receipt.pdf", None, PDF).await;
+        assert!(matches!(unknown, Err(AttachmentError::Db(_))));
+        let large = attachments.attach(&db, "r1", "large.pdf", None, &[b'%'; 65]).await;
+        assert!(matches!(large, Err(AttachmentError::TooLarge { size: 65, limit: 64 })));
+
+        // The content stays while another attachment points at it.
+        attachments.remove(&db, first.id).await.unwrap();
+        assert!(blob.exists());
+        attachments.remove(&db, second.id).await.unwrap();
+        assert!(!blob.exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::attachment::AttachmentError;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{Method, StatusCode, Url};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use utils::settings::AttachmentSettings;
+
+/// Where attachment content lives, keyed by its hex SHA-256.
+pub enum Store {
+    Local(LocalStore),
+    S3(S3Store),
+}
+
+impl Store {
+    /// An S3 store when an endpoint is configured, else a local one.
+    pub fn from_settings(settings: &AttachmentSettings) -> Result<Self, AttachmentError> {
+        let Some(endpoint) = &settings.attachment_s3_endpoint else {
+            return Ok(Store::Local(LocalStore::new(&settings.attachment_dir)));
+        };
+        let missing = |name: &str| AttachmentError::InvalidSettings(format!("{name} is required for S3"));
+        let secret_key = settings.attachment_s3_secret_key.clone();
+        Ok(Store::S3(S3Store::new(
+            endpoint,
+            settings.attachment_s3_bucket.as_deref().ok_or_else(|| missing("attachment_s3_bucket"))?,
+            &settings.attachment_s3_region,
+            settings.attachment_s3_access_key.as_deref().ok_or_else(|| missing("attachment_s3_access_key"))?,
+            secret_key.ok_or_else(|| missing("attachment_s3_secret_key"))?,
+        )?))
+    }
+
+    /// Store `content` under `sha256`; content that is there already is kept.
+    pub async fn put(&self, sha256: &str, content: &[u8]) -> Result<(), AttachmentError> {
+        check_key(sha256)?;
+        match self {
+            Store::Local(store) => store.put(sha256, content).await,
+            Store::S3(store) => store.put(sha256, content).await,
+        }
+    }
+
+    pub async fn get(&self, sha256: &str) -> Result<Vec<u8>, AttachmentError> {
+        check_key(sha256)?;
+        match self {
+            Store::Local(store) => store.get(sha256).await,
+            Store::S3(store) => store.get(sha256).await,
+        }
+    }
+
+    /// Delete the content under `sha256`, if there is any.
+    pub async fn delete(&self, sha256: &str) -> Result<(), AttachmentError> {
+        check_key(sha256)?;
+        match self {
+            Store::Local(store) => store.delete(sha256).await,
+            Store::S3(store) => store.delete(sha256).await,
+        }
+    }
+}
+
+/// Keys become file names and URL paths, so only digests are accepted.
+fn check_key(sha256: &str) -> Result<(), AttachmentError> {
+    if sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(AttachmentError::Storage(format!("invalid content key `{sha256}`")))
+    }
+}
+
+/// Files in a directory, spread over subdirectories named after the first
+/// two hex digits.
+pub struct LocalStore {
+    dir: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        LocalStore {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn path(&self, sha256: &str) -> PathBuf {
+        self.dir.join(&sha256[..2]).join(sha256)
+    }
+
+    async fn put(&self, sha256: &str, content: &[u8]) -> Result<(), AttachmentError> {
+        let path = self.path(sha256);
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Readers never see a partly written file.
+        let partial = path.with_extension(format!("{}.part", uuid::Uuid::new_v4()));
+        tokio::fs::write(&partial, content).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(())
+    }
+
+    async fn get(&self, sha256: &str) -> Result<Vec<u8>, AttachmentError> {
+        match tokio::fs::read(self.path(sha256)).await {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(AttachmentError::MissingContent(sha256.to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, sha256: &str) -> Result<(), AttachmentError> {
+        match tokio::fs::remove_file(self.path(sha256)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Objects in a bucket of an S3-compatible service such as MinIO, addressed
+/// path-style (`{endpoint}/{bucket}/{sha256}`) and signed with AWS
+/// Signature Version 4.
+pub struct S3Store {
+    client: reqwest::Client,
+    bucket_url: Url,
+    region: String,
+    access_key: String,
+    secret_key: SecretString,
+}
+
+impl S3Store {
+    pub fn new(
+        endpoint: &str,
+        bucket: &str,
+        region: &str,
+        access_key: &str,
+        secret_key: SecretString,
+    ) -> Result<Self, AttachmentError> {
+        let invalid = |e: String| AttachmentError::InvalidSettings(format!("S3 endpoint `{endpoint}`: {e}"));
+        let bucket_url = Url::parse(&format!("{}/{bucket}/", endpoint.trim_end_matches('/')))
+            .map_err(|e| invalid(e.to_string()))?;
+        if bucket_url.host_str().is_none() {
+            return Err(invalid("no host".to_string()));
+        }
+        Ok(S3Store {
+            client: reqwest::Client::new(),
+            bucket_url,
+            region: region.to_string(),
+            access_key: access_key.to_string(),
+            secret_key,
+        })
+    }
+
+    async fn put(&self, sha256: &str, content: &[u8]) -> Result<(), AttachmentError> {
+        self.send(Method::PUT, sha256, content.to_vec()).await?;
+        Ok(())
+    }
+
+    async fn get(&self, sha256: &str) -> Result<Vec<u8>, AttachmentError> {
+        match self.send(Method::GET, sha256, Vec::new()).await? {
+            Some(content) => Ok(content),
+            None => Err(AttachmentError::MissingContent(sha256.to_string())),
+        }
+    }
+
+    async fn delete(&self, sha256: &str) -> Result<(), AttachmentError> {
+        self.send(Method::DELETE, sha256, Vec::new()).await?;
+        Ok(())
+    }
+
+    /// The response body, `None` for a missing object.
+    async fn send(
+        &self,
+        method: Method,
+        sha256: &str,
+        body: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, AttachmentError> {
+        let url = self.bucket_url.join(sha256).map_err(|e| AttachmentError::Storage(e.to_string()))?;
+        let payload_sha256 = hex::encode(Sha256::digest(&body));
+        let signed = self.sign(method.as_str(), &url, &payload_sha256, Utc::now());
+        let response = self
+            .client
+            .request(method, url)
+            .header("x-amz-content-sha256", payload_sha256)
+            .header("x-amz-date", signed.date)
+            .header("authorization", signed.authorization)
+            .body(body)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(response.bytes().await?.to_vec())),
+            status => {
+                let message = response.text().await.unwrap_or_default();
+                Err(AttachmentError::Storage(format!("S3 answered {status}: {message}")))
+            }
+        }
+    }
+
+    fn sign(&self, method: &str, url: &Url, payload_sha256: &str, now: DateTime<Utc>) -> Signed {
+        let date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let host = match url.port() {
+            Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let canonical_request = format!(
+            "{method}\n{}\n\nhost:{host}\nx-amz-content-sha256:{payload_sha256}\nx-amz-date:{date}\n\n\
+             {SIGNED_HEADERS}\n{payload_sha256}",
+            url.path()
+        );
+        let scope = format!("{}/{}/s3/aws4_request", &date[..8], self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{date}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = signing_key(self.secret_key.expose_secret(), &date[..8], &self.region, "s3");
+        let signature = hex::encode(hmac(&key, &string_to_sign));
+        Signed {
+            authorization: format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={SIGNED_HEADERS}, \
+                 Signature={signature}",
+                self.access_key
+            ),
+            date,
+        }
+    }
+}
+
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+struct Signed {
+    /// `x-amz-date`
+    date: String,
+    authorization: String,
+}
+
+fn hmac(key: &[u8], message: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// The key of a day, region and service, derived from the secret key.
+fn signing_key(secret_key: &str, day: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac(format!("AWS4{secret_key}").as_bytes(), day);
+    let key = hmac(&key, region);
+    let key = hmac(&key, service);
+    hmac(&key, "aws4_request")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::attachment::store::{signing_key, S3Store};
+    use chrono::{TimeZone, Utc};
+    use reqwest::Url;
+
+    #[test]
+    fn test_signature_v4() {
+        // The example of the AWS documentation on deriving the signing key.
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex::encode(key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+
+        let store = S3Store::new("http://localhost:9000/", "receipts", "us-east-1", "minio", "secret".into())
+            .unwrap();
+        let url = Url::parse("http://localhost:9000/receipts/ab").unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let signed = store.sign("GET", &url, "UNSIGNED-PAYLOAD", now);
+        assert_eq!(signed.date, "20250601T120000Z");
+        assert!(signed.authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=minio/20250601/us-east-1/s3/aws4_request, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature="
+        ));
+        assert_eq!(store.bucket_url.as_str(), "http://localhost:9000/receipts/");
+    }
+}
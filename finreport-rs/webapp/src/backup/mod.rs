@@ -0,0 +1,547 @@
+//! Backups of the whole database as a portable archive: a zip with one JSON
+//! lines file per table, a manifest naming the format version and the
+//! migrations the data was written with, and SHA-256 checksums of every
+//! file. Rows are plain JSON, so an archive taken from Postgres restores
+//! into SQLite and the other way round; an archive from an older schema is
+//! restored at its own version and then migrated like any database.
+//!
+//! Attachments are backed up as metadata only; their content stays in the
+//! attachment store.
+
+use chrono::Utc;
+use migration::{Migrator, MigratorTrait, SchemaManager};
+use sea_orm::sea_query::{Alias, Expr, Query as SqlQuery, SimpleExpr};
+use sea_orm::{
+    ConnectionTrait, DatabaseTransaction, DbBackend, DbConn, DbErr, FromQueryResult, IsolationLevel,
+    JsonValue, Statement, TransactionTrait, Value,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Seek, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+pub const FORMAT: &str = "finreport-backup";
+
+/// Version of the archive layout, not of the schema; the schema version is
+/// the list of migrations in the manifest.
+pub const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const CHECKSUMS: &str = "SHA256SUMS";
+
+/// Tables holding user data, parents before the tables referencing them.
+pub const TABLES: [&str; 25] = [
+    "account",
+    "account_balance",
+    "account_transactions",
+    "paypal_transactions",
+    "categories",
+    "transaction_categories",
+    "mandate_categories",
+    "mandate_category_conflicts",
+    "categorization_rules",
+    "transaction_history",
+    "import_runs",
+    "import_run_accounts",
+    "daily_balances",
+    "data_findings",
+    "saved_views",
+    "transaction_splits",
+    "tags",
+    "transaction_tags",
+    "transaction_notes",
+    "internal_transfers",
+    "counterparties",
+    "counterparty_rules",
+    "transaction_counterparties",
+    "transaction_remittance",
+    "attachments",
+];
+
+/// Filled by the migrations; an empty database may already have them.
+const SEEDED: [&str; 1] = ["categories"];
+
+/// Stay below SQLite's limit of bind parameters per statement.
+const MAX_VALUES_PER_INSERT: usize = 900;
+
+#[derive(Debug)]
+pub enum BackupError {
+    Db(DbErr),
+    Io(std::io::Error),
+    Zip(zip::result::ZipError),
+    Json(serde_json::Error),
+    /// Not an archive of this format, or one with missing or altered files.
+    InvalidArchive(String),
+    /// Restoring would mix the archive with data already there.
+    NotEmpty(String),
+    /// The archive was written by a version with migrations this one lacks.
+    IncompatibleSchema(String),
+}
+
+impl Display for BackupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackupError::Db(e) => write!(f, "{e}"),
+            BackupError::Io(e) => write!(f, "{e}"),
+            BackupError::Zip(e) => write!(f, "invalid archive: {e}"),
+            BackupError::Json(e) => write!(f, "invalid archive: {e}"),
+            BackupError::InvalidArchive(message) => write!(f, "invalid archive: {message}"),
+            BackupError::NotEmpty(table) => {
+                write!(f, "table {table} has rows, restore into a new database")
+            }
+            BackupError::IncompatibleSchema(message) => write!(f, "incompatible schema: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<DbErr> for BackupError {
+    fn from(value: DbErr) -> Self {
+        BackupError::Db(value)
+    }
+}
+
+impl From<std::io::Error> for BackupError {
+    fn from(value: std::io::Error) -> Self {
+        BackupError::Io(value)
+    }
+}
+
+impl From<zip::result::ZipError> for BackupError {
+    fn from(value: zip::result::ZipError) -> Self {
+        BackupError::Zip(value)
+    }
+}
+
+impl From<serde_json::Error> for BackupError {
+    fn from(value: serde_json::Error) -> Self {
+        BackupError::Json(value)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: String,
+    pub format_version: u32,
+    /// RFC 3339
+    pub created_at: String,
+    /// Backend the archive was taken from, `postgres` or `sqlite`.
+    pub backend: String,
+    /// Names of the migrations applied to the database, in order.
+    pub migrations: Vec<String>,
+    pub tables: Vec<TableEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableEntry {
+    pub name: String,
+    /// Path of the JSON lines file in the archive.
+    pub file: String,
+    pub rows: usize,
+    pub sha256: String,
+}
+
+/// Write every table of `conn` into a new archive.
+pub async fn backup<W: Write + Seek>(conn: &DbConn, writer: W) -> Result<Manifest, BackupError> {
+    // All tables are read in one transaction, so the archive is a consistent
+    // snapshot even while an import runs. SQLite transactions read from one
+    // snapshot anyway; Postgres needs REPEATABLE READ for that.
+    let txn = match conn.get_database_backend() {
+        DbBackend::Postgres => conn.begin_with_config(Some(IsolationLevel::RepeatableRead), None).await?,
+        _ => conn.begin().await?,
+    };
+    let migrations = applied_migrations(&txn).await?;
+    let manager = SchemaManager::new(&txn);
+    let mut zip = ZipWriter::new(writer);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut tables = Vec::new();
+    for table in TABLES {
+        // Archives of older schemas lack the later tables.
+        if !manager.has_table(table).await? {
+            continue;
+        }
+        let select = format!("SELECT * FROM \"{table}\" ORDER BY 1");
+        let statement = Statement::from_string(conn.get_database_backend(), select);
+        let rows = JsonValue::find_by_statement(statement).all(&txn).await?;
+        let mut content = Vec::new();
+        for row in &rows {
+            serde_json::to_writer(&mut content, row)?;
+            content.push(b'\n');
+        }
+        let file = format!("tables/{table}.jsonl");
+        zip.start_file(file.as_str(), options)?;
+        zip.write_all(&content)?;
+        tables.push(TableEntry {
+            name: table.to_string(),
+            file,
+            rows: rows.len(),
+            sha256: hex::encode(Sha256::digest(&content)),
+        });
+    }
+    txn.commit().await?;
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        format_version: FORMAT_VERSION,
+        created_at: Utc::now().to_rfc3339(),
+        backend: backend_name(conn.get_database_backend()).to_string(),
+        migrations,
+        tables,
+    };
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(&manifest_json)?;
+    // In the format of sha256sum, to check an unpacked archive by hand.
+    let mut checksums = format!("{}  {MANIFEST}\n", hex::encode(Sha256::digest(&manifest_json)));
+    for table in &manifest.tables {
+        checksums.push_str(&format!("{}  {}\n", table.sha256, table.file));
+    }
+    zip.start_file(CHECKSUMS, options)?;
+    zip.write_all(checksums.as_bytes())?;
+    zip.finish()?;
+    Ok(manifest)
+}
+
+/// Restore an archive into `conn`, which must not hold any data yet. The
+/// database is brought to the schema of the archive, filled, and then
+/// migrated to the current schema.
+pub async fn restore<R: Read + Seek>(conn: &DbConn, reader: R) -> Result<Manifest, BackupError> {
+    let mut archive = ZipArchive::new(reader)?;
+    let checksums = String::from_utf8(read_file(&mut archive, CHECKSUMS)?)
+        .map_err(|_| BackupError::InvalidArchive(format!("{CHECKSUMS} is not text")))?;
+    let checksums: HashMap<&str, &str> = checksums
+        .lines()
+        .filter_map(|line| line.split_once("  "))
+        .map(|(sum, file)| (file, sum))
+        .collect();
+    let manifest_json = read_checked(&mut archive, &checksums, MANIFEST)?;
+    let manifest: Manifest = serde_json::from_slice(&manifest_json)?;
+    if manifest.format != FORMAT {
+        return Err(BackupError::InvalidArchive(format!("unknown format `{}`", manifest.format)));
+    }
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(BackupError::InvalidArchive(format!(
+            "format version {} is newer than the supported {FORMAT_VERSION}",
+            manifest.format_version
+        )));
+    }
+    if let Some(table) = manifest.tables.iter().find(|t| !TABLES.contains(&t.name.as_str())) {
+        return Err(BackupError::InvalidArchive(format!("unknown table `{}`", table.name)));
+    }
+
+    let known: Vec<String> = Migrator::migrations().iter().map(|m| m.name().to_string()).collect();
+    if !known.starts_with(&manifest.migrations) {
+        return Err(BackupError::IncompatibleSchema(
+            "the archive was written by a newer version of finreport".to_string(),
+        ));
+    }
+    ensure_empty(conn).await?;
+    align_schema(conn, manifest.migrations.len()).await?;
+
+    let txn = conn.begin().await?;
+    for table in TABLES {
+        let Some(entry) = manifest.tables.iter().find(|t| t.name == table) else {
+            continue;
+        };
+        let content = read_checked(&mut archive, &checksums, &entry.file)?;
+        let rows = content
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_slice)
+            .collect::<Result<Vec<serde_json::Map<String, JsonValue>>, _>>()?;
+        if rows.len() != entry.rows {
+            return Err(BackupError::InvalidArchive(format!(
+                "{} has {} rows, the manifest lists {}",
+                entry.file,
+                rows.len(),
+                entry.rows
+            )));
+        }
+        insert_rows(&txn, table, &rows).await?;
+    }
+    txn.commit().await?;
+
+    Migrator::up(conn, None).await?;
+    Ok(manifest)
+}
+
+async fn applied_migrations<C: ConnectionTrait>(conn: &C) -> Result<Vec<String>, DbErr> {
+    let applied = Migrator::get_applied_migrations(conn).await?;
+    Ok(applied.iter().map(|m| m.name().to_string()).collect())
+}
+
+async fn ensure_empty(conn: &DbConn) -> Result<(), BackupError> {
+    let manager = SchemaManager::new(conn);
+    for table in TABLES.iter().filter(|t| !SEEDED.contains(t)) {
+        if !manager.has_table(*table).await? {
+            continue;
+        }
+        let select = format!("SELECT 1 AS found FROM \"{table}\" LIMIT 1");
+        let statement = Statement::from_string(conn.get_database_backend(), select);
+        if conn.query_one(statement).await?.is_some() {
+            return Err(BackupError::NotEmpty(table.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Apply or revert migrations until exactly the first `version` are applied.
+async fn align_schema(conn: &DbConn, version: usize) -> Result<(), BackupError> {
+    let applied = applied_migrations(conn).await?;
+    let known: Vec<String> = Migrator::migrations().iter().map(|m| m.name().to_string()).collect();
+    if !known.starts_with(&applied) {
+        return Err(BackupError::IncompatibleSchema(
+            "the database was migrated by a newer version of finreport".to_string(),
+        ));
+    }
+    if applied.len() > version {
+        // Reverting is safe, the database is empty.
+        Migrator::down(conn, Some((applied.len() - version) as u32)).await?;
+    } else if applied.len() < version {
+        Migrator::up(conn, Some((version - applied.len()) as u32)).await?;
+    }
+    Ok(())
+}
+
+async fn insert_rows(
+    txn: &DatabaseTransaction,
+    table: &str,
+    rows: &[serde_json::Map<String, JsonValue>],
+) -> Result<(), BackupError> {
+    let backend = txn.get_database_backend();
+    // Rows of the seeded tables replace the seed.
+    txn.execute(Statement::from_string(backend, format!("DELETE FROM \"{table}\""))).await?;
+    let Some(first) = rows.first() else {
+        return Ok(());
+    };
+    let columns: Vec<&String> = first.keys().collect();
+    // Postgres does not convert text parameters to dates and timestamps on
+    // its own, so every value is cast to the type of its column.
+    let types = match backend {
+        DbBackend::Postgres => column_types(txn, table).await?,
+        _ => HashMap::new(),
+    };
+    for chunk in rows.chunks((MAX_VALUES_PER_INSERT / columns.len()).max(1)) {
+        let mut insert = SqlQuery::insert();
+        insert.into_table(Alias::new(table)).columns(columns.iter().map(|c| Alias::new(c.as_str())));
+        for row in chunk {
+            let values = columns.iter().map(|column| {
+                let value = Expr::val(to_value(row.get(column.as_str()).unwrap_or(&JsonValue::Null)));
+                match types.get(column.as_str()) {
+                    Some(data_type) => value.cast_as(Alias::new(data_type)),
+                    None => SimpleExpr::from(value),
+                }
+            });
+            insert.values(values).map_err(|e| {
+                BackupError::InvalidArchive(format!("row of {table} does not match its columns: {e}"))
+            })?;
+        }
+        txn.execute(backend.build(&insert)).await?;
+    }
+    if backend == DbBackend::Postgres && columns.iter().any(|c| *c == "id") {
+        // Rows came with their ids; the next one has to follow the largest.
+        let statement = format!(
+            "SELECT setval(pg_get_serial_sequence('\"{table}\"', 'id'), MAX(id)) FROM \"{table}\" \
+             HAVING MAX(id) IS NOT NULL"
+        );
+        txn.execute(Statement::from_string(backend, statement)).await?;
+    }
+    Ok(())
+}
+
+/// Types of the columns of `table` that a text or number parameter can be
+/// cast to.
+async fn column_types(txn: &DatabaseTransaction, table: &str) -> Result<HashMap<String, String>, DbErr> {
+    let statement = Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT column_name, data_type FROM information_schema.columns \
+         WHERE table_schema = current_schema() AND table_name = $1",
+        [table.into()],
+    );
+    let columns = JsonValue::find_by_statement(statement).all(txn).await?;
+    Ok(columns
+        .iter()
+        .filter_map(|c| Some((c["column_name"].as_str()?.to_string(), c["data_type"].as_str()?.to_string())))
+        .filter(|(_, data_type)| data_type != "USER-DEFINED" && data_type != "ARRAY")
+        .collect())
+}
+
+fn to_value(value: &JsonValue) -> Value {
+    match value {
+        JsonValue::Null => Value::String(None),
+        JsonValue::Bool(b) => Value::Bool(Some(*b)),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => Value::BigInt(Some(i)),
+            None => Value::Double(n.as_f64()),
+        },
+        JsonValue::String(s) => Value::String(Some(Box::new(s.clone()))),
+        other => Value::String(Some(Box::new(other.to_string()))),
+    }
+}
+
+fn backend_name(backend: DbBackend) -> &'static str {
+    match backend {
+        DbBackend::Postgres => "postgres",
+        DbBackend::Sqlite => "sqlite",
+        DbBackend::MySql => "mysql",
+    }
+}
+
+fn read_file<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Vec<u8>, BackupError> {
+    let mut file = archive
+        .by_name(name)
+        .map_err(|_| BackupError::InvalidArchive(format!("{name} is missing")))?;
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// The file `name`, if its content has the checksum listed in `SHA256SUMS`.
+fn read_checked<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    checksums: &HashMap<&str, &str>,
+    name: &str,
+) -> Result<Vec<u8>, BackupError> {
+    let content = read_file(archive, name)?;
+    match checksums.get(name) {
+        Some(sum) if *sum == hex::encode(Sha256::digest(&content)) => Ok(content),
+        Some(_) => Err(BackupError::InvalidArchive(format!("checksum mismatch for {name}"))),
+        None => Err(BackupError::InvalidArchive(format!("{name} has no checksum"))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backup::{backup, restore, BackupError, TABLES};
+    use crate::service::test::{account, db, transaction};
+    use crate::service::{CategoryAssignment, ChangeSource, Mutation, Query, TransactionFilter};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ConnectionTrait, Database, DbConn, FromQueryResult, JsonValue, Statement};
+    use std::io::{Cursor, Read, Write};
+    use zip::write::SimpleFileOptions;
+    use zip::{ZipArchive, ZipWriter};
+
+    async fn dump(conn: &DbConn) -> Vec<(&'static str, Vec<JsonValue>)> {
+        let mut tables = Vec::new();
+        for table in TABLES {
+            let select = format!("SELECT * FROM \"{table}\" ORDER BY 1");
+            let statement = Statement::from_string(conn.get_database_backend(), select);
+            tables.push((table, JsonValue::find_by_statement(statement).all(conn).await.unwrap()));
+        }
+        tables
+    }
+
+    async fn archive_of(conn: &DbConn) -> Vec<u8> {
+        let mut archive = Cursor::new(Vec::new());
+        backup(conn, &mut archive).await.unwrap();
+        archive.into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore_round_trip() {
+        let source = db().await;
+        Mutation::ensure_account(&source, account("A1")).await.unwrap();
+        for (reference, amount) in [("r1", -20.5), ("r2", 1000.0)] {
+            let input = transaction(reference, "A1", amount);
+            Mutation::upsert_transaction(&source, input, ChangeSource::Import).await.unwrap();
+        }
+        let assignment = CategoryAssignment {
+            reference: "r1".to_string(),
+            category: "food".to_string(),
+            subcategory: "groceries".to_string(),
+            reasoning: None,
+            confidence: Some(0.5),
+            source: ChangeSource::User,
+        };
+        Mutation::assign_category(&source, assignment).await.unwrap();
+        let references = ["r1".to_string()];
+        Mutation::add_tags(&source, &references, &["urlaub".to_string()], ChangeSource::User).await.unwrap();
+        Mutation::set_note(&source, &references, Some("Kassenbon".to_string()), ChangeSource::User)
+            .await
+            .unwrap();
+
+        let archive = archive_of(&source).await;
+        let target = db().await;
+        let manifest = restore(&target, Cursor::new(&archive)).await.unwrap();
+        assert_eq!(manifest.migrations.len(), Migrator::migrations().len());
+        assert_eq!(dump(&target).await, dump(&source).await);
+        let restored = Query::transactions(&target, &TransactionFilter::default()).await.unwrap();
+        assert_eq!(restored.len(), 2);
+
+        // A second restore would mix two databases.
+        let again = restore(&target, Cursor::new(&archive)).await;
+        assert!(matches!(again, Err(BackupError::NotEmpty(table)) if table == "account"));
+    }
+
+    #[tokio::test]
+    async fn test_restore_migrates_older_archive() {
+        // Accounts, balances and transactions, before categories existed.
+        let source = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&source, Some(3)).await.unwrap();
+        for sql in [
+            "INSERT INTO account (account_id, display_id, account_type, iban, bic, institute) \
+             VALUES ('A1', 'A1', 'Girokonto', 'DE00A1', 'COBADEHD001', 'COMDIRECT')",
+            "INSERT INTO account_transactions (reference, account_id, booking_status, booking_date, \
+             amount, remitter, deptor, creditor, creditor_id, creditor_mandate_id, remittance_info, \
+             transaction_type) VALUES ('r1', 'A1', 'BOOKED', '2025-06-02', -20.5, '', '', 'REWE', '', '', \
+             'Einkauf', 'Lastschrift')",
+        ] {
+            source.execute_unprepared(sql).await.unwrap();
+        }
+        let archive = archive_of(&source).await;
+
+        // A fully migrated database is reverted to the archive's schema first.
+        let target = db().await;
+        let manifest = restore(&target, Cursor::new(archive)).await.unwrap();
+        assert_eq!(manifest.migrations.len(), 3);
+        assert_eq!(manifest.tables.len(), 3);
+        let applied = Migrator::get_applied_migrations(&target).await.unwrap();
+        assert_eq!(applied.len(), Migrator::migrations().len());
+        let restored = Query::transactions(&target, &TransactionFilter::default()).await.unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].amount, -20.5);
+        assert!(Query::find_category(&target, "food", "groceries").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_restore_rejects_altered_archive() {
+        let source = db().await;
+        Mutation::ensure_account(&source, account("A1")).await.unwrap();
+        let mut original = ZipArchive::new(Cursor::new(archive_of(&source).await)).unwrap();
+        let mut altered = ZipWriter::new(Cursor::new(Vec::new()));
+        for i in 0..original.len() {
+            let mut file = original.by_index(i).unwrap();
+            let mut content = Vec::new();
+            file.read_to_end(&mut content).unwrap();
+            if file.name() == "tables/account.jsonl" {
+                content = String::from_utf8(content).unwrap().replace("DE00A1", "DE00A2").into_bytes();
+            }
+            altered.start_file(file.name(), SimpleFileOptions::default()).unwrap();
+            altered.write_all(&content).unwrap();
+        }
+        let altered = altered.finish().unwrap().into_inner();
+
+        let target = db().await;
+        let result = restore(&target, Cursor::new(altered)).await;
+        assert!(matches!(result, Err(BackupError::InvalidArchive(m)) if m.contains("tables/account.jsonl")));
+        assert!(Query::accounts(&target).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tables_cover_schema() {
+        let db = db().await;
+        let statement = Statement::from_string(
+            db.get_database_backend(),
+            "SELECT name FROM sqlite_master WHERE type = 'table' \
+             AND name NOT IN ('seaql_migrations', 'sqlite_sequence') ORDER BY name",
+        );
+        let tables = JsonValue::find_by_statement(statement).all(&db).await.unwrap();
+        let mut expected = TABLES.to_vec();
+        expected.sort();
+        let names: Vec<&str> = tables.iter().filter_map(|t| t["name"].as_str()).collect();
+        assert_eq!(names, expected);
+    }
+}
@@ -0,0 +1,177 @@
+//! Daily balance series reconstructed from the bookings. The bank only
+//! reports the current balance, so `account_balance` holds one snapshot per
+//! day the importer ran. Walking the booked transactions outwards from the
+//! latest snapshot yields the balance of every other day; stored snapshots
+//! that disagree with the walk point to missing or duplicate transactions.
+
+use crate::service::{Mutation, Query, ServiceError, TransactionFilter};
+use chrono::{Days, NaiveDate};
+use entity::entities::{account_balance, account_transactions};
+use sea_orm::DbConn;
+use std::collections::BTreeMap;
+
+/// Snapshot and reconstruction may differ by rounding, not by a booking.
+const TOLERANCE: f64 = 0.005;
+
+/// End-of-day balance of one account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyBalance {
+    pub date: NaiveDate,
+    pub amount: f64,
+    /// Stored snapshot of that day, if the importer ran.
+    pub snapshot: Option<f64>,
+}
+
+impl DailyBalance {
+    /// Snapshot minus reconstruction.
+    pub fn difference(&self) -> Option<f64> {
+        self.snapshot.map(|snapshot| round(snapshot - self.amount))
+    }
+
+    pub fn is_mismatch(&self) -> bool {
+        self.difference().is_some_and(|d| d.abs() > TOLERANCE)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Reconciliation {
+    pub account_id: String,
+    /// Empty when the account has no snapshot to start from.
+    pub days: Vec<DailyBalance>,
+}
+
+impl Reconciliation {
+    pub fn mismatches(&self) -> impl Iterator<Item = &DailyBalance> {
+        self.days.iter().filter(|d| d.is_mismatch())
+    }
+}
+
+/// Balances for every day from the first booking or snapshot to the last,
+/// anchored at the latest snapshot. Only booked transactions count, and a
+/// snapshot is taken as the balance at the end of its day. `None` without
+/// any snapshot.
+pub fn reconstruct(
+    transactions: &[account_transactions::Model],
+    snapshots: &[account_balance::Model],
+) -> Option<Vec<DailyBalance>> {
+    let anchor = snapshots.iter().max_by_key(|s| s.date)?;
+    let snapshots: BTreeMap<NaiveDate, f64> = snapshots.iter().map(|s| (s.date, s.amount)).collect();
+    let mut bookings: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+    for t in transactions.iter().filter(|t| t.booking_status == "BOOKED") {
+        *bookings.entry(t.booking_date).or_default() += t.amount;
+    }
+    let booked = |date: NaiveDate| bookings.get(&date).copied().unwrap_or_default();
+
+    let first = [bookings.keys().next(), snapshots.keys().next()]
+        .into_iter()
+        .flatten()
+        .min()
+        .copied()
+        .unwrap_or(anchor.date);
+    let last = bookings
+        .keys()
+        .next_back()
+        .copied()
+        .unwrap_or(anchor.date)
+        .max(anchor.date);
+
+    let mut amounts = BTreeMap::from([(anchor.date, anchor.amount)]);
+    let mut date = anchor.date;
+    let mut amount = anchor.amount;
+    while date > first {
+        // The end of the previous day is this day's end minus its bookings.
+        amount -= booked(date);
+        date = date - Days::new(1);
+        amounts.insert(date, amount);
+    }
+    let (mut date, mut amount) = (anchor.date, anchor.amount);
+    while date < last {
+        date = date + Days::new(1);
+        amount += booked(date);
+        amounts.insert(date, amount);
+    }
+
+    Some(
+        amounts
+            .into_iter()
+            .map(|(date, amount)| DailyBalance {
+                date,
+                amount: round(amount),
+                snapshot: snapshots.get(&date).copied(),
+            })
+            .collect(),
+    )
+}
+
+/// Rebuild the stored series of one account from its current bookings and
+/// snapshots.
+pub async fn reconcile(conn: &DbConn, account_id: &str) -> Result<Reconciliation, ServiceError> {
+    let filter = TransactionFilter {
+        accounts: vec![account_id.to_string()],
+        ..Default::default()
+    };
+    let transactions = Query::transactions(conn, &filter).await?;
+    let snapshots = Query::balances(conn, &filter).await?;
+    let days = reconstruct(&transactions, &snapshots).unwrap_or_default();
+    Mutation::replace_daily_balances(conn, account_id, &days).await?;
+    Ok(Reconciliation {
+        account_id: account_id.to_string(),
+        days,
+    })
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod test {
+    use crate::balance::reconstruct;
+    use crate::service::test::{booking, day, snapshot};
+    use entity::entities::account_transactions;
+
+    #[test]
+    fn test_walks_back_from_latest_snapshot() {
+        let pending = account_transactions::Model {
+            booking_status: "NOTBOOKED".to_string(),
+            ..booking("r4", "A1", 5, -999.0)
+        };
+        let transactions = [
+            booking("r1", "A1", 2, -20.0),
+            booking("r2", "A1", 4, 1000.0),
+            booking("r3", "A1", 4, -0.1),
+            pending,
+            booking("r5", "A1", 6, -30.0),
+        ];
+        let days = reconstruct(&transactions, &[snapshot(5, 1179.9)]).unwrap();
+        let amounts: Vec<_> = days.iter().map(|d| (d.date, d.amount)).collect();
+        assert_eq!(
+            amounts,
+            [
+                (day(2), 180.0),
+                (day(3), 180.0),
+                (day(4), 1179.9),
+                (day(5), 1179.9),
+                (day(6), 1149.9),
+            ]
+        );
+        assert!(days.iter().all(|d| !d.is_mismatch()));
+    }
+
+    #[test]
+    fn test_flags_disagreeing_snapshot() {
+        let transactions = [booking("r1", "A1", 2, -20.0), booking("r2", "A1", 3, -5.0)];
+        // The snapshot of day 1 says 225, but walking back from day 3 gives
+        // 220: a debit of 5 was imported twice or a credit is missing.
+        let days = reconstruct(&transactions, &[snapshot(1, 225.0), snapshot(3, 195.0)]).unwrap();
+        let mismatches: Vec<_> = days.iter().filter(|d| d.is_mismatch()).collect();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].date, day(1));
+        assert_eq!(mismatches[0].difference(), Some(5.0));
+    }
+
+    #[test]
+    fn test_needs_a_snapshot() {
+        assert!(reconstruct(&[booking("r1", "A1", 2, -20.0)], &[]).is_none());
+    }
+}
@@ -1,25 +1,41 @@
 use categorizer::categorize::{CategorizeAiResponse, Category};
+use clap::Parser;
 use rig::{completion::Prompt, providers::openai};
+use secrecy::ExposeSecret;
 use serde_json::json;
+use std::error::Error;
 use tokio::fs;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use comdirect_rs::comdirect::transaction::Transaction;
-use webapp::db::{Persistence, DB_URL};
+use utils::settings::DatabaseSettings;
+use webapp::categorization::{self, Facts};
+use webapp::db::seaql;
+use webapp::import::paypal;
+use webapp::service::{Mutation, Query, ServiceError};
+
+/// Categorize uncategorized transactions, by rules where one matches and
+/// with the LLM otherwise.
+#[derive(Parser)]
+struct Args {
+    /// Stop after this many transactions.
+    #[arg(long)]
+    limit: Option<u64>,
+}
 
 #[tokio::main]
-async fn main() {
-    let key = categorizer::categorize::settings()
-        .await
-        .expect("Failed to load settings")
-        .openai_key;
-    let openai_client = openai::Client::new(key.as_str());
-    let categories_json = fs::read_to_string("prompts/categories.json")
-        .await
-        .expect("Failed to read categories");
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let key = categorizer::categorize::settings().await?.openai_key;
+    let database = config::Config::builder()
+        .add_source(
+            config::Environment::with_prefix("APP")
+                .prefix_separator("_")
+                .separator("__"),
+        )
+        .build()?
+        .try_deserialize::<DatabaseSettings>()?;
 
-    let prompt_template: String = fs::read_to_string("prompts/categorize.txt")
-        .await
-        .expect("Failed to read systemd prompt");
+    let openai_client = openai::Client::new(key.as_str());
+    let categories_json = fs::read_to_string("prompts/categories.json").await?;
+    let prompt_template: String = fs::read_to_string("prompts/categorize.txt").await?;
 
     let updated_prompt = prompt_template.replace("{categories}", &categories_json);
     let gpt4 = openai_client
@@ -31,65 +47,54 @@ async fn main() {
         }))
         .build();
 
-    let mut transactions: Vec<String> = Vec::new();
+    let conn = seaql::init_db(database.database_url.expose_secret()).await?;
+    Mutation::sync_categories(&conn, &serde_json::from_str::<Vec<Category>>(&categories_json)?)
+        .await?;
 
-    let file = fs::File::open("transactions-102455031500.ndjson")
-        .await
-        .expect("Failed to open file");
-    let reader = BufReader::new(file);
-    let mut lines = reader.lines();
-
-    while let Some(line) = lines.next_line().await.expect("Failed to read line") {
-        if line.trim().is_empty() {
-            continue;
-        }
-        transactions.push(line);
-    }
-    let mut persistence = Persistence::new(DB_URL)
-        .await
-        .expect("Failed to create persistence");
+    let by_mandate = categorization::apply_mandates(&conn).await?;
+    println!("{by_mandate} direct debits categorized by mandate");
+    let by_rules = categorization::apply_rules(&conn).await?;
+    println!("{by_rules} transactions categorized by rules");
 
-    let categories = serde_json::from_str::<Vec<Category>>(&categories_json)
-        .expect("Failed to parse categories");
-    persistence
-        .load_categories(&categories)
-        .await
-        .expect("Failed to init categories");
+    let merchants = paypal::merchants_by_reference(&conn).await?;
+    let transactions = Query::uncategorized(&conn, args.limit).await?;
+    println!("{} transactions to categorize", transactions.len());
 
-    for t in transactions.iter() {
-        let transaction =
-            serde_json::from_str::<Transaction>(&t).expect("Failed to parse transaction");
-        let is_categorized: bool = persistence
-            .check_categorized(&transaction)
-            .await
-            .expect("Failed to check if transaction is categorized");
-        if is_categorized {
-            println!("Transaction already categorized: {t}");
-            continue;
-        } else {
-            // println!("Not categorized: {t}");
-            let response = gpt4
-                .prompt(t)
-                // .prompt(&transactions[random_transaction])
-                .await
-                .expect("Failed to prompt GPT-4");
+    for transaction in transactions {
+        let facts = Facts::of(&transaction, &merchants);
+        let input = json!({
+            "reference": transaction.reference,
+            "bookingDate": transaction.booking_date.to_string(),
+            "amount": transaction.amount,
+            "payee": facts.payee,
+            // The purpose without the SEPA references, which only add noise.
+            "remittanceInfo": facts.remittance.purpose,
+            "ultimateCreditor": facts.remittance.ultimate_creditor,
+            "transactionType": transaction.transaction_type,
+            "directDebitCreditorId": facts.creditor_id,
+            "directDebitMandateId": facts.mandate_id,
+        })
+        .to_string();
 
-            let r: CategorizeAiResponse = serde_json::from_str(response.as_str()).unwrap();
-            println!("GPT-4 o4-mini. Transaction: {r}");
-            persistence
-                .add_category(&transaction, &r)
-                .await
-                .expect("Failed to add category to transaction");
+        let response = gpt4.prompt(input.as_str()).await?;
+        let mut r: CategorizeAiResponse = match serde_json::from_str(response.as_str()) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Unparseable answer for {}: {e}: {response}", transaction.reference);
+                continue;
+            }
+        };
+        // The answer is stored for the transaction we asked about, whatever
+        // reference the model echoed back.
+        r.reference = transaction.reference.clone();
+        println!("GPT-4 o4-mini. Transaction: {r}");
+        match Mutation::assign_category(&conn, r.into()).await {
+            Ok(_) => {}
+            Err(e @ ServiceError::UnknownCategory { .. }) => {
+                eprintln!("Skipping {}: {e}", transaction.reference);
+            }
+            Err(e) => return Err(e.into()),
         }
     }
-    // let random_transaction = rand::rng().random_range(0..transactions.len());
-    // // Prompt the model and print its response
-    // let response = gpt4
-    //     .prompt(&transactions[273])
-    //     // .prompt(&transactions[random_transaction])
-    //     .await
-    //     .expect("Failed to prompt GPT-4");
-    //
-    // let r: CategorizeAiResponse = serde_json::from_str(response.as_str()).unwrap();
-    // println!("GPT-4 o4-mini. Transaction {random_transaction}: {r}");
+    Ok(())
 }
@@ -1,37 +1,58 @@
-use std::error::Error;
+use clap::Parser;
 use comdirect_rs::comdirect::transaction::Transaction;
-use webapp::db::{Persistence, DB_URL};
-use sqlx::{migrate::MigrateDatabase, Sqlite};
+use dotenv::dotenv;
+use secrecy::ExposeSecret;
+use std::error::Error;
+use std::path::PathBuf;
 use tokio::fs;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utils::settings::DatabaseSettings;
+use webapp::db::seaql;
+use webapp::service::{ChangeSource, Mutation, Query};
 
+/// Load a JSON dump of Comdirect API transactions into `account_transactions`.
+#[derive(Parser)]
+struct Args {
+    /// `account.account_id` the transactions belong to.
+    #[arg(long)]
+    account: String,
+    #[arg(default_value = "transactions-102455031500.json")]
+    file: PathBuf,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let json_content = fs::read_to_string("transactions-102455031500.json").await?;
-    let transactions: Vec<Transaction> = serde_json::from_str(&json_content)?;
-
-    if !Sqlite::database_exists(DB_URL).await.unwrap_or(false) {
-        println!("Creating database {}", DB_URL);
-        match Sqlite::create_database(DB_URL).await {
-            Ok(_) => println!("Create db success"),
-            Err(error) => panic!("error: {}", error),
-        }
-    } else {
-        println!("Database already exists.");
-    }
-    save_transactions_to_db(
-        &*transactions, DB_URL).await.expect("Failed to save transactions");
+    dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .init();
+    let args = Args::parse();
 
-    Ok(())
-}
+    let json_content = fs::read_to_string(&args.file).await?;
+    let transactions: Vec<Transaction> = serde_json::from_str(&json_content)?;
 
+    let settings = config::Config::builder()
+        .add_source(
+            config::Environment::with_prefix("APP")
+                .prefix_separator("_")
+                .separator("__"),
+        )
+        .build()?
+        .try_deserialize::<DatabaseSettings>()?;
+    let conn = seaql::init_db(settings.database_url.expose_secret()).await?;
 
-async fn save_transactions_to_db(
-    transactions: &[Transaction],
-    file_path: &str,
-) -> Result<(), Box<dyn Error>> {
-    let mut persistence = Persistence::new(file_path).await?;
-    persistence.insert_transactions(transactions).await?;
+    if Query::find_account(&conn, &args.account).await?.is_none() {
+        return Err(format!("account {} does not exist; run the importer first", args.account).into());
+    }
 
+    let summary =
+        Mutation::upsert_comdirect(&conn, &args.account, &transactions, ChangeSource::Import).await?;
+    info!(
+        inserted = summary.inserted,
+        updated = summary.updated,
+        failed = summary.failed,
+        "[db_importer] done"
+    );
     Ok(())
 }
@@ -0,0 +1,560 @@
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+use dotenv::dotenv;
+use secrecy::ExposeSecret;
+use std::error::Error;
+use std::io::Write;
+use std::path::PathBuf;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utils::settings::DatabaseSettings;
+use webapp::backup;
+use webapp::balance;
+use webapp::categorization;
+use webapp::counterparty;
+use webapp::db::seaql;
+use webapp::export::{self, ExportFormat, ExportSelection};
+use webapp::filter::{FilterError, FilterQuery};
+use webapp::import::profile::{ImportProfile, ParsedStatement};
+use webapp::import::{import_rows, paypal, ImportAccount};
+use webapp::quality::{self, DetectionSettings};
+use webapp::remittance;
+use webapp::report::{self, Totals};
+use webapp::service::{FindingStatus, Mutation, Query, SavedViewInput, TransferStatus};
+use webapp::transfer::{self, TransferSettings};
+use webapp::views::DEFAULT_OWNER;
+
+/// finreport command line tools.
+#[derive(Parser)]
+#[command(name = "finreport")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inspect CSV import profiles.
+    #[command(subcommand)]
+    Profile(ProfileCommand),
+    /// Import statements from files.
+    #[command(subcommand)]
+    Import(ImportCommand),
+    /// Export transactions for desktop finance tools and spreadsheets.
+    Export(ExportArgs),
+    /// Rebuild the daily balance series and report days where stored
+    /// snapshots disagree with the bookings.
+    Balances(BalancesArgs),
+    /// Detect probable duplicates and gaps in the bookings and list the
+    /// open findings.
+    Quality(QualityArgs),
+    /// Link internal transfers between own accounts and list them.
+    Transfers(TransfersArgs),
+    /// Resolve transactions to canonical payees and list the top payees.
+    Payees(PayeesArgs),
+    /// Manage saved views, named filters shared with the web app.
+    #[command(subcommand)]
+    View(ViewCommand),
+    /// Write the whole database into an archive that restores on either
+    /// backend.
+    Backup(BackupArgs),
+    /// Restore an archive written by `backup` into an empty database.
+    Restore {
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileCommand {
+    /// List the built-in profiles.
+    List,
+    /// Parse a file with a profile and preview the rows without touching the database.
+    Test {
+        /// Built-in profile name or path to a profile TOML file.
+        profile: String,
+        file: PathBuf,
+        /// Number of rows to print.
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum ViewCommand {
+    /// List the views with their totals.
+    List(OwnerArgs),
+    /// Create a view or replace the filter of the view with that name.
+    Save {
+        name: String,
+        /// Filter expression, e.g. `category:food -payee~rewe`.
+        filter: String,
+        #[command(flatten)]
+        owner: OwnerArgs,
+    },
+    /// Print the monthly totals of a view.
+    Show {
+        name: String,
+        #[command(flatten)]
+        owner: OwnerArgs,
+    },
+    Delete {
+        name: String,
+        #[command(flatten)]
+        owner: OwnerArgs,
+    },
+}
+
+#[derive(Args)]
+struct OwnerArgs {
+    /// User the views belong to, as sent by the proxy of the web app.
+    #[arg(long, default_value = DEFAULT_OWNER)]
+    owner: String,
+}
+
+#[derive(Subcommand)]
+enum ImportCommand {
+    /// Import a bank CSV export using an import profile.
+    Csv(CsvImportArgs),
+    /// Import a PayPal activity report and link payments to bank debits.
+    Paypal(PaypalImportArgs),
+}
+
+#[derive(Args)]
+struct CsvImportArgs {
+    /// Built-in profile name or path to a profile TOML file.
+    #[arg(long)]
+    profile: String,
+    /// `account.account_id` the rows belong to.
+    #[arg(long)]
+    account: String,
+    /// IBAN of the account; required when the account does not exist yet.
+    #[arg(long)]
+    iban: Option<String>,
+    #[arg(long)]
+    bic: Option<String>,
+    file: PathBuf,
+}
+
+#[derive(Args)]
+struct PaypalImportArgs {
+    /// Days a bank debit may be booked after the PayPal payment.
+    #[arg(long, default_value_t = 5)]
+    window_days: u64,
+    /// Only match debits carrying this `creditor_mandate_id` (repeatable).
+    #[arg(long = "mandate")]
+    mandates: Vec<String>,
+    file: PathBuf,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// ofx, qif, beancount, hledger, csv or xlsx.
+    format: ExportFormat,
+    /// `account.account_id` to export (repeatable); all accounts when omitted.
+    #[arg(long = "account")]
+    accounts: Vec<String>,
+    /// First booking date, inclusive (YYYY-MM-DD).
+    #[arg(long)]
+    from: Option<NaiveDate>,
+    /// Last booking date, inclusive (YYYY-MM-DD).
+    #[arg(long)]
+    to: Option<NaiveDate>,
+    /// Only transactions matching this filter, e.g.
+    /// `amount<-100 category:food -payee~rewe`.
+    #[arg(long)]
+    filter: Option<String>,
+    /// Only transactions carrying this tag (repeatable, all required).
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+    /// Leave out internal transfers between own accounts.
+    #[arg(long)]
+    without_transfers: bool,
+    /// Only transactions of this saved view, combined with `--filter`.
+    #[arg(long)]
+    view: Option<String>,
+    #[command(flatten)]
+    owner: OwnerArgs,
+    /// Output file; stdout when omitted.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct BalancesArgs {
+    /// `account.account_id` to reconcile (repeatable); all accounts when omitted.
+    #[arg(long = "account")]
+    accounts: Vec<String>,
+}
+
+#[derive(Args)]
+struct QualityArgs {
+    /// Bookings up to this many days apart can be duplicates.
+    #[arg(long, default_value_t = DetectionSettings::default().window_days)]
+    window_days: u64,
+    /// Minimum share of common words in payee and remittance info, 0 to 1.
+    #[arg(long, default_value_t = DetectionSettings::default().min_similarity)]
+    min_similarity: f64,
+}
+
+#[derive(Args)]
+struct TransfersArgs {
+    /// Legs may be booked up to this many days apart.
+    #[arg(long, default_value_t = TransferSettings::default().window_days)]
+    window_days: u64,
+}
+
+#[derive(Args)]
+struct PayeesArgs {
+    /// First booking date, inclusive (YYYY-MM-DD).
+    #[arg(long)]
+    from: Option<NaiveDate>,
+    /// Last booking date, inclusive (YYYY-MM-DD).
+    #[arg(long)]
+    to: Option<NaiveDate>,
+    /// How many payees to list.
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+}
+
+#[derive(Args)]
+struct BackupArgs {
+    /// Archive to write; `finreport-backup-<timestamp>.zip` when omitted.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .init();
+
+    match Cli::parse().command {
+        Command::Profile(ProfileCommand::List) => {
+            for name in ImportProfile::builtin_names() {
+                println!("{name}");
+            }
+        }
+        Command::Profile(ProfileCommand::Test {
+            profile,
+            file,
+            limit,
+        }) => {
+            let profile = ImportProfile::load(&profile)?;
+            let statement = profile.parse(&tokio::fs::read(&file).await?)?;
+            print_preview(&statement, limit);
+        }
+        Command::Import(ImportCommand::Csv(args)) => {
+            let profile = ImportProfile::load(&args.profile)?;
+            let statement = profile.parse(&tokio::fs::read(&args.file).await?)?;
+            if !statement.errors.is_empty() {
+                print_preview(&statement, 0);
+                return Err("refusing to import a file with unparseable rows".into());
+            }
+
+            let conn = seaql::init_db(database_settings()?.database_url.expose_secret()).await?;
+            let target = ImportAccount {
+                account_id: args.account,
+                iban: args.iban,
+                bic: args.bic,
+            };
+            let summary = import_rows(&conn, &profile, &target, &statement.rows).await?;
+            remittance::backfill(&conn).await?;
+            let transfers = transfer::detect(&conn, &TransferSettings::default()).await?;
+            let counterparties = counterparty::resolve(&conn).await?;
+            let by_mandate = categorization::apply_mandates(&conn).await?;
+            info!(
+                inserted = summary.inserted,
+                updated = summary.updated,
+                failed = summary.failed,
+                transfers,
+                new_payees = counterparties.created,
+                by_mandate,
+                "[import] csv done"
+            );
+        }
+        Command::Import(ImportCommand::Paypal(args)) => {
+            let activity = paypal::parse_activity_report(&tokio::fs::read(&args.file).await?)?;
+            for error in &activity.errors {
+                println!("line {}: {}", error.line, error.message);
+            }
+
+            let conn = seaql::init_db(database_settings()?.database_url.expose_secret()).await?;
+            paypal::import_activity(&conn, &activity.rows).await?;
+            let linked = paypal::link_payments(&conn, args.window_days, &args.mandates).await?;
+            info!(
+                rows = activity.rows.len(),
+                failed = activity.errors.len(),
+                linked,
+                "[import] paypal done"
+            );
+        }
+        Command::Export(args) => {
+            let conn = seaql::init_db(database_settings()?.database_url.expose_secret()).await?;
+            let mut selection = ExportSelection {
+                accounts: args.accounts,
+                from: args.from,
+                to: args.to,
+                query: args.filter.as_deref().map(parse_filter).transpose()?,
+                tags: args.tags,
+                without_transfers: args.without_transfers,
+            };
+            if let Some(name) = args.view {
+                let view = Query::saved_view_by_name(&conn, &args.owner.owner, &name).await?;
+                selection = selection.with_view(&view)?;
+            }
+            let body = export::render(args.format, &export::load(&conn, &selection).await?)?;
+            match args.output {
+                Some(path) => tokio::fs::write(path, body).await?,
+                None => std::io::stdout().write_all(&body)?,
+            }
+        }
+        Command::Balances(args) => {
+            let conn = seaql::init_db(database_settings()?.database_url.expose_secret()).await?;
+            let accounts = match args.accounts.is_empty() {
+                true => Query::accounts(&conn).await?.into_iter().map(|a| a.account_id).collect(),
+                false => args.accounts,
+            };
+            for account_id in accounts {
+                let reconciliation = balance::reconcile(&conn, &account_id).await?;
+                if reconciliation.days.is_empty() {
+                    println!("{account_id}: no balance snapshot, nothing to reconstruct");
+                    continue;
+                }
+                let mismatches: Vec<_> = reconciliation.mismatches().collect();
+                println!(
+                    "{account_id}: {} days from {}, {} mismatching snapshots",
+                    reconciliation.days.len(),
+                    reconciliation.days[0].date,
+                    mismatches.len()
+                );
+                for day in mismatches {
+                    println!(
+                        "  {}  snapshot {:>12.2}  bookings {:>12.2}  difference {:>10.2}",
+                        day.date,
+                        day.snapshot.unwrap_or_default(),
+                        day.amount,
+                        day.difference().unwrap_or_default()
+                    );
+                }
+            }
+        }
+        Command::Quality(args) => {
+            let conn = seaql::init_db(database_settings()?.database_url.expose_secret()).await?;
+            let settings = DetectionSettings {
+                window_days: args.window_days,
+                min_similarity: args.min_similarity,
+            };
+            let summary = quality::detect(&conn, &settings).await?;
+            println!(
+                "{} probable duplicates, {} gaps, {} new",
+                summary.duplicates, summary.gaps, summary.new
+            );
+            for finding in Query::findings(&conn, Some(FindingStatus::Open), None).await? {
+                println!(
+                    "  #{:<5} {:<9} {}  {} .. {}  {:>10.2}  {} {}",
+                    finding.id,
+                    finding.kind,
+                    finding.account_id,
+                    finding.date_from,
+                    finding.date_to,
+                    finding.amount.unwrap_or_default(),
+                    finding.reference.unwrap_or_default(),
+                    finding.other_reference.unwrap_or_default()
+                );
+            }
+        }
+        Command::Transfers(args) => {
+            let conn = seaql::init_db(database_settings()?.database_url.expose_secret()).await?;
+            let settings = TransferSettings {
+                window_days: args.window_days,
+            };
+            println!("{} new internal transfers", transfer::detect(&conn, &settings).await?);
+            let transfers = Query::transfers(&conn, Some(TransferStatus::Linked)).await?;
+            let references: Vec<String> = transfers
+                .iter()
+                .flat_map(|t| [t.outgoing_reference.clone(), t.incoming_reference.clone()])
+                .collect();
+            let legs = Query::transactions_by_reference(&conn, &references).await?;
+            let leg = |reference: &str| legs.iter().find(|t| t.reference == reference);
+            for transfer in &transfers {
+                let (Some(outgoing), Some(incoming)) =
+                    (leg(&transfer.outgoing_reference), leg(&transfer.incoming_reference))
+                else {
+                    continue;
+                };
+                println!(
+                    "  #{:<5} {}  {:<16} -> {}  {:<16} {:>10.2}  {}",
+                    transfer.id,
+                    outgoing.booking_date,
+                    outgoing.account_id,
+                    incoming.booking_date,
+                    incoming.account_id,
+                    incoming.amount,
+                    transfer.source
+                );
+            }
+        }
+        Command::Payees(args) => {
+            let conn = seaql::init_db(database_settings()?.database_url.expose_secret()).await?;
+            let summary = counterparty::resolve(&conn).await?;
+            println!("{} transactions resolved anew, {} new payee names", summary.changed, summary.created);
+            let filter = ExportSelection {
+                from: args.from,
+                to: args.to,
+                ..without_transfers()
+            };
+            for payee in counterparty::top_payees(&conn, &filter, args.limit).await? {
+                println!(
+                    "  #{:<5} {:<32} {:>5}  {:>10.2}  {:>10.2}",
+                    payee.counterparty.id,
+                    payee.counterparty.name,
+                    payee.totals.count,
+                    payee.totals.expenses,
+                    payee.totals.income
+                );
+            }
+        }
+        Command::View(ViewCommand::List(owner)) => {
+            let conn = seaql::init_db(database_settings()?.database_url.expose_secret()).await?;
+            for view in Query::saved_views(&conn, &owner.owner).await? {
+                let transactions =
+                    webapp::views::transactions(&conn, &view, without_transfers()).await?;
+                let totals = Totals::of(&transactions);
+                println!(
+                    "{:<24} {:>6} transactions  net {:>12.2}  {}",
+                    view.name,
+                    totals.count,
+                    totals.net(),
+                    view.filter
+                );
+            }
+        }
+        Command::View(ViewCommand::Save { name, filter, owner }) => {
+            parse_filter(&filter)?;
+            let conn = seaql::init_db(database_settings()?.database_url.expose_secret()).await?;
+            let id = Query::saved_view_by_name(&conn, &owner.owner, &name).await.ok().map(|v| v.id);
+            let input = SavedViewInput {
+                owner: owner.owner,
+                name,
+                filter,
+            };
+            let view = Mutation::save_view(&conn, id, input).await?;
+            println!("saved view #{} {}", view.id, view.name);
+        }
+        Command::View(ViewCommand::Show { name, owner }) => {
+            let conn = seaql::init_db(database_settings()?.database_url.expose_secret()).await?;
+            let view = Query::saved_view_by_name(&conn, &owner.owner, &name).await?;
+            let transactions = webapp::views::transactions(&conn, &view, without_transfers()).await?;
+            println!("{:<7}  {:>6}  {:>12}  {:>12}  {:>12}", "month", "count", "income", "expenses", "net");
+            for ((year, month), totals) in report::monthly_totals(&transactions) {
+                println!(
+                    "{year:04}-{month:02}  {:>6}  {:>12.2}  {:>12.2}  {:>12.2}",
+                    totals.count,
+                    totals.income,
+                    totals.expenses,
+                    totals.net()
+                );
+            }
+        }
+        Command::View(ViewCommand::Delete { name, owner }) => {
+            let conn = seaql::init_db(database_settings()?.database_url.expose_secret()).await?;
+            let view = Query::saved_view_by_name(&conn, &owner.owner, &name).await?;
+            Mutation::delete_view(&conn, &owner.owner, view.id).await?;
+        }
+        Command::Backup(args) => {
+            // The archive records the schema as it is, so nothing is migrated.
+            let conn = seaql::connect(database_settings()?.database_url.expose_secret()).await?;
+            let path = args.output.unwrap_or_else(|| {
+                let now = chrono::Local::now().format("%Y%m%d-%H%M%S");
+                PathBuf::from(format!("finreport-backup-{now}.zip"))
+            });
+            let manifest = backup::backup(&conn, std::fs::File::create_new(&path)?).await?;
+            let rows: usize = manifest.tables.iter().map(|t| t.rows).sum();
+            info!(
+                rows,
+                tables = manifest.tables.len(),
+                path = %path.display(),
+                "[backup] written"
+            );
+        }
+        Command::Restore { file } => {
+            let conn = seaql::connect(database_settings()?.database_url.expose_secret()).await?;
+            let manifest = backup::restore(&conn, std::fs::File::open(&file)?).await?;
+            let rows: usize = manifest.tables.iter().map(|t| t.rows).sum();
+            info!(
+                rows,
+                created_at = manifest.created_at,
+                backend = manifest.backend,
+                "[backup] restored"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// View totals count income and expenses, not internal transfers.
+fn without_transfers() -> ExportSelection {
+    ExportSelection {
+        without_transfers: true,
+        ..Default::default()
+    }
+}
+
+/// Parse a `--filter`, pointing at the error on the terminal.
+fn parse_filter(input: &str) -> Result<FilterQuery, FilterError> {
+    input
+        .parse::<FilterQuery>()
+        .inspect_err(|e| eprintln!("{}", e.pointer(input)))
+}
+
+fn database_settings() -> Result<DatabaseSettings, config::ConfigError> {
+    config::Config::builder()
+        .add_source(
+            config::Environment::with_prefix("APP")
+                .prefix_separator("_")
+                .separator("__"),
+        )
+        .build()?
+        .try_deserialize::<DatabaseSettings>()
+}
+
+fn print_preview(statement: &ParsedStatement, limit: usize) {
+    println!(
+        "{:>5}  {:<10}  {:<10}  {:>12}  {:<30}  remittance info",
+        "line", "booked", "status", "amount", "counterparty"
+    );
+    for row in statement.rows.iter().take(limit) {
+        println!(
+            "{:>5}  {:<10}  {:<10}  {:>12.2}  {:<30}  {}",
+            row.line,
+            row.booking_date,
+            row.booking_status,
+            row.amount,
+            truncate(&row.counterparty, 30),
+            truncate(&row.remittance_info, 60),
+        );
+    }
+    if statement.rows.len() > limit {
+        println!("... {} more rows", statement.rows.len() - limit);
+    }
+    let total: f64 = statement.rows.iter().map(|r| r.amount).sum();
+    println!("{} rows parsed, sum {total:.2}", statement.rows.len());
+    for error in &statement.errors {
+        println!("line {}: {}", error.line, error.message);
+    }
+    if !statement.errors.is_empty() {
+        println!("{} rows failed to parse", statement.errors.len());
+    }
+}
+
+fn truncate(value: &str, max: usize) -> String {
+    if value.chars().count() > max {
+        let mut truncated: String = value.chars().take(max - 1).collect();
+        truncated.push('…');
+        truncated
+    } else {
+        value.to_string()
+    }
+}
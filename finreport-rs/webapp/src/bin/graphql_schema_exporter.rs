@@ -1,10 +1,13 @@
+use sea_orm::DatabaseConnection;
 use std::error::Error;
 use std::path::Path;
+use std::sync::Arc;
 use tokio::fs;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let schema = webapp::graphql::create_schema();
+    // The SDL does not depend on the database; a disconnected handle suffices.
+    let schema = webapp::graphql::create_schema(Arc::new(DatabaseConnection::default()));
     let sdl = schema.sdl();
     // Ensure the directory exists
     let dir_path = Path::new("graphql");
@@ -2,10 +2,7 @@ use comdirect_rs::comdirect::accounts::{get_account_transactions, get_accounts};
 use comdirect_rs::comdirect::session::{load_comdirect_session, refresh_comdirect_session};
 use comdirect_rs::comdirect::session_client::Session;
 use dotenv::dotenv;
-use entities::{account, account_balance};
-use entity::entities;
-use sea_orm::sea_query::OnConflict;
-use sea_orm::{DbConn, EntityTrait, Set, Unchanged};
+use sea_orm::DbConn;
 use secrecy::ExposeSecret;
 use std::error::Error;
 use std::time::{Duration, Instant};
@@ -13,7 +10,14 @@ use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 use tracing_subscriber::EnvFilter;
 use utils::settings::Settings;
+use webapp::balance;
+use webapp::categorization;
+use webapp::counterparty;
 use webapp::db::seaql;
+use webapp::quality::{self, DetectionSettings};
+use webapp::remittance;
+use webapp::service::{AccountInput, BalanceInput, ChangeSource, ImportRunKind, Mutation};
+use webapp::transfer::{self, TransferSettings};
 
 // --- Loop tuning -------------------------------------------------------------
 
@@ -21,6 +25,9 @@ const REFRESH_INTERVAL: Duration = Duration::from_secs(8 * 60); // 8 min
 const IMPORT_INTERVAL: Duration = Duration::from_secs(4 * 3600); // 4 h
 const MAX_BOOTSTRAP_ATTEMPTS: u32 = 6;
 
+/// `import_runs.connector` of this importer.
+const CONNECTOR: &str = "comdirect";
+
 /// Exponential-ish backoff between failed bootstrap attempts, capped at 1h.
 /// 10m → 20m → 40m → 60m → 60m → 60m  (6 total attempts).
 fn bootstrap_backoff(attempt: u32) -> Duration {
@@ -83,7 +90,22 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     max = MAX_BOOTSTRAP_ATTEMPTS,
                     "[bootstrap] starting"
                 );
-                match load_comdirect_session(client_settings.clone()).await {
+                let bootstrap_run =
+                    match Mutation::start_import_run(&conn, ImportRunKind::Bootstrap, CONNECTOR).await {
+                        Ok(run) => Some(run),
+                        Err(e) => {
+                            warn!(%e, "[bootstrap] failed to record run");
+                            None
+                        }
+                    };
+                let bootstrap = load_comdirect_session(client_settings.clone()).await;
+                if let Some(run) = &bootstrap_run {
+                    let error = bootstrap.as_ref().err().map(|e| format!("{e:?}"));
+                    if let Err(e) = Mutation::finish_import_run(&conn, run.id, 0, error).await {
+                        warn!(%e, "[bootstrap] failed to record run");
+                    }
+                }
+                match bootstrap {
                     Ok(session) => {
                         info!("[bootstrap] session acquired");
                         // Import immediately on first successful bootstrap so
@@ -187,41 +209,69 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
 // --- Import work -------------------------------------------------------------
 
+/// Import all accounts and record the run in `import_runs`, failed or not.
 async fn run_import(
     session: &Session,
     client_settings: &Settings,
     conn: &DbConn,
+) -> Result<(), Box<dyn Error>> {
+    let run = Mutation::start_import_run(conn, ImportRunKind::Import, CONNECTOR).await?;
+    let mut pages = 0;
+    let result = import_accounts(session, client_settings, conn, run.id, &mut pages).await;
+    let error = result.as_ref().err().map(|e| e.to_string());
+    if let Err(e) = Mutation::finish_import_run(conn, run.id, pages, error).await {
+        error!(%e, import_run_id = run.id, "[import] failed to record run");
+    }
+    if result.is_ok() {
+        match quality::detect(conn, &DetectionSettings::default()).await {
+            Ok(summary) => info!(
+                duplicates = summary.duplicates,
+                gaps = summary.gaps,
+                new = summary.new,
+                "[import] data-quality findings"
+            ),
+            Err(e) => error!(%e, "[import] failed to detect duplicates and gaps"),
+        }
+        match transfer::detect(conn, &TransferSettings::default()).await {
+            Ok(linked) => info!(linked, "[import] internal transfers"),
+            Err(e) => error!(%e, "[import] failed to detect internal transfers"),
+        }
+        match remittance::backfill(conn).await {
+            Ok(parsed) => info!(parsed, "[import] remittance info of earlier bookings"),
+            Err(e) => error!(%e, "[import] failed to parse remittance info"),
+        }
+        match counterparty::resolve(conn).await {
+            Ok(summary) => info!(changed = summary.changed, new = summary.created, "[import] counterparties"),
+            Err(e) => error!(%e, "[import] failed to resolve counterparties"),
+        }
+        match categorization::apply_mandates(conn).await {
+            Ok(categorized) => info!(categorized, "[import] direct debits categorized by mandate"),
+            Err(e) => error!(%e, "[import] failed to categorize direct debits by mandate"),
+        }
+    }
+    result
+}
+
+async fn import_accounts(
+    session: &Session,
+    client_settings: &Settings,
+    conn: &DbConn,
+    import_run_id: i32,
+    pages: &mut u32,
 ) -> Result<(), Box<dyn Error>> {
     let accounts = get_accounts(session.clone(), client_settings.clone()).await?;
+    *pages += 1;
     info!(
         count = accounts.accounts.len(),
         "[import] loaded accounts from Comdirect"
     );
 
     for account in accounts.accounts {
-        let account_orm = account::ActiveModel {
-            account_id: Unchanged(account.account.account_id.clone()),
-            display_id: Unchanged(account.account.display_id.to_owned()),
-            account_type: Unchanged(account.account.account_type.text.to_owned()),
-            iban: Unchanged(account.account.iban.to_owned()),
-            bic: Unchanged(account.account.bic.to_owned()),
-            institute: Unchanged("COMDIRECT".to_string()),
-            ..Default::default()
-        };
-
-        match account::Entity::insert(account_orm)
-            .on_conflict(
-                OnConflict::column(account::Column::AccountId)
-                    .do_nothing()
-                    .to_owned(),
-            )
-            .exec(conn)
-            .await
-        {
-            Ok(r) => info!(
-                display_id = %account.account.display_id,
-                last_insert_id = ?r.last_insert_id,
-                "inserted account"
+        match Mutation::ensure_account(conn, AccountInput::from_comdirect(&account)).await {
+            Ok(stored) => info!(
+                display_id = %stored.display_id,
+                id = stored.id,
+                "ensured account"
             ),
             Err(err) => error!(
                 display_id = %account.account.display_id,
@@ -230,26 +280,13 @@ async fn run_import(
             ),
         }
 
-        let balance_orm = account_balance::ActiveModel {
-            account_id: Set(account.account.account_id.to_owned()),
-            amount: Set(account.balance.value.parse().unwrap_or(0.0)),
-            date: Set(chrono::Local::now().date_naive()),
-            ..Default::default()
+        let balance = BalanceInput {
+            account_id: account.account.account_id.to_owned(),
+            amount: account.balance.value.parse().unwrap_or(0.0),
+            date: chrono::Local::now().date_naive(),
         };
-
-        match account_balance::Entity::insert(balance_orm)
-            .on_conflict(
-                OnConflict::columns([
-                    account_balance::Column::AccountId,
-                    account_balance::Column::Date,
-                ])
-                .do_nothing()
-                .to_owned(),
-            )
-            .exec(conn)
-            .await
-        {
-            Ok(_) => info!(
+        match Mutation::record_balance(conn, balance).await {
+            Ok(()) => info!(
                 display_id = %account.account.display_id,
                 balance = %account.balance.value,
                 "inserted balance"
@@ -262,58 +299,47 @@ async fn run_import(
         }
 
         debug!(account_id = %account.account_id, "fetching transactions");
-        let transactions =
+        let fetched =
             get_account_transactions(session.clone(), client_settings.clone(), &account.account)
                 .await?;
+        *pages += fetched.pages;
 
-        for transaction in transactions {
-            let transaction_orm = entities::account_transactions::ActiveModel {
-                reference: Set(transaction.reference.to_owned()),
-                account_id: Set(account.account.account_id.to_owned()),
-                booking_status: Set(transaction.booking_status),
-                booking_date: Set(transaction.booking_date.parse().unwrap()),
-                amount: Set(transaction.amount.value.parse().unwrap_or(0.0)),
-                remitter: Set(transaction.remitter.unwrap_or_default().holder_name),
-                deptor: Set(transaction.deptor.unwrap_or_default()),
-                creditor: Set(transaction
-                    .creditor
-                    .unwrap_or_default()
-                    .holder_name
-                    .to_owned()),
-                creditor_id: Set(transaction.direct_debit_creditor_id.unwrap_or_default()),
-                creditor_mandate_id: Set(transaction.direct_debit_mandate_id.unwrap_or_default()),
-                remittance_info: Set(transaction.remittance_info),
-                transaction_type: Set(transaction.transaction_type.text),
-                ..Default::default()
-            };
+        let account_id = &account.account.account_id;
+        let summary = Mutation::upsert_comdirect(
+            conn,
+            account_id,
+            &fetched.transactions,
+            ChangeSource::ImportRun(import_run_id),
+        )
+        .await?;
+        Mutation::record_import_account(
+            conn,
+            import_run_id,
+            account_id,
+            fetched.transactions.len(),
+            &summary,
+        )
+        .await?;
+        info!(
+            display_id = %account.account.display_id,
+            inserted = summary.inserted,
+            updated = summary.updated,
+            failed = summary.failed,
+            "[import] stored transactions"
+        );
 
-            match entities::account_transactions::Entity::insert(transaction_orm)
-                .on_conflict(
-                    OnConflict::column(entities::account_transactions::Column::Reference)
-                        .update_columns([
-                            entities::account_transactions::Column::BookingStatus,
-                            entities::account_transactions::Column::BookingDate,
-                            entities::account_transactions::Column::Amount,
-                            entities::account_transactions::Column::Remitter,
-                            entities::account_transactions::Column::Deptor,
-                            entities::account_transactions::Column::Creditor,
-                            entities::account_transactions::Column::CreditorId,
-                            entities::account_transactions::Column::CreditorMandateId,
-                            entities::account_transactions::Column::RemittanceInfo,
-                            entities::account_transactions::Column::TransactionType,
-                        ])
-                        .to_owned(),
-                )
-                .exec(conn)
-                .await
-            {
-                Ok(_) => debug!(reference = %transaction.reference, "inserted transaction"),
-                Err(e) => error!(
-                    reference = %transaction.reference,
-                    %e,
-                    "failed to insert transaction"
-                ),
+        match balance::reconcile(conn, account_id).await {
+            Ok(reconciliation) => {
+                for day in reconciliation.mismatches() {
+                    warn!(
+                        display_id = %account.account.display_id,
+                        date = %day.date,
+                        difference = ?day.difference(),
+                        "[import] balance snapshot disagrees with bookings"
+                    );
+                }
             }
+            Err(e) => error!(%e, "[import] failed to reconcile balances"),
         }
     }
 
@@ -0,0 +1,425 @@
+//! Categorization of the uncategorized transactions. Direct debits come
+//! first: once a user categorized or confirmed one, its mandate in
+//! `mandate_categories` categorizes every debit with the same mandate. Then
+//! the rules of [`categorizer::rules`]: rent, insurances and broadcasting
+//! fees are recognized by IBAN or creditor ID without asking anyone. The
+//! `categorize` binary sends only what is left to the model.
+
+use crate::export;
+use crate::import::paypal::{self, PAYPAL_CREDITOR_ID};
+use crate::remittance::{self, Remittance};
+use crate::service::{CategoryAssignment, ChangeSource, Mutation, Query, ServiceError, TransactionFilter};
+use categorizer::rules::{Booking, Criteria, Rule, RuleSet};
+use entity::entities::{account_transactions, categories, categorization_rules, mandate_category_conflicts};
+use sea_orm::DbConn;
+use std::collections::HashMap;
+use tracing::warn;
+
+/// A transaction as rules and the model see it: the payee as in exports,
+/// except the merchant for PayPal debits, and the SEPA fields parsed from
+/// the remittance info where the bank left the columns empty.
+#[derive(Debug, Clone)]
+pub struct Facts {
+    pub payee: String,
+    pub creditor_id: String,
+    pub mandate_id: String,
+    pub remittance: Remittance,
+}
+
+impl Facts {
+    /// `merchants` by reference, see [`paypal::merchants_by_reference`].
+    pub fn of(transaction: &account_transactions::Model, merchants: &HashMap<String, String>) -> Facts {
+        let remittance = remittance::parse(&transaction.remittance_info);
+        let or_parsed = |field: &str, parsed: &Option<String>| match field.trim() {
+            "" => parsed.clone().unwrap_or_default(),
+            field => field.to_string(),
+        };
+        Facts {
+            payee: merchants
+                .get(&transaction.reference)
+                .cloned()
+                .unwrap_or_else(|| export::payee(transaction)),
+            creditor_id: or_parsed(&transaction.creditor_id, &remittance.creditor_id),
+            mandate_id: or_parsed(&transaction.creditor_mandate_id, &remittance.mandate_reference),
+            remittance,
+        }
+    }
+
+    /// The mandate debits are categorized by. PayPal has one mandate for
+    /// all merchants, so its debits have none here.
+    pub fn mandate(&self) -> Option<&str> {
+        match self.mandate_id.trim() {
+            "" => None,
+            _ if self.creditor_id.trim().eq_ignore_ascii_case(PAYPAL_CREDITOR_ID) => None,
+            mandate => Some(mandate),
+        }
+    }
+
+    pub fn booking<'a>(&'a self, transaction: &'a account_transactions::Model) -> Booking<'a> {
+        Booking {
+            payee: &self.payee,
+            iban: &transaction.counterparty_iban,
+            creditor_id: &self.creditor_id,
+            mandate_id: &self.mandate_id,
+            amount: transaction.amount,
+            transaction_type: &transaction.transaction_type,
+            remittance_info: &transaction.remittance_info,
+        }
+    }
+}
+
+pub fn criteria(rule: &categorization_rules::Model) -> Criteria {
+    Criteria {
+        payee: rule.payee.clone(),
+        iban: rule.iban.clone(),
+        creditor_id: rule.creditor_id.clone(),
+        mandate_id: rule.mandate_id.clone(),
+        min_amount: rule.min_amount,
+        max_amount: rule.max_amount,
+        transaction_type: rule.transaction_type.clone(),
+        remittance_pattern: rule.remittance_pattern.clone(),
+    }
+}
+
+/// Categorize the uncategorized transactions a rule matches; returns how
+/// many were categorized. Split transactions are left alone.
+pub async fn apply_rules(conn: &DbConn) -> Result<usize, ServiceError> {
+    let stored = Query::categorization_rules(conn).await?;
+    let mut rules = RuleSet::default();
+    for (rule, _) in &stored {
+        let compiled = Rule {
+            id: rule.id,
+            priority: rule.priority,
+            criteria: criteria(rule),
+        };
+        if let Err(e) = rules.add(compiled) {
+            warn!(rule = rule.id, %e, "[categorization] skipping rule");
+        }
+    }
+    if rules.is_empty() {
+        return Ok(0);
+    }
+    let by_id: HashMap<i32, &(categorization_rules::Model, categories::Model)> =
+        stored.iter().map(|entry| (entry.0.id, entry)).collect();
+
+    let merchants = paypal::merchants_by_reference(conn).await?;
+    let mut categorized = 0;
+    for transaction in Query::uncategorized(conn, None).await? {
+        let facts = Facts::of(&transaction, &merchants);
+        let Some(matched) = rules.matching(&facts.booking(&transaction)) else {
+            continue;
+        };
+        let (rule, category) = by_id[&matched.id];
+        let assignment = CategoryAssignment {
+            reference: transaction.reference,
+            category: category.category.clone(),
+            subcategory: category.subcategory.clone(),
+            reasoning: Some(format!("rule `{}`", rule.name)),
+            confidence: Some(1.0),
+            source: ChangeSource::Rule(rule.id),
+        };
+        Mutation::assign_category(conn, assignment).await?;
+        categorized += 1;
+    }
+    Ok(categorized)
+}
+
+/// What [`learn_mandate`] made of a categorized debit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MandateLearning {
+    /// Not a debit with a mandate, or split or uncategorized.
+    Skipped,
+    /// The mandate maps to the category of the debit; `applied` other debits
+    /// were recategorized.
+    Learned { applied: usize },
+    /// The mandate maps to another category. Neither the mapping nor other
+    /// debits change until the conflict is resolved.
+    Conflict(mandate_category_conflicts::Model),
+}
+
+/// Learn from a user who categorized or confirmed the transaction
+/// `reference`: a mandate seen for the first time is mapped to its category,
+/// which then goes to all debits of the mandate. Split debits teach nothing,
+/// their parts have categories of their own.
+pub async fn learn_mandate(conn: &DbConn, reference: &str) -> Result<MandateLearning, ServiceError> {
+    let Some(transaction) = Query::transactions_by_reference(conn, &[reference.to_string()]).await?.pop()
+    else {
+        return Ok(MandateLearning::Skipped);
+    };
+    let facts = Facts::of(&transaction, &HashMap::new());
+    let Some(mandate) = facts.mandate() else {
+        return Ok(MandateLearning::Skipped);
+    };
+    // Split transactions have no category of their own.
+    let Some(category) = Query::category(conn, reference).await? else {
+        return Ok(MandateLearning::Skipped);
+    };
+    match Query::find_mandate_category(conn, mandate).await? {
+        Some(mapped) if mapped.category_id != category.id => {
+            let conflict = Mutation::flag_mandate_conflict(conn, mandate, reference, category.id).await?;
+            return Ok(MandateLearning::Conflict(conflict));
+        }
+        Some(_) => {
+            Mutation::dismiss_mandate_conflicts(conn, reference).await?;
+        }
+        None => {
+            Mutation::save_mandate_category(conn, mandate, category.id, Some(facts.payee.clone())).await?;
+        }
+    }
+    Ok(MandateLearning::Learned {
+        applied: apply_mandates(conn).await?,
+    })
+}
+
+/// Give every debit with a mapped mandate the category of the mandate;
+/// returns how many changed. Split debits and categories a user chose or
+/// confirmed are left alone, whatever categorized a debit before.
+pub async fn apply_mandates(conn: &DbConn) -> Result<usize, ServiceError> {
+    let mapped: HashMap<String, categories::Model> = Query::mandate_categories(conn)
+        .await?
+        .into_iter()
+        .map(|(mandate, category)| (mandate.creditor_mandate_id, category))
+        .collect();
+    if mapped.is_empty() {
+        return Ok(0);
+    }
+    let current = Query::categories_by_reference(conn).await?;
+    let splits = Query::splits_by_reference(conn).await?;
+    let sources = Query::category_sources(conn).await?;
+    let user = ChangeSource::User.to_string();
+
+    let mut categorized = 0;
+    for transaction in Query::transactions(conn, &TransactionFilter::default()).await? {
+        let facts = Facts::of(&transaction, &HashMap::new());
+        let Some((mandate, category)) = facts.mandate().and_then(|m| mapped.get_key_value(m)) else {
+            continue;
+        };
+        let reference = &transaction.reference;
+        if splits.contains_key(reference)
+            || current.get(reference).map(|c| c.id) == Some(category.id)
+            || sources.get(reference) == Some(&user)
+        {
+            continue;
+        }
+        let assignment = CategoryAssignment {
+            reference: transaction.reference.clone(),
+            category: category.category.clone(),
+            subcategory: category.subcategory.clone(),
+            reasoning: Some(format!("mandate {mandate}")),
+            confidence: Some(1.0),
+            source: ChangeSource::Mandate,
+        };
+        Mutation::assign_category(conn, assignment).await?;
+        categorized += 1;
+    }
+    Ok(categorized)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::categorization::{MandateLearning, apply_mandates, apply_rules, learn_mandate};
+    use crate::import::paypal::PAYPAL_CREDITOR_ID;
+    use crate::service::test::{account, db, transaction};
+    use crate::service::{
+        CategorizationRuleInput, CategoryAssignment, ChangeSource, MandateConflictStatus, Mutation, Query,
+        ServiceError, SplitInput, TransactionFilter,
+    };
+    use categorizer::rules::Criteria;
+
+    #[tokio::test]
+    async fn test_rules_categorize_before_the_model() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        let mut rent = transaction("rent", "A1", -950.0);
+        rent.counterparty_iban = "DE89370400440532013000".to_string();
+        let mut broadcasting = transaction("rbb", "A1", -55.08);
+        broadcasting.remittance_info = "Rundfunk 04.2025-06.2025 Beitragsnr. 123456789".to_string();
+        // Would match the rent rule, but its parts have their own categories.
+        let mut split = transaction("split", "A1", -50.0);
+        split.counterparty_iban = "DE89370400440532013000".to_string();
+        for input in [rent, broadcasting, split, transaction("other", "A1", -20.0)] {
+            Mutation::upsert_transaction(&db, input, ChangeSource::Import).await.unwrap();
+        }
+        let parts = ["groceries", "groceries"].map(|subcategory| SplitInput {
+            amount: -25.0,
+            category: "food".to_string(),
+            subcategory: subcategory.to_string(),
+            note: None,
+            tag: None,
+        });
+        Mutation::split_transaction(&db, "split", parts.to_vec(), ChangeSource::User).await.unwrap();
+
+        let rule = |name: &str, subcategory: &str, criteria: Criteria| CategorizationRuleInput {
+            name: name.to_string(),
+            priority: 0,
+            category: "housing".to_string(),
+            subcategory: subcategory.to_string(),
+            criteria,
+        };
+        let by_iban = Criteria {
+            iban: Some("DE89 3704 0044 0532 0130 00".to_string()),
+            ..Default::default()
+        };
+        let rent = CategorizationRuleInput {
+            priority: 10,
+            ..rule("Miete", "mortgage", by_iban)
+        };
+        Mutation::save_categorization_rule(&db, None, rent).await.unwrap();
+        let by_pattern = Criteria {
+            remittance_pattern: Some(r"^rundfunk\b".to_string()),
+            ..Default::default()
+        };
+        let broadcasting = rule("Rundfunk", "household_repairs", by_pattern.clone());
+        let saved = Mutation::save_categorization_rule(&db, None, broadcasting).await.unwrap();
+        let renamed = rule("Rundfunkbeitrag", "home_insurance", by_pattern);
+        Mutation::save_categorization_rule(&db, Some(saved.id), renamed).await.unwrap();
+        let everything = rule("Alles", "mortgage", Criteria::default());
+        let invalid = Mutation::save_categorization_rule(&db, None, everything).await;
+        assert!(matches!(invalid, Err(ServiceError::InvalidCategorizationRule(_))));
+
+        assert_eq!(apply_rules(&db).await.unwrap(), 2);
+        let categories = Query::categories_by_reference(&db).await.unwrap();
+        assert_eq!(categories["rent"].subcategory, "mortgage");
+        assert_eq!(categories["rbb"].subcategory, "home_insurance");
+        let left = Query::uncategorized(&db, None).await.unwrap();
+        assert_eq!(left.iter().map(|t| t.reference.as_str()).collect::<Vec<_>>(), ["other"]);
+        let history = Query::history(&db, "rbb").await.unwrap();
+        assert!(history.iter().any(|h| h.source == format!("rule:{}", saved.id)));
+        assert_eq!(apply_rules(&db).await.unwrap(), 0);
+        assert_eq!(Query::transactions(&db, &TransactionFilter::default()).await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_mandates_categorize_direct_debits() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        let debit = |reference: &str, mandate: &str, creditor_id: &str| {
+            let mut debit = transaction(reference, "A1", -30.0);
+            debit.creditor_mandate_id = mandate.to_string();
+            debit.creditor_id = creditor_id.to_string();
+            debit
+        };
+        let insurer = "DE98ZZZ09999999999";
+        for input in [
+            debit("jan", "M-1", insurer),
+            debit("feb", "M-1", insurer),
+            debit("mar", "M-1", insurer),
+            debit("pp", "PP-1", PAYPAL_CREDITOR_ID),
+        ] {
+            Mutation::upsert_transaction(&db, input, ChangeSource::Import).await.unwrap();
+        }
+        let assign = |reference: &str, subcategory: &str, source: ChangeSource| CategoryAssignment {
+            reference: reference.to_string(),
+            category: "housing".to_string(),
+            subcategory: subcategory.to_string(),
+            reasoning: None,
+            confidence: None,
+            source,
+        };
+        Mutation::assign_category(&db, assign("jan", "mortgage", ChangeSource::Categorizer)).await.unwrap();
+        let part = |amount: f64| SplitInput {
+            amount,
+            category: "food".to_string(),
+            subcategory: "groceries".to_string(),
+            note: None,
+            tag: None,
+        };
+        Mutation::split_transaction(&db, "mar", vec![part(-10.0), part(-20.0)], ChangeSource::User)
+            .await
+            .unwrap();
+        Mutation::assign_category(&db, assign("pp", "mortgage", ChangeSource::User)).await.unwrap();
+        assert_eq!(learn_mandate(&db, "pp").await.unwrap(), MandateLearning::Skipped);
+        assert_eq!(learn_mandate(&db, "mar").await.unwrap(), MandateLearning::Skipped);
+
+        // The user's category goes to past debits, whatever categorized them.
+        Mutation::assign_category(&db, assign("feb", "home_insurance", ChangeSource::User)).await.unwrap();
+        assert_eq!(learn_mandate(&db, "feb").await.unwrap(), MandateLearning::Learned { applied: 1 });
+        let mandates = Query::mandate_categories(&db).await.unwrap();
+        assert_eq!(mandates.len(), 1);
+        assert_eq!(mandates[0].1.subcategory, "home_insurance");
+        // And to future ones.
+        Mutation::upsert_transaction(&db, debit("apr", "M-1", insurer), ChangeSource::Import).await.unwrap();
+        assert_eq!(apply_mandates(&db).await.unwrap(), 1);
+        let categories = Query::categories_by_reference(&db).await.unwrap();
+        for reference in ["jan", "feb", "apr"] {
+            assert_eq!(categories[reference].subcategory, "home_insurance");
+        }
+        assert!(!categories.contains_key("mar"));
+        assert_eq!(Query::splits(&db, "mar").await.unwrap().len(), 2);
+        let history = Query::history(&db, "apr").await.unwrap();
+        assert!(history.iter().any(|h| h.source == "mandate"));
+
+        // A debit moved elsewhere is a conflict, not a new mapping.
+        Mutation::assign_category(&db, assign("apr", "household_repairs", ChangeSource::User)).await.unwrap();
+        let MandateLearning::Conflict(conflict) = learn_mandate(&db, "apr").await.unwrap() else {
+            panic!("expected a conflict");
+        };
+        assert_eq!(apply_mandates(&db).await.unwrap(), 0);
+        let open = Query::mandate_conflicts(&db, Some(MandateConflictStatus::Open)).await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].1.subcategory, "household_repairs");
+        assert_eq!(Query::mandate_categories(&db).await.unwrap()[0].1.subcategory, "home_insurance");
+
+        let resolved = Mutation::resolve_mandate_conflict(&db, conflict.id, true).await.unwrap();
+        assert_eq!(resolved.status, MandateConflictStatus::Remapped.to_string());
+        // `feb` was the user's choice and stays; `jan` follows the mandate.
+        assert_eq!(apply_mandates(&db).await.unwrap(), 1);
+        let categories = Query::categories_by_reference(&db).await.unwrap();
+        assert_eq!(categories["jan"].subcategory, "household_repairs");
+        assert_eq!(categories["feb"].subcategory, "home_insurance");
+        let again = Mutation::resolve_mandate_conflict(&db, conflict.id, false).await;
+        assert!(matches!(again, Err(ServiceError::MandateConflictNotFound(_))));
+        assert!(Query::mandate_conflicts(&db, Some(MandateConflictStatus::Open)).await.unwrap().is_empty());
+        assert_eq!(Query::transactions(&db, &TransactionFilter::default()).await.unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_debits_keep_their_category() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        let debit = |reference: &str| {
+            let mut debit = transaction(reference, "A1", -30.0);
+            debit.creditor_mandate_id = "M-1".to_string();
+            debit
+        };
+        for reference in ["jan", "feb", "mar"] {
+            Mutation::upsert_transaction(&db, debit(reference), ChangeSource::Import).await.unwrap();
+        }
+        let assign = |reference: &str, subcategory: &str, source: ChangeSource| CategoryAssignment {
+            reference: reference.to_string(),
+            category: "housing".to_string(),
+            subcategory: subcategory.to_string(),
+            reasoning: None,
+            confidence: None,
+            source,
+        };
+        Mutation::assign_category(&db, assign("jan", "home_insurance", ChangeSource::User)).await.unwrap();
+        assert_eq!(learn_mandate(&db, "jan").await.unwrap(), MandateLearning::Learned { applied: 2 });
+
+        // The user accepts the categorizer's answers where they disagree with
+        // the mandate, once by confirming and once by setting them again.
+        for reference in ["feb", "mar"] {
+            Mutation::assign_category(&db, assign(reference, "mortgage", ChangeSource::Categorizer))
+                .await
+                .unwrap();
+        }
+        Mutation::confirm_category(&db, "feb", ChangeSource::User).await.unwrap();
+        assert!(matches!(learn_mandate(&db, "feb").await.unwrap(), MandateLearning::Conflict(_)));
+        Mutation::assign_category(&db, assign("mar", "mortgage", ChangeSource::User)).await.unwrap();
+        assert!(matches!(learn_mandate(&db, "mar").await.unwrap(), MandateLearning::Conflict(_)));
+
+        // The next import categorizes only the new debit.
+        Mutation::upsert_transaction(&db, debit("apr"), ChangeSource::Import).await.unwrap();
+        assert_eq!(apply_mandates(&db).await.unwrap(), 1);
+        let categories = Query::categories_by_reference(&db).await.unwrap();
+        assert_eq!(categories["feb"].subcategory, "mortgage");
+        assert_eq!(categories["mar"].subcategory, "mortgage");
+        assert_eq!(categories["apr"].subcategory, "home_insurance");
+        let open = Query::mandate_conflicts(&db, Some(MandateConflictStatus::Open)).await.unwrap();
+        assert_eq!(open.len(), 2);
+
+        Mutation::upsert_transaction(&db, debit("may"), ChangeSource::Import).await.unwrap();
+        let uncategorized = Mutation::confirm_category(&db, "may", ChangeSource::User).await;
+        assert!(matches!(uncategorized, Err(ServiceError::InvalidTransaction { .. })));
+    }
+}
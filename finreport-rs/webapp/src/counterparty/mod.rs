@@ -0,0 +1,300 @@
+//! Canonical payees. Banks spell the same merchant in many ways ("REWE Markt
+//! GmbH", "REWE SAGT DANKE 1234", card descriptors with the city appended),
+//! so every booking is resolved to a counterparty by its rules: the IBAN or
+//! SEPA creditor ID of the other party, a regex over the payee name, or an
+//! alias, a payee name normalized by [`normalize`]. Names no rule knows get a
+//! counterparty of their own; merging counterparties moves their aliases, so
+//! later bookings follow.
+
+use crate::export;
+use crate::import::paypal;
+use crate::report::Totals;
+use crate::service::{CounterpartyRuleKind, Mutation, Query, ServiceError, TransactionFilter};
+use entity::entities::{account_transactions, counterparties, counterparty_rules};
+use regex::{Regex, RegexBuilder};
+use sea_orm::DbConn;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use tracing::warn;
+
+/// Words that do not tell who was paid: legal forms, card schemes and
+/// payment terminals, and what supermarkets print on receipts.
+const NOISE: [&str; 22] = [
+    "gmbh", "mbh", "ag", "kg", "ohg", "ug", "se", "co", "haftungsbeschraenkt", "inc", "ltd", "llc", "visa",
+    "mastercard", "maestro", "girocard", "kartenzahlung", "sumup", "markt", "filiale", "sagt", "danke",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResolveSummary {
+    /// Transactions whose counterparty changed.
+    pub changed: usize,
+    /// Payee names no rule knew. Each got an alias, on a new counterparty
+    /// unless one with the name existed.
+    pub created: usize,
+}
+
+/// Spending with one counterparty.
+#[derive(Debug, Clone)]
+pub struct PayeeTotal {
+    pub counterparty: counterparties::Model,
+    pub totals: Totals,
+}
+
+/// The rules of all counterparties, ready to match.
+#[derive(Debug, Default)]
+pub struct Resolver {
+    ibans: HashMap<String, i32>,
+    creditor_ids: HashMap<String, i32>,
+    regexes: Vec<(Regex, i32)>,
+    aliases: HashMap<String, i32>,
+}
+
+impl Resolver {
+    /// Rules that do not parse any more are skipped with a warning.
+    pub fn new(rules: &[counterparty_rules::Model]) -> Resolver {
+        let mut resolver = Resolver::default();
+        for rule in rules {
+            let id = rule.counterparty_id;
+            let pattern = rule.pattern.clone();
+            match CounterpartyRuleKind::ALL.into_iter().find(|k| k.to_string() == rule.kind) {
+                Some(CounterpartyRuleKind::Alias) => {
+                    resolver.aliases.insert(pattern, id);
+                }
+                Some(CounterpartyRuleKind::Iban) => {
+                    resolver.ibans.insert(pattern, id);
+                }
+                Some(CounterpartyRuleKind::CreditorId) => {
+                    resolver.creditor_ids.insert(pattern, id);
+                }
+                Some(CounterpartyRuleKind::Regex) => match regex(&pattern) {
+                    Ok(regex) => resolver.regexes.push((regex, id)),
+                    Err(e) => warn!(rule = rule.id, %e, "[counterparty] skipping rule"),
+                },
+                None => warn!(rule = rule.id, kind = rule.kind, "[counterparty] unknown rule kind"),
+            }
+        }
+        resolver
+    }
+
+    /// The counterparty of a booking with payee `name`. IBAN and creditor ID
+    /// rules win over regexes, regexes over aliases.
+    pub fn resolve(&self, transaction: &account_transactions::Model, name: &str) -> Option<i32> {
+        let by_iban = self.ibans.get(&compact(&transaction.counterparty_iban));
+        let by_creditor_id = self.creditor_ids.get(&compact(&transaction.creditor_id));
+        by_iban
+            .or(by_creditor_id)
+            .copied()
+            .or_else(|| self.regexes.iter().find(|(regex, _)| regex.is_match(name)).map(|(_, id)| *id))
+            .or_else(|| self.aliases.get(&normalize(name)).copied())
+    }
+}
+
+/// Lower case words of a payee name without umlauts, numbers, single
+/// letters and [`NOISE`], and without the location card descriptors append
+/// after `//`. Falls back to the lower case name when nothing is left.
+pub fn normalize(name: &str) -> String {
+    let name = name.split("//").next().unwrap_or_default().to_lowercase();
+    let folded = name.replace('ä', "ae").replace('ö', "oe").replace('ü', "ue").replace('ß', "ss");
+    let words: Vec<&str> = folded
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() > 1 && !w.chars().any(|c| c.is_numeric()) && !NOISE.contains(w))
+        .collect();
+    if words.is_empty() {
+        return name.split_whitespace().collect::<Vec<_>>().join(" ");
+    }
+    words.join(" ")
+}
+
+/// The pattern of a rule as stored: aliases normalized, IBANs and creditor
+/// IDs upper case without spaces, regexes checked to compile.
+pub fn rule_pattern(kind: CounterpartyRuleKind, pattern: &str) -> Result<String, ServiceError> {
+    let invalid = |message: &str| ServiceError::InvalidCounterparty(format!("{kind} `{pattern}` {message}"));
+    let pattern = match kind {
+        CounterpartyRuleKind::Alias => normalize(pattern),
+        CounterpartyRuleKind::Regex => {
+            regex(pattern).map_err(|e| invalid(&format!("does not compile: {e}")))?;
+            pattern.to_string()
+        }
+        CounterpartyRuleKind::Iban => {
+            let iban = compact(pattern);
+            let valid = (15..=34).contains(&iban.len())
+                && iban.chars().take(2).all(|c| c.is_ascii_alphabetic())
+                && iban.chars().all(|c| c.is_ascii_alphanumeric());
+            if !valid {
+                return Err(invalid("is no IBAN"));
+            }
+            iban
+        }
+        CounterpartyRuleKind::CreditorId => compact(pattern),
+    };
+    if pattern.is_empty() {
+        return Err(invalid("is empty"));
+    }
+    Ok(pattern)
+}
+
+/// Resolve every transaction to a counterparty, creating counterparties for
+/// payee names no rule knows. Existing links follow changed rules; the payee
+/// name of PayPal debits is the merchant.
+pub async fn resolve(conn: &DbConn) -> Result<ResolveSummary, ServiceError> {
+    let transactions = Query::transactions(conn, &TransactionFilter::default()).await?;
+    let merchants = paypal::merchants_by_reference(conn).await?;
+    let remittances = Query::remittances_by_reference(conn).await?;
+    let resolver = Resolver::new(&Query::counterparty_rules(conn).await?);
+
+    let mut links: HashMap<String, i32> = HashMap::new();
+    // Unknown aliases with the spellings seen for them, by transaction.
+    let mut unknown: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    for t in &transactions {
+        let parsed = remittances.get(&t.reference);
+        // A payment service collecting for a shop names the shop as the
+        // ultimate creditor, and some banks leave the creditor ID in the text.
+        let name = merchants
+            .get(&t.reference)
+            .or_else(|| parsed.and_then(|p| p.ultimate_creditor.as_ref()))
+            .cloned()
+            .unwrap_or_else(|| export::payee(t));
+        let mut t = Cow::Borrowed(t);
+        if let Some(creditor_id) = parsed.and_then(|p| p.creditor_id.as_ref())
+            && t.creditor_id.trim().is_empty()
+        {
+            t.to_mut().creditor_id = creditor_id.clone();
+        }
+        if let Some(id) = resolver.resolve(&t, &name) {
+            links.insert(t.reference.clone(), id);
+        } else if !name.trim().is_empty() {
+            unknown.entry(normalize(&name)).or_default().push((t.reference.clone(), display_name(&name)));
+        }
+    }
+
+    let names: BTreeMap<String, String> = unknown
+        .iter()
+        .map(|(alias, seen)| (alias.clone(), most_common(seen.iter().map(|(_, name)| name))))
+        .collect();
+    let created = Mutation::create_counterparties(conn, &names).await?;
+    for (alias, seen) in unknown {
+        for (reference, _) in seen {
+            links.insert(reference, created[&alias]);
+        }
+    }
+    Ok(ResolveSummary {
+        changed: Mutation::set_counterparties(conn, &links).await?,
+        created: names.len(),
+    })
+}
+
+/// Counterparties by the money spent with them in `filter`, at most `limit`.
+/// Refunds count as income and do not reduce the expenses.
+pub async fn top_payees(
+    conn: &DbConn,
+    filter: &TransactionFilter,
+    limit: usize,
+) -> Result<Vec<PayeeTotal>, ServiceError> {
+    let counterparties = Query::counterparties_by_reference(conn).await?;
+    let mut totals: HashMap<i32, PayeeTotal> = HashMap::new();
+    for t in Query::transactions(conn, filter).await? {
+        let Some(counterparty) = counterparties.get(&t.reference) else {
+            continue;
+        };
+        totals
+            .entry(counterparty.id)
+            .or_insert_with(|| PayeeTotal {
+                counterparty: counterparty.clone(),
+                totals: Totals::default(),
+            })
+            .totals
+            .add(t.amount);
+    }
+    let mut payees: Vec<PayeeTotal> = totals.into_values().filter(|p| p.totals.expenses > 0.0).collect();
+    payees.sort_by(|a, b| {
+        b.totals
+            .expenses
+            .total_cmp(&a.totals.expenses)
+            .then_with(|| a.counterparty.name.cmp(&b.counterparty.name))
+    });
+    payees.truncate(limit);
+    Ok(payees)
+}
+
+fn regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+/// Upper case without whitespace, as IBANs and creditor IDs are compared.
+fn compact(value: &str) -> String {
+    value.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase()
+}
+
+/// A payee name as a counterparty name: without the card descriptor
+/// location and with single spaces.
+fn display_name(name: &str) -> String {
+    let name = name.split("//").next().unwrap_or_default();
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The most frequent name, the shortest among equally frequent ones.
+fn most_common<'a>(names: impl Iterator<Item = &'a String>) -> String {
+    let mut counts: HashMap<&String, usize> = HashMap::new();
+    for name in names {
+        *counts.entry(name).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|(a, m), (b, n)| m.cmp(n).then_with(|| b.len().cmp(&a.len())).then_with(|| b.cmp(a)))
+        .map(|(name, _)| name.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::counterparty::{normalize, rule_pattern, Resolver};
+    use crate::service::CounterpartyRuleKind;
+    use crate::service::test::booking;
+    use entity::entities::account_transactions;
+    use entity::entities::counterparty_rules::Model;
+
+    fn rule(id: i32, counterparty_id: i32, kind: CounterpartyRuleKind, pattern: &str) -> Model {
+        Model {
+            id,
+            counterparty_id,
+            kind: kind.to_string(),
+            pattern: pattern.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_spellings() {
+        assert_eq!(normalize("REWE Markt GmbH"), "rewe");
+        assert_eq!(normalize("REWE SAGT DANKE 1234"), "rewe");
+        assert_eq!(normalize("VISA Bäckerei Müller e.K.//Berlin/DE"), "baeckerei mueller");
+        assert_eq!(normalize("Stadtwerke Nord AG & Co. KG"), "stadtwerke nord");
+        assert_eq!(normalize("1&1"), "1&1");
+    }
+
+    #[test]
+    fn test_rules_by_precedence() {
+        let resolver = Resolver::new(&[
+            rule(1, 10, CounterpartyRuleKind::Alias, "rewe"),
+            rule(2, 20, CounterpartyRuleKind::Regex, "^rewe.*berlin"),
+            rule(3, 30, CounterpartyRuleKind::CreditorId, "DE98ZZZ09999999999"),
+            rule(4, 40, CounterpartyRuleKind::Iban, "DE89370400440532013000"),
+            rule(5, 50, CounterpartyRuleKind::Regex, "("),
+        ]);
+        let debit = |creditor_id: &str, counterparty_iban: &str| account_transactions::Model {
+            creditor_id: creditor_id.to_string(),
+            counterparty_iban: counterparty_iban.to_string(),
+            ..booking("r1", "A1", 2, -10.0)
+        };
+        assert_eq!(resolver.resolve(&debit("", ""), "REWE Markt GmbH"), Some(10));
+        assert_eq!(resolver.resolve(&debit("", ""), "Rewe Berlin-Mitte"), Some(20));
+        assert_eq!(resolver.resolve(&debit("de98zzz09999999999", ""), "REWE"), Some(30));
+        let both = debit("DE98ZZZ09999999999", "DE89 3704 0044 0532 0130 00");
+        assert_eq!(resolver.resolve(&both, ""), Some(40));
+        assert_eq!(resolver.resolve(&debit("", ""), "Edeka"), None);
+
+        let iban = rule_pattern(CounterpartyRuleKind::Iban, "de89 3704 0044 0532 0130 00").unwrap();
+        assert_eq!(iban, "DE89370400440532013000");
+        assert!(rule_pattern(CounterpartyRuleKind::Iban, "12345").is_err());
+        assert!(rule_pattern(CounterpartyRuleKind::Regex, "(").is_err());
+    }
+}
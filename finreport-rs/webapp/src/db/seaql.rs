@@ -1,13 +1,51 @@
 use migration::{Migrator, MigratorTrait};
-use sea_orm::{Database, DatabaseConnection};
+use sea_orm::{Database, DatabaseConnection, DbBackend};
+
+/// Backend selected by the `database_url` scheme: `postgres://` (or
+/// `postgresql://`) and `sqlite://`.
+pub fn backend_for_url(database_url: &str) -> Option<DbBackend> {
+    match database_url.split_once(':').map(|(scheme, _)| scheme) {
+        Some("postgres" | "postgresql") => Some(DbBackend::Postgres),
+        Some("sqlite") => Some(DbBackend::Sqlite),
+        _ => None,
+    }
+}
 
 pub async fn init_db(database_url: &str) -> Result<DatabaseConnection, std::io::Error> {
-    let conn = Database::connect(database_url)
-        .await
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    let conn = connect(database_url).await?;
     Migrator::up(&conn, None)
         .await
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        .map_err(std::io::Error::other)?;
 
     Ok(conn)
 }
+
+/// Connect without migrating, for tools that pick the schema version
+/// themselves, like restoring a backup.
+pub async fn connect(database_url: &str) -> Result<DatabaseConnection, std::io::Error> {
+    let url = match backend_for_url(database_url) {
+        Some(DbBackend::Sqlite) => sqlite_url(database_url),
+        Some(_) => database_url.to_string(),
+        None => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "unsupported database_url scheme, expected postgres:// or sqlite://",
+            ))
+        }
+    };
+    Database::connect(url)
+        .await
+        .map_err(std::io::Error::other)
+}
+
+/// A single-user install is just a file: create it on first start unless the
+/// URL asks for another mode.
+fn sqlite_url(database_url: &str) -> String {
+    if database_url.contains("mode=") || database_url.contains(":memory:") {
+        database_url.to_string()
+    } else if database_url.contains('?') {
+        format!("{database_url}&mode=rwc")
+    } else {
+        format!("{database_url}?mode=rwc")
+    }
+}
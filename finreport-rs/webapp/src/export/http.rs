@@ -0,0 +1,100 @@
+use crate::export::{load, render, ExportFormat, ExportSelection};
+use crate::filter::FilterError;
+use crate::service::{Query, ServiceError};
+use crate::views::Owner;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDate;
+use sea_orm::DatabaseConnection;
+use serde::Deserialize;
+use tracing::error;
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// Comma separated `account.account_id`s; all accounts when absent.
+    accounts: Option<String>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    /// Filter expression, see [`crate::filter`].
+    filter: Option<String>,
+    /// Id of a saved view of the requesting user.
+    view: Option<i32>,
+    /// Comma separated tags a transaction must all carry.
+    tags: Option<String>,
+    /// Leave out internal transfers between own accounts.
+    #[serde(default)]
+    without_transfers: bool,
+}
+
+impl TryFrom<ExportQuery> for ExportSelection {
+    type Error = FilterError;
+
+    fn try_from(query: ExportQuery) -> Result<Self, Self::Error> {
+        Ok(ExportSelection {
+            accounts: list(query.accounts),
+            from: query.from,
+            to: query.to,
+            query: query.filter.as_deref().map(str::parse).transpose()?,
+            tags: list(query.tags),
+            without_transfers: query.without_transfers,
+        })
+    }
+}
+
+fn list(value: Option<String>) -> Vec<String> {
+    value
+        .map(|v| v.split(',').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// `GET /export/transactions.{format}?accounts=..&from=YYYY-MM-DD&to=YYYY-MM-DD&filter=..&view=..&tags=..`
+/// with `&without_transfers=true` to leave out internal transfers.
+#[get("/export/transactions.{format}")]
+pub async fn transactions(
+    req: HttpRequest,
+    conn: web::Data<DatabaseConnection>,
+    format: web::Path<String>,
+    query: web::Query<ExportQuery>,
+) -> impl Responder {
+    let format: ExportFormat = match format.parse() {
+        Ok(f) => f,
+        Err(e) => return HttpResponse::NotFound().body(e.to_string()),
+    };
+    let query = query.into_inner();
+    let view = query.view;
+    let mut selection = match ExportSelection::try_from(query) {
+        Ok(selection) => selection,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    if let Some(id) = view {
+        let owner = Owner::from_headers(req.headers());
+        selection = match Query::saved_view(&conn, &owner.0, id).await.and_then(|v| selection.with_view(&v)) {
+            Ok(selection) => selection,
+            Err(e @ ServiceError::ViewNotFound(_)) => return HttpResponse::NotFound().body(e.to_string()),
+            Err(e) => {
+                error!(%e, "[export] failed to apply view {id}");
+                return HttpResponse::InternalServerError().finish();
+            }
+        };
+    }
+    let body = match load(&conn, &selection).await {
+        Ok(data) => render(format, &data),
+        Err(e) => Err(e),
+    };
+    match body {
+        Ok(body) => HttpResponse::Ok()
+            .content_type(format.content_type())
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(format!(
+                    "transactions.{}",
+                    format.extension()
+                ))],
+            })
+            .body(body),
+        Err(e) => {
+            error!(%e, "[export] failed to export transactions");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
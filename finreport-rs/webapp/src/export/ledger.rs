@@ -0,0 +1,417 @@
+//! Plain-text accounting journals for beancount and hledger (which ledger
+//! reads as well).
+//!
+//! Output is deterministic: accounts and transactions are sorted and every
+//! transaction carries its bank `reference` as metadata, so exporting the
+//! same range twice yields the same file and later exports diff cleanly.
+
+use crate::export::{ExportAccount, ExportData, ExportPart, ExportTransaction};
+use chrono::{Days, NaiveDate};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+const OPENING_BALANCES: &str = "Equity:Opening-Balances";
+/// Both legs of an internal transfer post here, so it nets to zero once
+/// both are booked.
+const TRANSFERS: &str = "Assets:Transfers";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Beancount,
+    Hledger,
+}
+
+pub fn render(data: &ExportData, dialect: Dialect) -> String {
+    let accounts: Vec<(String, &ExportAccount)> = data
+        .accounts
+        .iter()
+        .map(|a| (asset_account(a), a))
+        .collect();
+    let first_date = accounts
+        .iter()
+        .flat_map(|(_, a)| a.transactions.iter().map(|t| t.transaction.booking_date))
+        .chain(data.selection.from)
+        .min();
+
+    let mut out = String::new();
+    if dialect == Dialect::Beancount {
+        out.push_str("option \"operating_currency\" \"EUR\"\n\n");
+    }
+
+    let mut names: BTreeSet<String> = accounts.iter().map(|(name, _)| name.clone()).collect();
+    names.insert(OPENING_BALANCES.to_string());
+    for t in accounts.iter().flat_map(|(_, a)| &a.transactions) {
+        names.extend(counter_postings(t).into_iter().map(|(name, _)| name));
+    }
+    for name in &names {
+        match (dialect, first_date) {
+            (Dialect::Beancount, Some(date)) => {
+                let _ = writeln!(out, "{} open {name}", opening_date(date));
+            }
+            (Dialect::Beancount, None) => {}
+            (Dialect::Hledger, _) => {
+                let _ = writeln!(out, "account {name}");
+            }
+        }
+    }
+
+    for (name, account) in &accounts {
+        out.push('\n');
+        render_account(&mut out, dialect, name, account, data.selection.from);
+    }
+    out
+}
+
+fn render_account(
+    out: &mut String,
+    dialect: Dialect,
+    name: &str,
+    account: &ExportAccount,
+    from: Option<NaiveDate>,
+) {
+    let mut transactions: Vec<&ExportTransaction> = account.transactions.iter().collect();
+    transactions.sort_by(|a, b| {
+        (a.transaction.booking_date, &a.transaction.reference)
+            .cmp(&(b.transaction.booking_date, &b.transaction.reference))
+    });
+    let balances: Vec<_> = account
+        .balances
+        .iter()
+        .filter(|b| from.is_none_or(|from| b.date >= from))
+        .collect();
+
+    // Journals covering a date range need the balance carried into it, or
+    // every assertion would fail. It is derived from the latest snapshot.
+    let start = transactions
+        .first()
+        .map(|t| t.transaction.booking_date)
+        .or(from);
+    if let (Some(start), Some(latest)) = (start, balances.last()) {
+        let booked: f64 = transactions
+            .iter()
+            .filter(|t| t.transaction.booking_date <= latest.date)
+            .map(|t| t.transaction.amount)
+            .sum();
+        let opening = latest.amount - booked;
+        let _ = writeln!(out, "{} * \"Opening balance\"", opening_date(start));
+        let _ = writeln!(out, "  {name}  {} EUR", amount(opening));
+        let _ = writeln!(out, "  {OPENING_BALANCES}\n");
+    }
+
+    let mut pending = balances.iter().peekable();
+    for t in &transactions {
+        let date = t.transaction.booking_date;
+        while let Some(balance) = pending.next_if(|b| b.date < date) {
+            render_assertion(out, dialect, name, balance.date, balance.amount);
+        }
+        render_transaction(out, dialect, name, t);
+    }
+    for balance in pending {
+        render_assertion(out, dialect, name, balance.date, balance.amount);
+    }
+}
+
+fn render_transaction(out: &mut String, dialect: Dialect, asset: &str, t: &ExportTransaction) {
+    let tx = &t.transaction;
+    let flag = if tx.booking_status == "BOOKED" {
+        "*"
+    } else {
+        "!"
+    };
+    match dialect {
+        Dialect::Beancount => {
+            let tags: String = t.tags.iter().map(|tag| format!(" #{}", tag_name(tag))).collect();
+            let _ = writeln!(
+                out,
+                "{} {flag} \"{}\" \"{}\"{tags}\n  reference: \"{}\"",
+                tx.booking_date,
+                quoted(&t.payee),
+                quoted(&tx.remittance_info),
+                quoted(&tx.reference),
+            );
+            if let Some(note) = &t.note {
+                let _ = writeln!(out, "  note: \"{}\"", quoted(note));
+            }
+        }
+        Dialect::Hledger => {
+            let tags: String = t.tags.iter().map(|tag| format!(", {}:", tag_name(tag))).collect();
+            let _ = writeln!(
+                out,
+                "{} {flag} {} | {}  ; reference:{}{tags}",
+                tx.booking_date,
+                single_line(&t.payee).replace('|', "/"),
+                single_line(&tx.remittance_info),
+                tx.reference,
+            );
+            if let Some(note) = &t.note {
+                let _ = writeln!(out, "  ; {}", single_line(note));
+            }
+        }
+    }
+    let _ = writeln!(out, "  {asset}  {} EUR", amount(tx.amount));
+    match counter_postings(t).as_slice() {
+        [(name, _)] => {
+            let _ = writeln!(out, "  {name}");
+        }
+        postings => {
+            for (name, value) in postings {
+                let _ = writeln!(out, "  {name}  {} EUR", amount(-value));
+            }
+        }
+    }
+    out.push('\n');
+}
+
+/// Accounts and amounts balancing the asset posting: the transfer account
+/// for an unsplit internal transfer, the category of each part otherwise.
+fn counter_postings(t: &ExportTransaction) -> Vec<(String, f64)> {
+    if t.transfer.is_some() && t.splits.is_empty() {
+        return vec![(TRANSFERS.to_string(), t.transaction.amount)];
+    }
+    t.parts()
+        .iter()
+        .map(|part| (category_account(part), part.amount))
+        .collect()
+}
+
+/// Snapshots are taken after the day's bookings. beancount checks `balance`
+/// at the start of its date, hledger after the posting it is attached to.
+fn render_assertion(
+    out: &mut String,
+    dialect: Dialect,
+    asset: &str,
+    date: NaiveDate,
+    balance: f64,
+) {
+    match dialect {
+        Dialect::Beancount => {
+            let _ = writeln!(
+                out,
+                "{} balance {asset}  {} EUR\n",
+                date + Days::new(1),
+                amount(balance)
+            );
+        }
+        Dialect::Hledger => {
+            let _ = writeln!(
+                out,
+                "{date} * Balance snapshot\n  {asset}  0 EUR = {} EUR\n",
+                amount(balance)
+            );
+        }
+    }
+}
+
+fn opening_date(date: NaiveDate) -> NaiveDate {
+    date - Days::new(1)
+}
+
+fn asset_account(account: &ExportAccount) -> String {
+    format!(
+        "Assets:{}:{}",
+        component(&account.account.institute),
+        component(&format!(
+            "{} {}",
+            account.account.account_type, account.account.display_id
+        ))
+    )
+}
+
+/// `Expenses:<Category>:<Subcategory>` for debits and `Income:...` for
+/// credits, derived from the `categories` taxonomy.
+fn category_account(part: &ExportPart) -> String {
+    let root = if part.amount < 0.0 {
+        "Expenses"
+    } else {
+        "Income"
+    };
+    match (&part.category, &part.subcategory) {
+        (Some(category), Some(subcategory)) => {
+            format!("{root}:{}:{}", component(category), component(subcategory))
+        }
+        (Some(category), None) => format!("{root}:{}", component(category)),
+        _ => format!("{root}:Uncategorized"),
+    }
+}
+
+/// Account name components must start with an upper case letter or digit
+/// and may only contain letters, digits and dashes.
+fn component(value: &str) -> String {
+    let ascii = value
+        .replace('ä', "ae")
+        .replace('ö', "oe")
+        .replace('ü', "ue")
+        .replace('Ä', "Ae")
+        .replace('Ö', "Oe")
+        .replace('Ü', "Ue")
+        .replace('ß', "ss");
+    let words: Vec<String> = ascii
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect();
+    if words.is_empty() {
+        "Unknown".to_string()
+    } else {
+        words.join("-")
+    }
+}
+
+fn amount(value: f64) -> String {
+    // Avoid "-0.00" for amounts that round to zero.
+    format!("{:.2}", if value.abs() < 0.005 { 0.0 } else { value })
+}
+
+/// Tags may only hold letters, digits, `-` and `_` in both dialects.
+fn tag_name(tag: &str) -> String {
+    tag.chars()
+        .map(|c| match c.is_alphanumeric() || c == '_' {
+            true => c,
+            false => '-',
+        })
+        .collect()
+}
+
+fn single_line(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn quoted(value: &str) -> String {
+    single_line(value).replace('\\', "/").replace('"', "'")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::export::ledger::{render, Dialect};
+    use crate::export::{ExportAccount, ExportData, ExportPart, ExportSelection, ExportTransaction};
+    use chrono::NaiveDate;
+    use entity::entities::{account, account_balance, account_transactions};
+
+    fn date(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 6, d).unwrap()
+    }
+
+    fn transaction(
+        reference: &str,
+        day: u32,
+        amount: f64,
+        category: Option<&str>,
+    ) -> ExportTransaction {
+        ExportTransaction {
+            transaction: account_transactions::Model {
+                id: 0,
+                reference: reference.to_string(),
+                account_id: "A1".to_string(),
+                booking_status: "BOOKED".to_string(),
+                booking_date: date(day),
+                amount,
+                remitter: String::new(),
+                deptor: String::new(),
+                creditor: "REWE Markt GmbH".to_string(),
+                creditor_id: String::new(),
+                creditor_mandate_id: String::new(),
+                remittance_info: "Einkauf \"Filiale\"".to_string(),
+                transaction_type: String::new(),
+                counterparty_iban: String::new(),
+            },
+            payee: "REWE Markt GmbH".to_string(),
+            category: category.map(str::to_string),
+            subcategory: category.map(|_| "groceries".to_string()),
+            splits: Vec::new(),
+            tags: Vec::new(),
+            note: None,
+            transfer: None,
+        }
+    }
+
+    fn data() -> ExportData {
+        ExportData {
+            selection: ExportSelection::default(),
+            accounts: vec![ExportAccount {
+                account: account::Model {
+                    id: 1,
+                    account_id: "A1".to_string(),
+                    display_id: "1234".to_string(),
+                    account_type: "Girokonto".to_string(),
+                    iban: "DE00".to_string(),
+                    bic: "COBADEHD001".to_string(),
+                    institute: "COMDIRECT".to_string(),
+                },
+                balances: vec![account_balance::Model {
+                    id: 1,
+                    amount: 70.0,
+                    date: date(3),
+                    account_id: "A1".to_string(),
+                }],
+                transactions: vec![
+                    transaction("r2", 3, -20.0, None),
+                    transaction("r1", 2, -10.0, Some("food")),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_beancount_journal() {
+        let journal = render(&data(), Dialect::Beancount);
+        let expected = "option \"operating_currency\" \"EUR\"\n\n\
+            2025-06-01 open Assets:COMDIRECT:Girokonto-1234\n\
+            2025-06-01 open Equity:Opening-Balances\n\
+            2025-06-01 open Expenses:Food:Groceries\n\
+            2025-06-01 open Expenses:Uncategorized\n\
+            \n\
+            2025-06-01 * \"Opening balance\"\n  Assets:COMDIRECT:Girokonto-1234  100.00 EUR\n  Equity:Opening-Balances\n\n\
+            2025-06-02 * \"REWE Markt GmbH\" \"Einkauf 'Filiale'\"\n  reference: \"r1\"\n  Assets:COMDIRECT:Girokonto-1234  -10.00 EUR\n  Expenses:Food:Groceries\n\n\
+            2025-06-03 * \"REWE Markt GmbH\" \"Einkauf 'Filiale'\"\n  reference: \"r2\"\n  Assets:COMDIRECT:Girokonto-1234  -20.00 EUR\n  Expenses:Uncategorized\n\n\
+            2025-06-04 balance Assets:COMDIRECT:Girokonto-1234  70.00 EUR\n\n";
+        assert_eq!(journal, expected);
+    }
+
+    #[test]
+    fn test_hledger_assertion_follows_bookings() {
+        let journal = render(&data(), Dialect::Hledger);
+        let booking = journal.find("reference:r2").unwrap();
+        let assertion = journal.find("0 EUR = 70.00 EUR").unwrap();
+        assert!(booking < assertion, "{journal}");
+        assert!(journal.starts_with("account Assets:COMDIRECT:Girokonto-1234\n"));
+    }
+
+    #[test]
+    fn test_split_postings() {
+        let mut data = data();
+        let part = |amount: f64, subcategory: &str| ExportPart {
+            amount,
+            category: Some("food".to_string()),
+            subcategory: Some(subcategory.to_string()),
+            note: None,
+            tag: None,
+        };
+        data.accounts[0].transactions[0].splits = vec![part(-15.0, "groceries"), part(-5.0, "sweets")];
+        let journal = render(&data, Dialect::Hledger);
+        assert!(journal.contains("account Expenses:Food:Sweets\n"));
+        assert!(journal.contains(
+            "  Assets:COMDIRECT:Girokonto-1234  -20.00 EUR\n  \
+             Expenses:Food:Groceries  15.00 EUR\n  Expenses:Food:Sweets  5.00 EUR\n\n"
+        ));
+        assert!(!journal.contains("Expenses:Uncategorized"), "{journal}");
+    }
+
+    #[test]
+    fn test_tags_and_notes() {
+        let mut data = data();
+        let t = &mut data.accounts[0].transactions[0];
+        t.tags = vec!["urlaub 2025".to_string()];
+        t.note = Some("mit Anna\nteilen".to_string());
+        let beancount = render(&data, Dialect::Beancount);
+        assert!(beancount.contains("\"Einkauf 'Filiale'\" #urlaub-2025\n"), "{beancount}");
+        assert!(beancount.contains("  note: \"mit Anna teilen\"\n"));
+        let hledger = render(&data, Dialect::Hledger);
+        assert!(hledger.contains(", urlaub-2025:\n  ; mit Anna teilen\n"), "{hledger}");
+    }
+}
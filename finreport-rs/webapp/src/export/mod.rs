@@ -0,0 +1,289 @@
+pub mod http;
+pub mod ledger;
+pub mod ofx;
+pub mod qif;
+pub mod spreadsheet;
+
+use crate::import::paypal;
+use crate::service::{Query, ServiceError, TransactionFilter};
+use rust_xlsxwriter::XlsxError;
+use entity::entities::{account, account_balance, account_transactions};
+use sea_orm::{DbConn, DbErr};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Ofx,
+    Qif,
+    Beancount,
+    Hledger,
+    Csv,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Ofx => "ofx",
+            ExportFormat::Qif => "qif",
+            ExportFormat::Beancount => "beancount",
+            ExportFormat::Hledger => "journal",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Ofx => "application/x-ofx",
+            ExportFormat::Qif => "application/qif",
+            ExportFormat::Beancount | ExportFormat::Hledger => "text/plain; charset=utf-8",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Xlsx => {
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            }
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ofx" => Ok(ExportFormat::Ofx),
+            "qif" => Ok(ExportFormat::Qif),
+            "beancount" => Ok(ExportFormat::Beancount),
+            // hledger journals are read by ledger as well.
+            "hledger" | "journal" | "ledger" => Ok(ExportFormat::Hledger),
+            "csv" => Ok(ExportFormat::Csv),
+            "xlsx" => Ok(ExportFormat::Xlsx),
+            other => Err(ExportError::UnknownFormat(other.to_string())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    UnknownFormat(String),
+    Db(ServiceError),
+    Csv(csv::Error),
+    Xlsx(XlsxError),
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportError::UnknownFormat(format) => write!(f, "unknown export format `{format}`"),
+            ExportError::Db(e) => write!(f, "{e}"),
+            ExportError::Csv(e) => write!(f, "failed to write CSV: {e}"),
+            ExportError::Xlsx(e) => write!(f, "failed to write workbook: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<ServiceError> for ExportError {
+    fn from(value: ServiceError) -> Self {
+        ExportError::Db(value)
+    }
+}
+
+impl From<DbErr> for ExportError {
+    fn from(value: DbErr) -> Self {
+        ExportError::Db(value.into())
+    }
+}
+
+impl From<csv::Error> for ExportError {
+    fn from(value: csv::Error) -> Self {
+        ExportError::Csv(value)
+    }
+}
+
+impl From<XlsxError> for ExportError {
+    fn from(value: XlsxError) -> Self {
+        ExportError::Xlsx(value)
+    }
+}
+
+/// Which transactions to export.
+pub type ExportSelection = TransactionFilter;
+
+#[derive(Debug, Clone)]
+pub struct ExportTransaction {
+    pub transaction: account_transactions::Model,
+    /// Counterparty as a desktop tool would call it: the canonical name when
+    /// resolved, else the merchant of a linked PayPal payment or the raw name.
+    pub payee: String,
+    pub category: Option<String>,
+    pub subcategory: Option<String>,
+    /// Parts of a split transaction; empty unless it is split.
+    pub splits: Vec<ExportPart>,
+    /// Sorted tag names.
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+    /// Reference of the other leg when this is an internal transfer.
+    pub transfer: Option<String>,
+}
+
+/// A categorized share of a transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportPart {
+    pub amount: f64,
+    pub category: Option<String>,
+    pub subcategory: Option<String>,
+    pub note: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl ExportTransaction {
+    /// What the transaction is booked as: its split parts, or the whole
+    /// amount with the category of the transaction.
+    pub fn parts(&self) -> Vec<ExportPart> {
+        if !self.splits.is_empty() {
+            return self.splits.clone();
+        }
+        vec![ExportPart {
+            amount: self.transaction.amount,
+            category: self.category.clone(),
+            subcategory: self.subcategory.clone(),
+            note: None,
+            tag: None,
+        }]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportAccount {
+    pub account: account::Model,
+    /// Balance snapshots up to the end of the selection, oldest first.
+    pub balances: Vec<account_balance::Model>,
+    pub transactions: Vec<ExportTransaction>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportData {
+    pub selection: ExportSelection,
+    pub accounts: Vec<ExportAccount>,
+}
+
+impl ExportData {
+    /// The data without internal transfers, which are neither income nor
+    /// expenses.
+    pub fn without_transfers(&self) -> ExportData {
+        let mut data = self.clone();
+        for account in &mut data.accounts {
+            account.transactions.retain(|t| t.transfer.is_none());
+        }
+        data
+    }
+}
+
+pub async fn load(conn: &DbConn, selection: &ExportSelection) -> Result<ExportData, ExportError> {
+    let accounts: Vec<account::Model> = Query::accounts(conn)
+        .await?
+        .into_iter()
+        .filter(|a| selection.accounts.is_empty() || selection.accounts.contains(&a.account_id))
+        .collect();
+    let transactions = Query::transactions(conn, selection).await?;
+    let categories = Query::categories_by_reference(conn).await?;
+    let mut splits = Query::splits_by_reference(conn).await?;
+    let mut tags = Query::tags_by_reference(conn).await?;
+    let mut notes = Query::notes_by_reference(conn).await?;
+    let mut transfers = Query::transfers_by_reference(conn).await?;
+    let merchants = paypal::merchants_by_reference(conn).await?;
+    let counterparties = Query::counterparties_by_reference(conn).await?;
+
+    // Opening balances need the snapshots before the selection as well.
+    let balance_filter = TransactionFilter {
+        from: None,
+        ..selection.clone()
+    };
+    let mut balances: HashMap<String, Vec<account_balance::Model>> = HashMap::new();
+    for balance in Query::balances(conn, &balance_filter).await? {
+        balances.entry(balance.account_id.clone()).or_default().push(balance);
+    }
+
+    let mut by_account: HashMap<String, Vec<ExportTransaction>> = HashMap::new();
+    for transaction in transactions {
+        let (category, subcategory) = categories
+            .get(&transaction.reference)
+            .cloned()
+            .map(|c| (Some(c.category), Some(c.subcategory)))
+            .unwrap_or_default();
+        let payee = counterparties
+            .get(&transaction.reference)
+            .map(|c| c.name.clone())
+            .or_else(|| merchants.get(&transaction.reference).cloned())
+            .unwrap_or_else(|| payee(&transaction));
+        let splits = splits
+            .remove(&transaction.reference)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(split, category)| ExportPart {
+                amount: split.amount,
+                category: Some(category.category),
+                subcategory: Some(category.subcategory),
+                note: split.note,
+                tag: split.tag,
+            })
+            .collect();
+        let tags = tags.remove(&transaction.reference).unwrap_or_default();
+        let note = notes.remove(&transaction.reference);
+        let transfer = transfers.remove(&transaction.reference);
+        by_account
+            .entry(transaction.account_id.clone())
+            .or_default()
+            .push(ExportTransaction {
+                transaction,
+                payee,
+                category,
+                subcategory,
+                splits,
+                tags,
+                note,
+                transfer,
+            });
+    }
+
+    Ok(ExportData {
+        selection: selection.clone(),
+        accounts: accounts
+            .into_iter()
+            .map(|account| ExportAccount {
+                balances: balances.remove(&account.account_id).unwrap_or_default(),
+                transactions: by_account.remove(&account.account_id).unwrap_or_default(),
+                account,
+            })
+            .collect(),
+    })
+}
+
+pub fn render(format: ExportFormat, data: &ExportData) -> Result<Vec<u8>, ExportError> {
+    Ok(match format {
+        ExportFormat::Ofx => ofx::render(data).into_bytes(),
+        ExportFormat::Qif => qif::render(data).into_bytes(),
+        ExportFormat::Beancount => ledger::render(data, ledger::Dialect::Beancount).into_bytes(),
+        ExportFormat::Hledger => ledger::render(data, ledger::Dialect::Hledger).into_bytes(),
+        ExportFormat::Csv => spreadsheet::render_csv(data)?,
+        ExportFormat::Xlsx => spreadsheet::render_xlsx(data)?,
+    })
+}
+
+/// The counterparty of a booking: the payee of debits, the payer of credits.
+pub fn payee(transaction: &account_transactions::Model) -> String {
+    let candidates = if transaction.amount < 0.0 {
+        [&transaction.creditor, &transaction.remitter, &transaction.deptor]
+    } else {
+        [&transaction.remitter, &transaction.deptor, &transaction.creditor]
+    };
+    candidates
+        .into_iter()
+        .find(|c| !c.trim().is_empty())
+        .cloned()
+        .unwrap_or_default()
+}
@@ -0,0 +1,183 @@
+//! OFX 2.2 bank statements, one `STMTTRNRS` per account.
+//!
+//! OFX has no category element, so categories are only carried by the QIF
+//! export.
+
+use crate::export::{ExportAccount, ExportData};
+use chrono::NaiveDate;
+use std::fmt::Write;
+
+pub fn render(data: &ExportData) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+    out.push_str("<?OFX OFXHEADER=\"200\" VERSION=\"220\" SECURITY=\"NONE\" OLDFILEUID=\"NONE\" NEWFILEUID=\"NONE\"?>\n");
+    out.push_str("<OFX>\n");
+    let now = chrono::Local::now().format("%Y%m%d%H%M%S");
+    let _ = writeln!(
+        out,
+        "<SIGNONMSGSRSV1><SONRS><STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>\
+         <DTSERVER>{now}</DTSERVER><LANGUAGE>GER</LANGUAGE></SONRS></SIGNONMSGSRSV1>"
+    );
+    out.push_str("<BANKMSGSRSV1>\n");
+    for (i, account) in data.accounts.iter().enumerate() {
+        render_statement(&mut out, i, account, data);
+    }
+    out.push_str("</BANKMSGSRSV1>\n</OFX>\n");
+    out
+}
+
+fn render_statement(out: &mut String, index: usize, account: &ExportAccount, data: &ExportData) {
+    let dates = account.transactions.iter().map(|t| t.transaction.booking_date);
+    let start = data.selection.from.or_else(|| dates.clone().min());
+    let end = data.selection.to.or_else(|| dates.max());
+
+    let _ = write!(
+        out,
+        "<STMTTRNRS><TRNUID>{index}</TRNUID><STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>\n\
+         <STMTRS><CURDEF>EUR</CURDEF>\n\
+         <BANKACCTFROM><BANKID>{}</BANKID><ACCTID>{}</ACCTID><ACCTTYPE>{}</ACCTTYPE></BANKACCTFROM>\n\
+         <BANKTRANLIST>",
+        escape(&account.account.bic),
+        escape(&account.account.iban),
+        account_type(&account.account.account_type),
+    );
+    if let (Some(start), Some(end)) = (start, end) {
+        let _ = write!(out, "<DTSTART>{}</DTSTART><DTEND>{}</DTEND>", date(start), date(end));
+    }
+    out.push('\n');
+    for t in &account.transactions {
+        let tx = &t.transaction;
+        let _ = writeln!(
+            out,
+            "<STMTTRN><TRNTYPE>{}</TRNTYPE><DTPOSTED>{}</DTPOSTED><TRNAMT>{:.2}</TRNAMT>\
+             <FITID>{}</FITID><NAME>{}</NAME><MEMO>{}</MEMO></STMTTRN>",
+            if tx.amount < 0.0 { "DEBIT" } else { "CREDIT" },
+            date(tx.booking_date),
+            tx.amount,
+            escape(&tx.reference),
+            // NAME is limited to 32 characters by the spec.
+            escape(&t.payee.chars().take(32).collect::<String>()),
+            escape(&tx.remittance_info),
+        );
+    }
+    out.push_str("</BANKTRANLIST>\n");
+    if let Some(balance) = account.balances.last() {
+        let _ = writeln!(
+            out,
+            "<LEDGERBAL><BALAMT>{:.2}</BALAMT><DTASOF>{}</DTASOF></LEDGERBAL>",
+            balance.amount,
+            date(balance.date)
+        );
+    }
+    out.push_str("</STMTRS></STMTTRNRS>\n");
+}
+
+fn account_type(account_type: &str) -> &'static str {
+    let lower = account_type.to_lowercase();
+    if lower.contains("tagesgeld") || lower.contains("spar") {
+        "SAVINGS"
+    } else {
+        "CHECKING"
+    }
+}
+
+fn date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::export::ofx::render;
+    use crate::export::{ExportAccount, ExportData, ExportSelection, ExportTransaction};
+    use chrono::NaiveDate;
+    use entity::entities::{account, account_balance, account_transactions};
+
+    fn transaction(reference: &str, day: u32, amount: f64, payee: &str, memo: &str) -> ExportTransaction {
+        ExportTransaction {
+            transaction: account_transactions::Model {
+                id: 0,
+                reference: reference.to_string(),
+                account_id: "A1".to_string(),
+                booking_status: "BOOKED".to_string(),
+                booking_date: NaiveDate::from_ymd_opt(2025, 3, day).unwrap(),
+                amount,
+                remitter: String::new(),
+                deptor: String::new(),
+                creditor: payee.to_string(),
+                creditor_id: String::new(),
+                creditor_mandate_id: String::new(),
+                remittance_info: memo.to_string(),
+                transaction_type: String::new(),
+                counterparty_iban: String::new(),
+            },
+            payee: payee.to_string(),
+            category: None,
+            subcategory: None,
+            splits: Vec::new(),
+            tags: Vec::new(),
+            note: None,
+            transfer: None,
+        }
+    }
+
+    fn data() -> ExportData {
+        ExportData {
+            selection: ExportSelection::default(),
+            accounts: vec![ExportAccount {
+                account: account::Model {
+                    id: 1,
+                    account_id: "A1".to_string(),
+                    display_id: "1234".to_string(),
+                    account_type: "Tagesgeld PLUS".to_string(),
+                    iban: "DE02120300000000202051".to_string(),
+                    bic: "COBADEHD001".to_string(),
+                    institute: "COMDIRECT".to_string(),
+                },
+                balances: vec![account_balance::Model {
+                    id: 1,
+                    amount: 1265.5,
+                    date: NaiveDate::from_ymd_opt(2025, 3, 31).unwrap(),
+                    account_id: "A1".to_string(),
+                }],
+                transactions: vec![
+                    transaction(
+                        "r1",
+                        7,
+                        -1234.5,
+                        "Stadtwerke <Nord> & Energieversorgung GmbH",
+                        "Abschlag \"März\" & Nachzahlung",
+                    ),
+                    transaction("r2", 25, 2500.0, "ACME GmbH", "Gehalt"),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_statement_transactions() {
+        let ofx = render(&data());
+        assert!(
+            ofx.contains(
+                "<STMTTRN><TRNTYPE>DEBIT</TRNTYPE><DTPOSTED>20250307</DTPOSTED><TRNAMT>-1234.50</TRNAMT>\
+                 <FITID>r1</FITID><NAME>Stadtwerke &lt;Nord&gt; &amp; Energieverso</NAME>\
+                 <MEMO>Abschlag &quot;März&quot; &amp; Nachzahlung</MEMO></STMTTRN>\n"
+            ),
+            "{ofx}"
+        );
+        assert!(ofx.contains(
+            "<STMTTRN><TRNTYPE>CREDIT</TRNTYPE><DTPOSTED>20250325</DTPOSTED><TRNAMT>2500.00</TRNAMT>\
+             <FITID>r2</FITID><NAME>ACME GmbH</NAME><MEMO>Gehalt</MEMO></STMTTRN>\n"
+        ));
+        assert!(ofx.contains("<DTSTART>20250307</DTSTART><DTEND>20250325</DTEND>"));
+        assert!(ofx.contains("<ACCTTYPE>SAVINGS</ACCTTYPE>"));
+        assert!(ofx.contains("<LEDGERBAL><BALAMT>1265.50</BALAMT><DTASOF>20250331</DTASOF></LEDGERBAL>"));
+    }
+}
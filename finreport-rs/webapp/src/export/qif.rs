@@ -0,0 +1,154 @@
+//! Quicken Interchange Format. Several accounts go into one file using the
+//! `AutoSwitch` account list, which GnuCash, KMyMoney and Moneydance read.
+
+use crate::export::ExportData;
+use std::fmt::Write;
+
+pub fn render(data: &ExportData) -> String {
+    let mut out = String::new();
+    out.push_str("!Option:AutoSwitch\n");
+    for account in &data.accounts {
+        let _ = write!(
+            out,
+            "!Account\nN{} {}\nTBank\n^\n!Type:Bank\n",
+            line(&account.account.account_type),
+            line(&account.account.iban)
+        );
+        for t in &account.transactions {
+            let tx = &t.transaction;
+            let _ = write!(
+                out,
+                "D{}\nT{:.2}\nP{}\nM{}\n",
+                tx.booking_date.format("%m/%d/%Y"),
+                tx.amount,
+                line(&t.payee),
+                line(&tx.remittance_info),
+            );
+            if let Some(category) = category(&t.category, &t.subcategory) {
+                let _ = writeln!(out, "L{category}");
+            }
+            // Split lines: category, memo and amount of each part.
+            for part in &t.splits {
+                let _ = writeln!(
+                    out,
+                    "S{}\nE{}\n${:.2}",
+                    category(&part.category, &part.subcategory).unwrap_or_default(),
+                    line(part.note.as_deref().unwrap_or_default()),
+                    part.amount
+                );
+            }
+            out.push_str("^\n");
+        }
+    }
+    out.push_str("!Clear:AutoSwitch\n");
+    out
+}
+
+fn category(category: &Option<String>, subcategory: &Option<String>) -> Option<String> {
+    match (category, subcategory) {
+        (Some(category), Some(subcategory)) => Some(format!("{}:{}", line(category), line(subcategory))),
+        (Some(category), None) => Some(line(category)),
+        _ => None,
+    }
+}
+
+/// QIF is line based; embedded newlines would start a new field.
+fn line(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::export::qif::render;
+    use crate::export::{ExportAccount, ExportData, ExportPart, ExportSelection, ExportTransaction};
+    use chrono::NaiveDate;
+    use entity::entities::{account, account_transactions};
+
+    fn transaction(
+        reference: &str,
+        day: u32,
+        amount: f64,
+        category: Option<(&str, &str)>,
+    ) -> ExportTransaction {
+        ExportTransaction {
+            transaction: account_transactions::Model {
+                id: 0,
+                reference: reference.to_string(),
+                account_id: "A1".to_string(),
+                booking_status: "BOOKED".to_string(),
+                booking_date: NaiveDate::from_ymd_opt(2025, 3, day).unwrap(),
+                amount,
+                remitter: String::new(),
+                deptor: String::new(),
+                creditor: "REWE Markt GmbH".to_string(),
+                creditor_id: String::new(),
+                creditor_mandate_id: String::new(),
+                remittance_info: "Einkauf\nFiliale 12".to_string(),
+                transaction_type: String::new(),
+                counterparty_iban: String::new(),
+            },
+            payee: "REWE Markt GmbH".to_string(),
+            category: category.map(|(category, _)| category.to_string()),
+            subcategory: category.map(|(_, subcategory)| subcategory.to_string()),
+            splits: Vec::new(),
+            tags: Vec::new(),
+            note: None,
+            transfer: None,
+        }
+    }
+
+    fn data() -> ExportData {
+        ExportData {
+            selection: ExportSelection::default(),
+            accounts: vec![ExportAccount {
+                account: account::Model {
+                    id: 1,
+                    account_id: "A1".to_string(),
+                    display_id: "1234".to_string(),
+                    account_type: "Girokonto".to_string(),
+                    iban: "DE02120300000000202051".to_string(),
+                    bic: String::new(),
+                    institute: "COMDIRECT".to_string(),
+                },
+                balances: Vec::new(),
+                transactions: vec![
+                    transaction("r1", 7, -1234.5, Some(("food", "groceries"))),
+                    transaction("r2", 12, 20.0, None),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_bank_register() {
+        let qif = render(&data());
+        let expected = "!Option:AutoSwitch\n\
+            !Account\nNGirokonto DE02120300000000202051\nTBank\n^\n!Type:Bank\n\
+            D03/07/2025\nT-1234.50\nPREWE Markt GmbH\nMEinkauf Filiale 12\nLfood:groceries\n^\n\
+            D03/12/2025\nT20.00\nPREWE Markt GmbH\nMEinkauf Filiale 12\n^\n\
+            !Clear:AutoSwitch\n";
+        assert_eq!(qif, expected);
+    }
+
+    #[test]
+    fn test_split_lines() {
+        let mut data = data();
+        let part = |amount: f64, subcategory: &str, note: Option<&str>| ExportPart {
+            amount,
+            category: Some("food".to_string()),
+            subcategory: Some(subcategory.to_string()),
+            note: note.map(str::to_string),
+            tag: None,
+        };
+        let t = &mut data.accounts[0].transactions[0];
+        (t.category, t.subcategory) = (None, None);
+        t.splits = vec![part(-1000.0, "groceries", None), part(-234.5, "sweets", Some("Kuchen"))];
+        let qif = render(&data);
+        assert!(
+            qif.contains(
+                "MEinkauf Filiale 12\nSfood:groceries\nE\n$-1000.00\nSfood:sweets\nEKuchen\n$-234.50\n^\n"
+            ),
+            "{qif}"
+        );
+    }
+}
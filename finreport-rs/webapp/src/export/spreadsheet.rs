@@ -0,0 +1,269 @@
+//! Spreadsheets for the accountant, in German locale: CSV with `;` as
+//! separator, decimal comma and `dd.mm.yyyy` dates, and an XLSX workbook
+//! with the transactions plus monthly-per-category pivots from
+//! [`crate::report`]. The pivots leave out internal transfers.
+
+use crate::export::{ExportData, ExportPart, ExportTransaction};
+use crate::report::{self, CategoryMonth, Pivot};
+use rust_xlsxwriter::{Format, FormatAlign, Workbook, Worksheet, XlsxError};
+
+const COLUMNS: [(&str, f64); 11] = [
+    ("Buchungstag", 12.0),
+    ("Konto", 24.0),
+    ("Status", 11.0),
+    ("Empfänger/Auftraggeber", 32.0),
+    ("Verwendungszweck", 60.0),
+    ("Kategorie", 18.0),
+    ("Unterkategorie", 24.0),
+    ("Betrag (EUR)", 14.0),
+    ("Referenz", 24.0),
+    ("Tags", 18.0),
+    ("Notiz", 40.0),
+];
+
+const DATE_FORMAT: &str = "dd.mm.yyyy";
+const AMOUNT_FORMAT: &str = "#,##0.00";
+
+/// Excel only detects UTF-8 CSV files by their byte order mark.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+pub fn render_csv(data: &ExportData) -> Result<Vec<u8>, csv::Error> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b';')
+        .from_writer(UTF8_BOM.to_vec());
+    writer.write_record(COLUMNS.iter().map(|(name, _)| *name))?;
+    for (iban, t, part) in rows(data) {
+        let tx = &t.transaction;
+        writer.write_record([
+            tx.booking_date.format("%d.%m.%Y").to_string(),
+            iban.to_string(),
+            status(&tx.booking_status).to_string(),
+            t.payee.clone(),
+            tx.remittance_info.clone(),
+            part.category.unwrap_or_default(),
+            part.subcategory.unwrap_or_default(),
+            decimal_comma(part.amount),
+            tx.reference.clone(),
+            t.tags.join(", "),
+            t.note.clone().unwrap_or_default(),
+        ])?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| csv::Error::from(e.into_error()))
+}
+
+pub fn render_xlsx(data: &ExportData) -> Result<Vec<u8>, XlsxError> {
+    let totals = report::monthly_by_category(&data.without_transfers());
+    let mut workbook = Workbook::new();
+    write_transactions(workbook.add_worksheet(), data)?;
+    write_pivot(
+        workbook.add_worksheet().set_name("Ausgaben je Monat")?,
+        &report::pivot(&totals, |t: &CategoryMonth| t.expenses),
+    )?;
+    write_pivot(
+        workbook.add_worksheet().set_name("Einnahmen je Monat")?,
+        &report::pivot(&totals, |t: &CategoryMonth| t.income),
+    )?;
+    workbook.save_to_buffer()
+}
+
+fn write_transactions(sheet: &mut Worksheet, data: &ExportData) -> Result<(), XlsxError> {
+    let header = Format::new().set_bold();
+    let date = Format::new().set_num_format(DATE_FORMAT);
+    let amount = Format::new().set_num_format(AMOUNT_FORMAT);
+
+    sheet.set_name("Umsätze")?;
+    for (col, (name, width)) in COLUMNS.iter().enumerate() {
+        let col = col as u16;
+        sheet.write_string_with_format(0, col, *name, &header)?;
+        sheet.set_column_width(col, *width)?;
+    }
+    let mut row = 0;
+    for (iban, t, part) in rows(data) {
+        row += 1;
+        let tx = &t.transaction;
+        sheet.write_datetime_with_format(row, 0, tx.booking_date, &date)?;
+        sheet.write_string(row, 1, iban)?;
+        sheet.write_string(row, 2, status(&tx.booking_status))?;
+        sheet.write_string(row, 3, &t.payee)?;
+        sheet.write_string(row, 4, &tx.remittance_info)?;
+        sheet.write_string(row, 5, part.category.as_deref().unwrap_or_default())?;
+        sheet.write_string(row, 6, part.subcategory.as_deref().unwrap_or_default())?;
+        sheet.write_number_with_format(row, 7, part.amount, &amount)?;
+        sheet.write_string(row, 8, &tx.reference)?;
+        sheet.write_string(row, 9, t.tags.join(", "))?;
+        sheet.write_string(row, 10, t.note.as_deref().unwrap_or_default())?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofilter(0, 0, row, COLUMNS.len() as u16 - 1)?;
+    Ok(())
+}
+
+fn write_pivot(sheet: &mut Worksheet, pivot: &Pivot) -> Result<(), XlsxError> {
+    let header = Format::new().set_bold().set_align(FormatAlign::Right);
+    let amount = Format::new().set_num_format(AMOUNT_FORMAT);
+    let total = Format::new().set_bold().set_num_format(AMOUNT_FORMAT);
+    let sum_col = pivot.months.len() as u16 + 1;
+
+    sheet.write_string_with_format(0, 0, "Kategorie", &Format::new().set_bold())?;
+    sheet.set_column_width(0, 24)?;
+    for (i, (year, month)) in pivot.months.iter().enumerate() {
+        let col = i as u16 + 1;
+        sheet.write_string_with_format(0, col, format!("{month:02}.{year}"), &header)?;
+        sheet.set_column_width(col, 12)?;
+    }
+    sheet.write_string_with_format(0, sum_col, "Summe", &header)?;
+    sheet.set_column_width(sum_col, 12)?;
+
+    let mut row = 0;
+    for (category, values) in &pivot.rows {
+        row += 1;
+        sheet.write_string(row, 0, category)?;
+        for (i, value) in values.iter().enumerate() {
+            sheet.write_number_with_format(row, i as u16 + 1, *value, &amount)?;
+        }
+        sheet.write_number_with_format(row, sum_col, values.iter().sum::<f64>(), &total)?;
+    }
+
+    row += 1;
+    let month_totals = pivot.month_totals();
+    sheet.write_string_with_format(row, 0, "Summe", &Format::new().set_bold())?;
+    for (i, value) in month_totals.iter().enumerate() {
+        sheet.write_number_with_format(row, i as u16 + 1, *value, &total)?;
+    }
+    sheet.write_number_with_format(row, sum_col, month_totals.iter().sum::<f64>(), &total)?;
+    sheet.set_freeze_panes(1, 1)?;
+    Ok(())
+}
+
+/// Transactions of all accounts in booking order, with the account IBAN. A
+/// split transaction takes one row per part.
+fn rows(data: &ExportData) -> Vec<(&str, &ExportTransaction, ExportPart)> {
+    let mut rows: Vec<(&str, &ExportTransaction, ExportPart)> = data
+        .accounts
+        .iter()
+        .flat_map(|a| a.transactions.iter().map(|t| (a.account.iban.as_str(), t)))
+        .flat_map(|(iban, t)| t.parts().into_iter().map(move |part| (iban, t, part)))
+        .collect();
+    rows.sort_by_key(|(_, t, _)| t.transaction.booking_date);
+    rows
+}
+
+fn status(booking_status: &str) -> &str {
+    match booking_status {
+        "BOOKED" => "gebucht",
+        "NOTBOOKED" => "vorgemerkt",
+        other => other,
+    }
+}
+
+fn decimal_comma(value: f64) -> String {
+    format!("{value:.2}").replace('.', ",")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::export::spreadsheet::{COLUMNS, render_csv, render_xlsx};
+    use crate::export::{ExportAccount, ExportData, ExportSelection, ExportTransaction};
+    use chrono::NaiveDate;
+    use entity::entities::{account, account_transactions};
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+
+    fn data() -> ExportData {
+        ExportData {
+            selection: ExportSelection::default(),
+            accounts: vec![ExportAccount {
+                account: account::Model {
+                    id: 1,
+                    account_id: "A1".to_string(),
+                    display_id: "1234".to_string(),
+                    account_type: "Girokonto".to_string(),
+                    iban: "DE02120300000000202051".to_string(),
+                    bic: String::new(),
+                    institute: "COMDIRECT".to_string(),
+                },
+                balances: Vec::new(),
+                transactions: vec![ExportTransaction {
+                    transaction: account_transactions::Model {
+                        id: 1,
+                        reference: "r1".to_string(),
+                        account_id: "A1".to_string(),
+                        booking_status: "BOOKED".to_string(),
+                        booking_date: NaiveDate::from_ymd_opt(2025, 3, 7).unwrap(),
+                        amount: -1234.5,
+                        remitter: String::new(),
+                        deptor: String::new(),
+                        creditor: "Stadtwerke; Nord".to_string(),
+                        creditor_id: String::new(),
+                        creditor_mandate_id: String::new(),
+                        remittance_info: "Abschlag März".to_string(),
+                        transaction_type: String::new(),
+                        counterparty_iban: String::new(),
+                    },
+                    payee: "Stadtwerke; Nord".to_string(),
+                    category: Some("housing".to_string()),
+                    subcategory: None,
+                    splits: Vec::new(),
+                    tags: vec!["haus".to_string(), "strom".to_string()],
+                    note: None,
+                    transfer: None,
+                }],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_german_csv() {
+        let csv = String::from_utf8(render_csv(&data()).unwrap()).unwrap();
+        let mut lines = csv.trim_start_matches('\u{feff}').lines();
+        assert!(lines.next().unwrap().starts_with("Buchungstag;Konto;Status;"));
+        assert_eq!(
+            lines.next().unwrap(),
+            "07.03.2025;DE02120300000000202051;gebucht;\"Stadtwerke; Nord\";Abschlag März;housing;;\
+             -1234,50;r1;haus, strom;"
+        );
+    }
+
+    fn read_part(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> String {
+        let mut xml = String::new();
+        archive.by_name(name).unwrap().read_to_string(&mut xml).unwrap();
+        xml
+    }
+
+    /// The value of cell `r` in a worksheet, with shared strings resolved.
+    fn cell(sheet: &str, strings: &[&str], r: &str) -> String {
+        let (attributes, rest) = sheet
+            .split_once(&format!("<c r=\"{r}\""))
+            .and_then(|(_, rest)| rest.split_once('>'))
+            .unwrap_or_else(|| panic!("no cell {r}"));
+        let value = &rest["<v>".len()..rest.find("</v>").unwrap()];
+        if attributes.contains("t=\"s\"") {
+            strings[value.parse::<usize>().unwrap()].to_string()
+        } else {
+            value.to_string()
+        }
+    }
+
+    #[test]
+    fn test_workbook_renders() {
+        let workbook = render_xlsx(&data()).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(workbook)).unwrap();
+        let sheet = read_part(&mut archive, "xl/worksheets/sheet1.xml");
+        let shared = read_part(&mut archive, "xl/sharedStrings.xml");
+        let strings: Vec<&str> = shared
+            .split("<t>")
+            .skip(1)
+            .map(|s| &s[..s.find("</t>").unwrap()])
+            .collect();
+
+        let header: Vec<String> = ["A1", "B1", "C1", "D1", "E1", "F1", "G1", "H1", "I1", "J1", "K1"]
+            .iter()
+            .map(|r| cell(&sheet, &strings, r))
+            .collect();
+        assert_eq!(header, COLUMNS.map(|(name, _)| name));
+        assert_eq!(cell(&sheet, &strings, "H2"), "-1234.5");
+        assert_eq!(cell(&sheet, &strings, "F2"), "housing");
+    }
+}
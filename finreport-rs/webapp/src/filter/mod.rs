@@ -0,0 +1,403 @@
+//! Compact filter syntax for transactions, e.g.
+//!
+//! ```text
+//! amount<-100 category:food account:Giro after:2025-01-01 payee~"rewe" -status=NOTBOOKED tag=reimbursable
+//! ```
+//!
+//! Terms are separated by whitespace and must all match; a leading `-`
+//! negates a term. A term is `field` `operator` `value` or a bare word,
+//! which matches remittance info and parties like a search. Values with
+//! spaces are quoted, `\"` escapes a quote.
+//!
+//! | field | operators | matches |
+//! |---|---|---|
+//! | `amount` | `:` `=` `<` `<=` `>` `>=` | signed amount, `-12.50` or `-12,50` |
+//! | `date` | `:` `=` `<` `<=` `>` `>=` | booking date, `2025-01-31`, `2025-01` or `2025` |
+//! | `after`, `before` | `:` | booking date on or after the start / on or before the end of a `date` |
+//! | `account` | `:` `~` `=` | account id, display id, IBAN or account type |
+//! | `category` | `:` `~` `=` | category or subcategory, or both as `food/groceries`; of any split part |
+//! | `tag` | `:` `~` `=` | a tag of the transaction or of a split part |
+//! | `payee` | `:` `~` `=` | remitter, creditor or deptor |
+//! | `info` | `:` `~` `=` | remittance info |
+//! | `status`, `type`, `reference` | `:` `~` `=` | booking status, transaction type, reference |
+//!
+//! Text fields ignore case; `:` and `~` look for the value anywhere in the
+//! field, `=` compares the whole field.
+
+mod parse;
+
+use crate::service::TransferStatus;
+use chrono::NaiveDate;
+use entity::entities::{
+    account, account_transactions, categories, internal_transfers, tags, transaction_categories,
+    transaction_splits, transaction_tags,
+};
+use sea_orm::sea_query::{Expr, Func, LikeExpr, Query, SimpleExpr};
+use sea_orm::{ColumnTrait, Condition, EntityName, Iden};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+const FIELDS: [&str; 12] = [
+    "amount",
+    "date",
+    "after",
+    "before",
+    "account",
+    "category",
+    "tag",
+    "payee",
+    "info",
+    "status",
+    "type",
+    "reference",
+];
+
+/// Amounts are compared to the cent.
+const CENT: f64 = 0.005;
+
+/// A parsed filter; see the module documentation for the syntax.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FilterQuery {
+    terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    negated: bool,
+    predicate: Predicate,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Predicate {
+    Amount(Comparison, f64),
+    /// First and last day of the given date, month or year.
+    Date(Comparison, NaiveDate, NaiveDate),
+    Text(TextField, Match, String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Match {
+    Exact,
+    Contains,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TextField {
+    /// A bare word.
+    Any,
+    Account,
+    Category,
+    Tag,
+    Payee,
+    Info,
+    Status,
+    Type,
+    Reference,
+}
+
+/// Invalid filter input. `column` counts characters from 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl FilterError {
+    /// The input with a caret under the offending column, for terminals.
+    pub fn pointer(&self, input: &str) -> String {
+        format!("{input}\n{}^", " ".repeat(self.column - 1))
+    }
+}
+
+impl Display for FilterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+impl FromStr for FilterQuery {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(FilterQuery {
+            terms: parse::parse(s)?,
+        })
+    }
+}
+
+impl FilterQuery {
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Transactions matching both filters.
+    pub fn and(mut self, other: FilterQuery) -> FilterQuery {
+        self.terms.extend(other.terms);
+        self
+    }
+
+    /// The filter as a condition on `account_transactions`; categories and
+    /// accounts are matched through subqueries.
+    pub fn condition(&self) -> Condition {
+        self.terms.iter().fold(Condition::all(), |all, term| {
+            let condition = term.predicate.condition();
+            all.add(if term.negated { condition.not() } else { condition })
+        })
+    }
+}
+
+impl Predicate {
+    fn condition(&self) -> Condition {
+        let amount = column(account_transactions::Column::Amount);
+        let date = column(account_transactions::Column::BookingDate);
+        match self {
+            Predicate::Amount(Comparison::Eq, value) => {
+                Condition::all().add(amount.between(value - CENT, value + CENT))
+            }
+            Predicate::Amount(comparison, value) => {
+                Condition::all().add(compare(amount, *comparison, *value, *value))
+            }
+            Predicate::Date(comparison, first, last) => {
+                Condition::all().add(compare(date, *comparison, *first, *last))
+            }
+            Predicate::Text(field, mode, value) => text_condition(*field, *mode, value),
+        }
+    }
+}
+
+fn text_condition(field: TextField, mode: Match, value: &str) -> Condition {
+    use account_transactions::Column as T;
+    let any = |columns: &[T]| {
+        columns
+            .iter()
+            .fold(Condition::any(), |any, c| any.add(matches(column(*c), mode, value)))
+    };
+    match field {
+        TextField::Any => any(&[T::RemittanceInfo, T::Remitter, T::Creditor, T::Deptor]),
+        TextField::Payee => any(&[T::Remitter, T::Creditor, T::Deptor]),
+        TextField::Info => any(&[T::RemittanceInfo]),
+        TextField::Status => any(&[T::BookingStatus]),
+        TextField::Type => any(&[T::TransactionType]),
+        TextField::Reference => any(&[T::Reference]),
+        TextField::Account => {
+            let accounts = [
+                account::Column::AccountId,
+                account::Column::DisplayId,
+                account::Column::Iban,
+                account::Column::AccountType,
+            ]
+            .into_iter()
+            .fold(Condition::any(), |any, c| any.add(matches(column(c), mode, value)));
+            Condition::all().add(
+                column(T::AccountId).in_subquery(
+                    Query::select()
+                        .column((account::Entity, account::Column::AccountId))
+                        .from(account::Entity)
+                        .cond_where(accounts)
+                        .to_owned(),
+                ),
+            )
+        }
+        TextField::Tag => {
+            use transaction_splits::Column as TS;
+            use transaction_tags::Column as TT;
+            Condition::any()
+                .add(
+                    column(T::Reference).in_subquery(
+                        Query::select()
+                            .column((transaction_tags::Entity, TT::Reference))
+                            .from(transaction_tags::Entity)
+                            .inner_join(
+                                tags::Entity,
+                                column(tags::Column::Id).equals((transaction_tags::Entity, TT::TagId)),
+                            )
+                            .and_where(matches(column(tags::Column::Name), mode, value))
+                            .to_owned(),
+                    ),
+                )
+                .add(
+                    column(T::Reference).in_subquery(
+                        Query::select()
+                            .column((transaction_splits::Entity, TS::Reference))
+                            .from(transaction_splits::Entity)
+                            .and_where(matches(column(TS::Tag), mode, value))
+                            .to_owned(),
+                    ),
+                )
+        }
+        TextField::Category => {
+            let category = column(categories::Column::Category);
+            let subcategory = column(categories::Column::Subcategory);
+            let categories = match value.split_once('/') {
+                Some((c, s)) => Condition::all()
+                    .add(matches(category, mode, c))
+                    .add(matches(subcategory, mode, s)),
+                None => Condition::any()
+                    .add(matches(category, mode, value))
+                    .add(matches(subcategory, mode, value)),
+            };
+            use transaction_categories::Column as TC;
+            use transaction_splits::Column as TS;
+            // A split transaction matches if one of its parts does.
+            Condition::any()
+                .add(
+                    column(T::Reference).in_subquery(
+                        Query::select()
+                            .column((transaction_categories::Entity, TC::Reference))
+                            .from(transaction_categories::Entity)
+                            .inner_join(
+                                categories::Entity,
+                                column(categories::Column::Id)
+                                    .equals((transaction_categories::Entity, TC::CategoryId)),
+                            )
+                            .cond_where(categories.clone())
+                            .to_owned(),
+                    ),
+                )
+                .add(
+                    column(T::Reference).in_subquery(
+                        Query::select()
+                            .column((transaction_splits::Entity, TS::Reference))
+                            .from(transaction_splits::Entity)
+                            .inner_join(
+                                categories::Entity,
+                                column(categories::Column::Id)
+                                    .equals((transaction_splits::Entity, TS::CategoryId)),
+                            )
+                            .cond_where(categories)
+                            .to_owned(),
+                    ),
+                )
+        }
+    }
+}
+
+/// Transactions tagged exactly `tag`, ignoring case.
+pub(crate) fn has_tag(tag: &str) -> Condition {
+    text_condition(TextField::Tag, Match::Exact, tag)
+}
+
+/// Either leg of a linked internal transfer.
+pub(crate) fn is_transfer() -> Condition {
+    use internal_transfers::Column as IT;
+    let legs = |leg: IT| {
+        column(account_transactions::Column::Reference).in_subquery(
+            Query::select()
+                .column((internal_transfers::Entity, leg))
+                .from(internal_transfers::Entity)
+                .and_where(column(IT::Status).eq(TransferStatus::Linked.to_string()))
+                .to_owned(),
+        )
+    };
+    Condition::any().add(legs(IT::OutgoingReference)).add(legs(IT::IncomingReference))
+}
+
+/// A column qualified with its table, so that subqueries stay unambiguous.
+fn column<C>(c: C) -> Expr
+where
+    C: ColumnTrait + Iden + 'static,
+    C::EntityName: EntityName + Default + Iden + 'static,
+{
+    Expr::col((C::EntityName::default(), c))
+}
+
+fn compare<V>(expr: Expr, comparison: Comparison, first: V, last: V) -> SimpleExpr
+where
+    V: Into<sea_orm::Value>,
+{
+    match comparison {
+        Comparison::Eq => expr.between(first, last),
+        Comparison::Lt => expr.lt(first),
+        Comparison::Le => expr.lte(last),
+        Comparison::Gt => expr.gt(last),
+        Comparison::Ge => expr.gte(first),
+    }
+}
+
+/// Case-insensitive comparison of a text column.
+fn matches(expr: Expr, mode: Match, value: &str) -> SimpleExpr {
+    let lowered = Expr::expr(Func::lower(expr));
+    let value = value.to_lowercase();
+    match mode {
+        Match::Exact => lowered.eq(value),
+        Match::Contains => lowered.like(contains(&value)),
+    }
+}
+
+/// LIKE pattern matching `value` anywhere, with its wildcards escaped.
+pub(crate) fn contains(value: &str) -> LikeExpr {
+    let escaped = value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    LikeExpr::new(format!("%{escaped}%")).escape('\\')
+}
+
+#[cfg(test)]
+mod test {
+    use crate::filter::FilterQuery;
+    use crate::service::test::{account, db, transaction};
+    use crate::service::{CategoryAssignment, ChangeSource, Mutation, Query, TransactionFilter};
+
+    #[tokio::test]
+    async fn test_filter_transactions() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        let mut groceries = transaction("r1", "A1", -120.0);
+        groceries.creditor = "REWE Markt GmbH".to_string();
+        let mut restaurant = transaction("r2", "A1", -140.0);
+        restaurant.creditor = "Trattoria 100%".to_string();
+        let mut salary = transaction("r3", "A1", 2500.0);
+        salary.remitter = "Arbeitgeber GmbH".to_string();
+        salary.creditor = String::new();
+        for t in [groceries, restaurant, salary] {
+            Mutation::upsert_transaction(&db, t, ChangeSource::Import).await.unwrap();
+        }
+        for (reference, subcategory) in [("r1", "groceries"), ("r2", "restaurants")] {
+            Mutation::assign_category(
+                &db,
+                CategoryAssignment {
+                    reference: reference.to_string(),
+                    category: "food".to_string(),
+                    subcategory: subcategory.to_string(),
+                    reasoning: None,
+                    confidence: None,
+                    source: ChangeSource::User,
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let references = |query: &str| {
+            let filter = TransactionFilter {
+                query: Some(query.parse::<FilterQuery>().unwrap()),
+                ..Default::default()
+            };
+            let db = &db;
+            async move {
+                Query::transactions(db, &filter)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|t| t.reference)
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(references("amount<-100 category:food").await, ["r1", "r2"]);
+        assert_eq!(references("category:food -payee~rewe").await, ["r2"]);
+        assert_eq!(references("category:food/groceries").await, ["r1"]);
+        assert_eq!(references("-category:food").await, ["r3"]);
+        assert_eq!(references(r#"payee:"100%""#).await, ["r2"]);
+        assert_eq!(references("account:girokonto amount:2500,00 gmbh").await, ["r3"]);
+        assert!(references("after:2025-07").await.is_empty());
+    }
+}
@@ -0,0 +1,248 @@
+use crate::filter::{Comparison, FilterError, Match, Predicate, Term, TextField, FIELDS};
+use chrono::{Months, NaiveDate};
+
+/// Operators, longest first so that `<=` is not read as `<`.
+const OPERATORS: [&str; 7] = ["<=", ">=", "<", ">", "=", ":", "~"];
+
+pub(crate) fn parse(input: &str) -> Result<Vec<Term>, FilterError> {
+    let mut parser = Parser { input, pos: 0 };
+    let mut terms = Vec::new();
+    loop {
+        parser.skip_whitespace();
+        if parser.peek().is_none() {
+            return Ok(terms);
+        }
+        terms.push(parser.term()?);
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    /// Byte offset of the next char.
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn at_end_of_term(&self) -> bool {
+        self.peek().is_none_or(char::is_whitespace)
+    }
+
+    fn error(&self, at: usize, message: impl Into<String>) -> FilterError {
+        FilterError {
+            column: self.input[..at].chars().count() + 1,
+            message: message.into(),
+        }
+    }
+
+    fn term(&mut self) -> Result<Term, FilterError> {
+        let start = self.pos;
+        let negated = self.peek() == Some('-');
+        if negated {
+            self.bump();
+            if self.at_end_of_term() {
+                return Err(self.error(start, "expected a term after `-`"));
+            }
+        }
+
+        let name_start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.bump();
+        }
+        let name = &self.input[name_start..self.pos];
+        let operator = OPERATORS
+            .into_iter()
+            .find(|op| self.input[self.pos..].starts_with(op));
+        let predicate = match operator {
+            Some(operator) if !name.is_empty() => {
+                self.pos += operator.len();
+                let value_start = self.pos;
+                let value = self.value()?;
+                if value.is_empty() {
+                    return Err(self.error(value_start, format!("expected a value after `{name}{operator}`")));
+                }
+                self.field(name, name_start, operator, &value, value_start)?
+            }
+            _ => {
+                self.pos = name_start;
+                Predicate::Text(TextField::Any, Match::Contains, self.value()?)
+            }
+        };
+        Ok(Term { negated, predicate })
+    }
+
+    /// A quoted string or everything up to the next whitespace.
+    fn value(&mut self) -> Result<String, FilterError> {
+        if self.peek() != Some('"') {
+            let start = self.pos;
+            while !self.at_end_of_term() {
+                self.bump();
+            }
+            return Ok(self.input[start..self.pos].to_string());
+        }
+
+        let quote = self.pos;
+        self.bump();
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                None => return Err(self.error(quote, "unterminated quote")),
+                Some('"') => break,
+                Some('\\') if matches!(self.peek(), Some('"' | '\\')) => value.extend(self.bump()),
+                Some(c) => value.push(c),
+            }
+        }
+        if !self.at_end_of_term() {
+            return Err(self.error(self.pos, "expected a space after the closing quote"));
+        }
+        Ok(value)
+    }
+
+    fn field(
+        &self,
+        name: &str,
+        name_start: usize,
+        operator: &str,
+        value: &str,
+        value_start: usize,
+    ) -> Result<Predicate, FilterError> {
+        let operator_start = name_start + name.len();
+        let text = |field: TextField| match operator {
+            ":" | "~" => Ok(Predicate::Text(field, Match::Contains, value.to_string())),
+            "=" => Ok(Predicate::Text(field, Match::Exact, value.to_string())),
+            _ => Err(self.error(operator_start, format!("`{name}` cannot be compared with `{operator}`"))),
+        };
+        match name.to_ascii_lowercase().as_str() {
+            "amount" => {
+                let comparison = self.comparison(name, operator, operator_start)?;
+                let amount = parse_amount(value).ok_or_else(|| {
+                    self.error(value_start, format!("expected an amount like -12.50, found `{value}`"))
+                })?;
+                Ok(Predicate::Amount(comparison, amount))
+            }
+            "date" => {
+                let comparison = self.comparison(name, operator, operator_start)?;
+                let (first, last) = self.date(value, value_start)?;
+                Ok(Predicate::Date(comparison, first, last))
+            }
+            "after" | "before" => {
+                if operator != ":" {
+                    return Err(self.error(operator_start, format!("expected `{name}:`")));
+                }
+                let (first, last) = self.date(value, value_start)?;
+                Ok(match name.to_ascii_lowercase().as_str() {
+                    "after" => Predicate::Date(Comparison::Ge, first, last),
+                    _ => Predicate::Date(Comparison::Le, first, last),
+                })
+            }
+            "account" => text(TextField::Account),
+            "category" => text(TextField::Category),
+            "tag" => text(TextField::Tag),
+            "payee" => text(TextField::Payee),
+            "info" => text(TextField::Info),
+            "status" => text(TextField::Status),
+            "type" => text(TextField::Type),
+            "reference" => text(TextField::Reference),
+            _ => Err(self.error(
+                name_start,
+                format!("unknown field `{name}`, expected one of {}", FIELDS.join(", ")),
+            )),
+        }
+    }
+
+    fn comparison(&self, name: &str, operator: &str, at: usize) -> Result<Comparison, FilterError> {
+        match operator {
+            ":" | "=" => Ok(Comparison::Eq),
+            "<" => Ok(Comparison::Lt),
+            "<=" => Ok(Comparison::Le),
+            ">" => Ok(Comparison::Gt),
+            ">=" => Ok(Comparison::Ge),
+            _ => Err(self.error(at, format!("`{name}` cannot be matched with `{operator}`"))),
+        }
+    }
+
+    /// First and last day of `YYYY-MM-DD`, `YYYY-MM` or `YYYY`.
+    fn date(&self, value: &str, at: usize) -> Result<(NaiveDate, NaiveDate), FilterError> {
+        let parts: Vec<&str> = value.split('-').collect();
+        let number = |i: usize| parts.get(i).and_then(|p| p.parse::<u32>().ok());
+        let range = match parts.len() {
+            1 => number(0).and_then(|y| {
+                Some((NaiveDate::from_ymd_opt(y as i32, 1, 1)?, NaiveDate::from_ymd_opt(y as i32, 12, 31)?))
+            }),
+            2 => number(0).zip(number(1)).and_then(|(y, m)| {
+                let first = NaiveDate::from_ymd_opt(y as i32, m, 1)?;
+                Some((first, first.checked_add_months(Months::new(1))?.pred_opt()?))
+            }),
+            3 => value.parse::<NaiveDate>().ok().map(|d| (d, d)),
+            _ => None,
+        };
+        range.ok_or_else(|| {
+            self.error(at, format!("expected a date like 2025-01-31, 2025-01 or 2025, found `{value}`"))
+        })
+    }
+}
+
+/// `-12.50` or `-12,50`.
+fn parse_amount(value: &str) -> Option<f64> {
+    let amount: f64 = value.replace(',', ".").parse().ok()?;
+    amount.is_finite().then_some(amount)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::filter::parse::parse;
+    use crate::filter::{Comparison, FilterError, Match, Predicate, Term, TextField};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_parse_example() {
+        let input = r#"amount<-100 category:food after:2025-01 payee~"rewe city" -status=NOTBOOKED amazon"#;
+        let terms = parse(input).unwrap();
+        let day = |m, d| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+        let term = |predicate| Term {
+            negated: false,
+            predicate,
+        };
+        assert_eq!(
+            terms,
+            [
+                term(Predicate::Amount(Comparison::Lt, -100.0)),
+                term(Predicate::Text(TextField::Category, Match::Contains, "food".to_string())),
+                term(Predicate::Date(Comparison::Ge, day(1, 1), day(1, 31))),
+                term(Predicate::Text(TextField::Payee, Match::Contains, "rewe city".to_string())),
+                Term {
+                    negated: true,
+                    predicate: Predicate::Text(TextField::Status, Match::Exact, "NOTBOOKED".to_string()),
+                },
+                term(Predicate::Text(TextField::Any, Match::Contains, "amazon".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let column = |input: &str| parse(input).map(|_| ()).map_err(|FilterError { column, .. }| column);
+        assert_eq!(column("amount<-100 colour:red"), Err(13));
+        assert_eq!(column("amount<abc"), Err(8));
+        assert_eq!(column("payee<rewe"), Err(6));
+        assert_eq!(column(r#"after:2025 payee:"rewe"#), Err(18));
+        assert_eq!(column("date:2025-02-30"), Err(6));
+        assert_eq!(column("category:"), Err(10));
+        assert_eq!(column("ümlaut -"), Err(8));
+    }
+}
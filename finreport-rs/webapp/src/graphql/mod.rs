@@ -4,9 +4,13 @@ mod queries;
 use crate::graphql::mutations::MutationRoot;
 use crate::graphql::queries::QueryRoot;
 use async_graphql::{EmptySubscription, Schema};
+use sea_orm::DatabaseConnection;
+use std::sync::Arc;
 
 pub type AppSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
 
-pub fn create_schema() -> AppSchema {
-    Schema::build(QueryRoot, MutationRoot, EmptySubscription).finish()
+pub fn create_schema(conn: Arc<DatabaseConnection>) -> AppSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .data(conn)
+        .finish()
 }
@@ -1,7 +1,104 @@
-use async_graphql::Object;
+use crate::{categorization, counterparty};
+use crate::graphql::queries::{
+    load_counterparties, load_findings, load_mandate_conflicts, load_transfers, owner, parse_filter,
+    CategorizationRule, Category, Counterparty, CounterpartyRuleKind, Finding, MandateConflict, SavedView,
+    Split, Transfer,
+};
+use crate::service::{self, CategoryAssignment, ChangeSource, Mutation, SavedViewInput, SplitInput};
+use crate::transfer::{self, TransferSettings};
+use async_graphql::{Context, Enum, InputObject, Object};
+use categorizer::rules::Criteria;
+use sea_orm::DatabaseConnection;
+use std::sync::Arc;
 
 pub struct MutationRoot;
 
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum FindingAction {
+    /// Delete one transaction of a duplicate pair.
+    Merge,
+    /// Dismiss without a decision.
+    Ignore,
+    /// Both transactions are genuine.
+    Keep,
+}
+
+impl From<FindingAction> for service::FindingAction {
+    fn from(action: FindingAction) -> Self {
+        match action {
+            FindingAction::Merge => service::FindingAction::Merge,
+            FindingAction::Ignore => service::FindingAction::Ignore,
+            FindingAction::Keep => service::FindingAction::Keep,
+        }
+    }
+}
+
+/// One part of a split; amounts carry the sign of the transaction.
+#[derive(InputObject)]
+pub struct SplitPart {
+    pub amount: f64,
+    pub category: String,
+    pub subcategory: String,
+    pub note: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl From<SplitPart> for SplitInput {
+    fn from(part: SplitPart) -> Self {
+        SplitInput {
+            amount: part.amount,
+            category: part.category,
+            subcategory: part.subcategory,
+            note: part.note,
+            tag: part.tag,
+        }
+    }
+}
+
+/// A categorization rule; criteria left out or `null` are not checked.
+#[derive(InputObject)]
+pub struct CategorizationRuleInput {
+    pub name: String,
+    /// Rules with a higher priority are tried first.
+    #[graphql(default)]
+    pub priority: i32,
+    pub category: String,
+    pub subcategory: String,
+    /// Part of the payee name, ignoring case.
+    pub payee: Option<String>,
+    pub iban: Option<String>,
+    pub creditor_id: Option<String>,
+    pub mandate_id: Option<String>,
+    /// Inclusive; debits are negative.
+    pub min_amount: Option<f64>,
+    /// Inclusive; debits are negative.
+    pub max_amount: Option<f64>,
+    pub transaction_type: Option<String>,
+    /// Case-insensitive regular expression searched in the remittance info.
+    pub remittance_pattern: Option<String>,
+}
+
+impl From<CategorizationRuleInput> for service::CategorizationRuleInput {
+    fn from(rule: CategorizationRuleInput) -> Self {
+        service::CategorizationRuleInput {
+            name: rule.name,
+            priority: rule.priority,
+            category: rule.category,
+            subcategory: rule.subcategory,
+            criteria: Criteria {
+                payee: rule.payee,
+                iban: rule.iban,
+                creditor_id: rule.creditor_id,
+                mandate_id: rule.mandate_id,
+                min_amount: rule.min_amount,
+                max_amount: rule.max_amount,
+                transaction_type: rule.transaction_type,
+                remittance_pattern: rule.remittance_pattern,
+            },
+        }
+    }
+}
+
 #[Object]
 impl MutationRoot {
     async fn load_accounts(&self) -> Result<bool, async_graphql::Error> {
@@ -10,4 +107,280 @@ impl MutationRoot {
         from the comdirect API and store them in the database"
         );
     }
+
+    /// Categorize a transaction by hand, replacing the categorizer's answer.
+    /// A direct debit teaches its mandate the category, see
+    /// `confirmCategory`.
+    async fn set_category(
+        &self,
+        ctx: &Context<'_>,
+        reference: String,
+        category: String,
+        subcategory: String,
+    ) -> Result<Category, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let assignment = CategoryAssignment {
+            reference: reference.clone(),
+            category,
+            subcategory,
+            reasoning: None,
+            confidence: None,
+            source: ChangeSource::User,
+        };
+        let category = Mutation::assign_category(conn, assignment).await?;
+        categorization::learn_mandate(conn, &reference).await?;
+        Ok(category.into())
+    }
+
+    /// Accept the category of a transaction as it is. The first direct debit
+    /// of a mandate a user categorizes or confirms maps the mandate to its
+    /// category for all its debits; a debit that disagrees with the mapping
+    /// shows up in `mandateConflicts` instead.
+    async fn confirm_category(
+        &self,
+        ctx: &Context<'_>,
+        reference: String,
+    ) -> Result<Category, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let category = Mutation::confirm_category(conn, &reference, ChangeSource::User).await?;
+        categorization::learn_mandate(conn, &reference).await?;
+        Ok(category.into())
+    }
+
+    /// Divide a transaction into parts with their own category, note and
+    /// tag, replacing its category. The parts must sum to the amount of the
+    /// transaction; an empty list removes the split.
+    async fn split_transaction(
+        &self,
+        ctx: &Context<'_>,
+        reference: String,
+        parts: Vec<SplitPart>,
+    ) -> Result<Vec<Split>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let parts = parts.into_iter().map(SplitInput::from).collect();
+        Ok(Mutation::split_transaction(conn, &reference, parts, ChangeSource::User)
+            .await?
+            .into_iter()
+            .map(Split::from)
+            .collect())
+    }
+
+    /// Add tags to every transaction in `references`; returns how many tags
+    /// were new on a transaction.
+    async fn add_tags(
+        &self,
+        ctx: &Context<'_>,
+        references: Vec<String>,
+        tags: Vec<String>,
+    ) -> Result<usize, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(Mutation::add_tags(conn, &references, &tags, ChangeSource::User).await?)
+    }
+
+    /// Remove tags from every transaction in `references`; returns how many
+    /// were removed.
+    async fn remove_tags(
+        &self,
+        ctx: &Context<'_>,
+        references: Vec<String>,
+        tags: Vec<String>,
+    ) -> Result<usize, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(Mutation::remove_tags(conn, &references, &tags, ChangeSource::User).await?)
+    }
+
+    /// Set the note of every transaction in `references`; `null` or a blank
+    /// note removes it. Returns how many notes changed.
+    async fn set_note(
+        &self,
+        ctx: &Context<'_>,
+        references: Vec<String>,
+        note: Option<String>,
+    ) -> Result<usize, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(Mutation::set_note(conn, &references, note, ChangeSource::User).await?)
+    }
+
+    /// Link two bookings on different accounts whose amounts cancel out as
+    /// an internal transfer, in either order.
+    async fn link_transfer(
+        &self,
+        ctx: &Context<'_>,
+        reference: String,
+        other_reference: String,
+    ) -> Result<Transfer, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let transfer = Mutation::link_transfer(conn, &reference, &other_reference, ChangeSource::User).await?;
+        Ok(load_transfers(conn, vec![transfer]).await?.remove(0))
+    }
+
+    /// Unlink the internal transfer `reference` is a leg of; the matcher
+    /// will not link the pair again.
+    async fn unlink_transfer(
+        &self,
+        ctx: &Context<'_>,
+        reference: String,
+    ) -> Result<Transfer, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let transfer = Mutation::unlink_transfer(conn, &reference, ChangeSource::User).await?;
+        Ok(load_transfers(conn, vec![transfer]).await?.remove(0))
+    }
+
+    /// Run the transfer matcher over all bookings; returns how many pairs
+    /// were linked.
+    async fn detect_transfers(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "TransferSettings::default().window_days")] window_days: u64,
+    ) -> Result<usize, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(transfer::detect(conn, &TransferSettings { window_days }).await?)
+    }
+
+    /// Give a counterparty another name; names are unique.
+    async fn rename_counterparty(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        name: String,
+    ) -> Result<Counterparty, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let counterparty = Mutation::rename_counterparty(conn, id, &name).await?;
+        Ok(load_counterparties(conn, vec![counterparty]).await?.remove(0))
+    }
+
+    /// Fold the counterparties `ids` into `into`, with their rules and
+    /// transactions.
+    async fn merge_counterparties(
+        &self,
+        ctx: &Context<'_>,
+        ids: Vec<i32>,
+        into: i32,
+    ) -> Result<Counterparty, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let counterparty = Mutation::merge_counterparties(conn, &ids, into).await?;
+        Ok(load_counterparties(conn, vec![counterparty]).await?.remove(0))
+    }
+
+    /// Add a rule to a counterparty and resolve all transactions again.
+    async fn add_counterparty_rule(
+        &self,
+        ctx: &Context<'_>,
+        counterparty_id: i32,
+        kind: CounterpartyRuleKind,
+        pattern: String,
+    ) -> Result<Counterparty, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Mutation::add_counterparty_rule(conn, counterparty_id, kind.into(), &pattern).await?;
+        counterparty::resolve(conn).await?;
+        let counterparty = service::Query::find_counterparty(conn, counterparty_id).await?;
+        Ok(load_counterparties(conn, vec![counterparty]).await?.remove(0))
+    }
+
+    /// Delete a rule and resolve all transactions again.
+    async fn remove_counterparty_rule(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+    ) -> Result<Counterparty, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let rule = Mutation::remove_counterparty_rule(conn, id).await?;
+        counterparty::resolve(conn).await?;
+        let counterparty = service::Query::find_counterparty(conn, rule.counterparty_id).await?;
+        Ok(load_counterparties(conn, vec![counterparty]).await?.remove(0))
+    }
+
+    /// Resolve all transactions to counterparties; returns how many changed.
+    async fn resolve_counterparties(&self, ctx: &Context<'_>) -> Result<usize, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(counterparty::resolve(conn).await?.changed)
+    }
+
+    /// Create a categorization rule, or replace rule `id`. Nothing is
+    /// categorized until the rules are applied.
+    async fn save_categorization_rule(
+        &self,
+        ctx: &Context<'_>,
+        id: Option<i32>,
+        rule: CategorizationRuleInput,
+    ) -> Result<CategorizationRule, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let rule = Mutation::save_categorization_rule(conn, id, rule.into()).await?;
+        Ok(service::Query::find_categorization_rule(conn, rule.id).await?.into())
+    }
+
+    /// Delete a rule; the transactions it categorized keep their category.
+    async fn delete_categorization_rule(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+    ) -> Result<bool, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Mutation::delete_categorization_rule(conn, id).await?;
+        Ok(true)
+    }
+
+    /// Categorize the uncategorized transactions a rule matches; returns how
+    /// many were categorized.
+    async fn apply_categorization_rules(&self, ctx: &Context<'_>) -> Result<usize, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(categorization::apply_rules(conn).await?)
+    }
+
+    /// Close an open mandate conflict. `remap` maps the mandate to the
+    /// category of the debit and recategorizes its other debits; otherwise
+    /// the debit stays an exception.
+    async fn resolve_mandate_conflict(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        remap: bool,
+    ) -> Result<MandateConflict, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let conflict = Mutation::resolve_mandate_conflict(conn, id, remap).await?;
+        if remap {
+            categorization::apply_mandates(conn).await?;
+        }
+        let conflict = service::Query::find_mandate_conflict(conn, conflict.id).await?;
+        Ok(load_mandate_conflicts(conn, vec![conflict]).await?.remove(0))
+    }
+
+    /// Act on an open finding. A merge keeps `keepReference`, by default the
+    /// earlier transaction of the pair.
+    async fn resolve_finding(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        action: FindingAction,
+        keep_reference: Option<String>,
+    ) -> Result<Finding, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let finding = Mutation::resolve_finding(conn, id, action.into(), keep_reference).await?;
+        Ok(load_findings(conn, vec![finding]).await?.remove(0))
+    }
+
+    /// Create a saved view, or change the name and filter of view `id`.
+    /// Filter errors carry the `column` in their extensions.
+    async fn save_view(
+        &self,
+        ctx: &Context<'_>,
+        id: Option<i32>,
+        name: String,
+        filter: String,
+    ) -> Result<SavedView, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        parse_filter(Some(filter.clone()))?;
+        let input = SavedViewInput {
+            owner: owner(ctx).0,
+            name,
+            filter,
+        };
+        Ok(Mutation::save_view(conn, id, input).await?.into())
+    }
+
+    async fn delete_view(&self, ctx: &Context<'_>, id: i32) -> Result<bool, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Mutation::delete_view(conn, &owner(ctx).0, id).await?;
+        Ok(true)
+    }
 }
@@ -1,4 +1,19 @@
-use async_graphql::{Object, SimpleObject};
+use crate::filter::{FilterError, FilterQuery};
+use crate::{balance, counterparty, report, search, views};
+use crate::service::{self, Query, TransactionFilter};
+use crate::views::Owner;
+use async_graphql::{
+    ComplexObject, Context, Enum, ErrorExtensions, InputObject, Object, SimpleObject,
+};
+use chrono::NaiveDate;
+use entity::entities::{
+    account, account_transactions, attachments, categories, categorization_rules, counterparties,
+    counterparty_rules, daily_balances, data_findings, import_run_accounts, internal_transfers,
+    mandate_categories, mandate_category_conflicts, saved_views, transaction_history, transaction_remittance,
+};
+use sea_orm::DatabaseConnection;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 pub struct QueryRoot;
 
@@ -11,36 +26,1189 @@ pub struct Report {
     pub total_expenses: f64,
 }
 
+#[derive(SimpleObject)]
+pub struct Account {
+    pub account_id: String,
+    pub display_id: String,
+    pub account_type: String,
+    pub iban: String,
+    pub bic: String,
+    pub institute: String,
+}
+
+impl From<account::Model> for Account {
+    fn from(a: account::Model) -> Self {
+        Account {
+            account_id: a.account_id,
+            display_id: a.display_id,
+            account_type: a.account_type,
+            iban: a.iban,
+            bic: a.bic,
+            institute: a.institute,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Category {
+    pub id: i32,
+    pub category: String,
+    pub subcategory: String,
+}
+
+impl From<categories::Model> for Category {
+    fn from(c: categories::Model) -> Self {
+        Category {
+            id: c.id,
+            category: c.category,
+            subcategory: c.subcategory,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Transaction {
+    pub reference: String,
+    pub account_id: String,
+    pub booking_status: String,
+    /// `YYYY-MM-DD`
+    pub booking_date: String,
+    pub amount: f64,
+    /// The canonical name of the counterparty, else the name the bank sent.
+    pub payee: String,
+    pub counterparty_id: Option<i32>,
+    pub remittance_info: String,
+    /// The fields found in `remittanceInfo`; `null` until it was parsed.
+    pub remittance: Option<Remittance>,
+    pub transaction_type: String,
+    /// `null` for split transactions; see `splits`.
+    pub category: Option<Category>,
+    /// Parts with their own category, summing to `amount`; empty unless the
+    /// transaction is split.
+    pub splits: Vec<Split>,
+    /// Sorted tag names, not counting those of split parts.
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+    /// Reference of the other leg when this is an internal transfer.
+    pub transfer: Option<String>,
+    /// Receipts and other files, oldest first.
+    pub attachments: Vec<Attachment>,
+}
+
+/// A file attached to a transaction, uploaded and downloaded over HTTP.
+#[derive(SimpleObject)]
+pub struct Attachment {
+    pub id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    /// Bytes
+    pub size: i64,
+    /// Hex SHA-256 of the content.
+    pub sha256: String,
+    /// RFC 3339
+    pub created_at: String,
+    /// Path to download the file from.
+    pub url: String,
+}
+
+impl From<attachments::Model> for Attachment {
+    fn from(a: attachments::Model) -> Self {
+        Attachment {
+            url: format!("/attachments/{}", a.id),
+            id: a.id,
+            file_name: a.file_name,
+            content_type: a.content_type,
+            size: a.size,
+            sha256: a.sha256,
+            created_at: a.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// SEPA references, purpose and card details of a remittance info.
+#[derive(SimpleObject)]
+pub struct Remittance {
+    pub end_to_end_reference: Option<String>,
+    pub mandate_reference: Option<String>,
+    pub creditor_id: Option<String>,
+    /// The text without the SEPA references.
+    pub purpose: String,
+    /// Who a payment service collected for.
+    pub ultimate_creditor: Option<String>,
+    /// `YYYY-MM-DD`, the day a card was used.
+    pub card_date: Option<String>,
+    /// Amount of a card payment in a foreign currency.
+    pub fx_amount: Option<f64>,
+    /// ISO 4217 code of `fxAmount`.
+    pub fx_currency: Option<String>,
+    /// Units of `fxCurrency` per euro.
+    pub fx_rate: Option<f64>,
+}
+
+impl From<transaction_remittance::Model> for Remittance {
+    fn from(r: transaction_remittance::Model) -> Self {
+        Remittance {
+            end_to_end_reference: r.end_to_end_reference,
+            mandate_reference: r.mandate_reference,
+            creditor_id: r.creditor_id,
+            purpose: r.purpose,
+            ultimate_creditor: r.ultimate_creditor,
+            card_date: r.card_date.map(|d| d.to_string()),
+            fx_amount: r.fx_amount,
+            fx_currency: r.fx_currency,
+            fx_rate: r.fx_rate,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Split {
+    pub amount: f64,
+    pub category: Category,
+    pub note: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl From<service::Split> for Split {
+    fn from((split, category): service::Split) -> Self {
+        Split {
+            amount: split.amount,
+            category: category.into(),
+            note: split.note,
+            tag: split.tag,
+        }
+    }
+}
+
+impl Transaction {
+    pub fn new(
+        t: account_transactions::Model,
+        category: Option<categories::Model>,
+        splits: Vec<service::Split>,
+    ) -> Self {
+        Transaction {
+            payee: crate::export::payee(&t),
+            reference: t.reference,
+            account_id: t.account_id,
+            booking_status: t.booking_status,
+            booking_date: t.booking_date.to_string(),
+            amount: t.amount,
+            counterparty_id: None,
+            remittance_info: t.remittance_info,
+            remittance: None,
+            transaction_type: t.transaction_type,
+            category: category.map(Category::from),
+            splits: splits.into_iter().map(Split::from).collect(),
+            tags: Vec::new(),
+            note: None,
+            transfer: None,
+            attachments: Vec::new(),
+        }
+    }
+}
+
+#[ComplexObject]
+impl Transaction {
+    /// Changes of the transaction, oldest first: why a total moved.
+    async fn history(&self, ctx: &Context<'_>) -> Result<Vec<HistoryEntry>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(Query::history(conn, &self.reference)
+            .await?
+            .into_iter()
+            .map(HistoryEntry::from)
+            .collect())
+    }
+}
+
+/// Attach categories, splits, tags, notes, transfers, counterparties, the
+/// parsed remittance info and attachments.
+pub async fn load_transactions(
+    conn: &DatabaseConnection,
+    transactions: Vec<account_transactions::Model>,
+) -> Result<Vec<Transaction>, async_graphql::Error> {
+    let mut categories = Query::categories_by_reference(conn).await?;
+    let mut splits = Query::splits_by_reference(conn).await?;
+    let mut tags = Query::tags_by_reference(conn).await?;
+    let mut notes = Query::notes_by_reference(conn).await?;
+    let mut transfers = Query::transfers_by_reference(conn).await?;
+    let mut counterparties = Query::counterparties_by_reference(conn).await?;
+    let mut remittances = Query::remittances_by_reference(conn).await?;
+    let mut attachments = Query::attachments_by_reference(conn).await?;
+    Ok(transactions
+        .into_iter()
+        .map(|t| {
+            let category = categories.remove(&t.reference);
+            let splits = splits.remove(&t.reference).unwrap_or_default();
+            let tags = tags.remove(&t.reference).unwrap_or_default();
+            let note = notes.remove(&t.reference);
+            let transfer = transfers.remove(&t.reference);
+            let counterparty = counterparties.remove(&t.reference);
+            let remittance = remittances.remove(&t.reference).map(Remittance::from);
+            let attachments = attachments.remove(&t.reference).unwrap_or_default();
+            let transaction = Transaction::new(t, category, splits);
+            Transaction {
+                tags,
+                note,
+                transfer,
+                remittance,
+                attachments: attachments.into_iter().map(Attachment::from).collect(),
+                payee: counterparty.as_ref().map(|c| c.name.clone()).unwrap_or(transaction.payee),
+                counterparty_id: counterparty.map(|c| c.id),
+                ..transaction
+            }
+        })
+        .collect())
+}
+
+/// Restricts which transactions a query looks at.
+#[derive(InputObject, Default)]
+pub struct TransactionFilters {
+    pub account_ids: Option<Vec<String>>,
+    /// `YYYY-MM-DD`, inclusive
+    pub from: Option<String>,
+    /// `YYYY-MM-DD`, inclusive
+    pub to: Option<String>,
+    /// Filter expression like `amount<-100 category:food -payee~rewe`.
+    pub filter: Option<String>,
+    /// Tags a transaction must all carry.
+    pub tags: Option<Vec<String>>,
+    /// Keep internal transfers between own accounts, which are left out by
+    /// default.
+    #[graphql(default)]
+    pub include_transfers: bool,
+}
+
+impl TransactionFilters {
+    fn into_filter(self) -> Result<TransactionFilter, async_graphql::Error> {
+        Ok(TransactionFilter {
+            accounts: self.account_ids.unwrap_or_default(),
+            from: parse_date(self.from)?,
+            to: parse_date(self.to)?,
+            query: parse_filter(self.filter)?,
+            tags: self.tags.unwrap_or_default(),
+            without_transfers: !self.include_transfers,
+        })
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Tag {
+    pub name: String,
+    /// Transactions carrying the tag, not counting split parts.
+    pub transactions: usize,
+}
+
+/// A canonical payee.
+#[derive(SimpleObject)]
+pub struct Counterparty {
+    pub id: i32,
+    pub name: String,
+    /// Transactions resolved to the counterparty.
+    pub transactions: usize,
+    pub rules: Vec<CounterpartyRule>,
+}
+
+#[derive(SimpleObject)]
+pub struct CounterpartyRule {
+    pub id: i32,
+    /// `alias`, `regex`, `iban` or `creditor_id`
+    pub kind: String,
+    pub pattern: String,
+}
+
+impl From<counterparty_rules::Model> for CounterpartyRule {
+    fn from(r: counterparty_rules::Model) -> Self {
+        CounterpartyRule {
+            id: r.id,
+            kind: r.kind,
+            pattern: r.pattern,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum CounterpartyRuleKind {
+    /// A payee name, compared after normalization.
+    Alias,
+    /// A case-insensitive regular expression over the payee name.
+    Regex,
+    /// The IBAN of the other party.
+    Iban,
+    /// The SEPA creditor ID of a direct debit.
+    CreditorId,
+}
+
+impl From<CounterpartyRuleKind> for service::CounterpartyRuleKind {
+    fn from(kind: CounterpartyRuleKind) -> Self {
+        match kind {
+            CounterpartyRuleKind::Alias => service::CounterpartyRuleKind::Alias,
+            CounterpartyRuleKind::Regex => service::CounterpartyRuleKind::Regex,
+            CounterpartyRuleKind::Iban => service::CounterpartyRuleKind::Iban,
+            CounterpartyRuleKind::CreditorId => service::CounterpartyRuleKind::CreditorId,
+        }
+    }
+}
+
+/// A rule that categorizes transactions before the model is asked. Every
+/// criterion that is not `null` has to match.
+#[derive(SimpleObject)]
+pub struct CategorizationRule {
+    pub id: i32,
+    pub name: String,
+    /// Rules with a higher priority are tried first.
+    pub priority: i32,
+    pub category: Category,
+    /// Part of the payee name, ignoring case.
+    pub payee: Option<String>,
+    pub iban: Option<String>,
+    pub creditor_id: Option<String>,
+    pub mandate_id: Option<String>,
+    /// Inclusive; debits are negative.
+    pub min_amount: Option<f64>,
+    /// Inclusive; debits are negative.
+    pub max_amount: Option<f64>,
+    pub transaction_type: Option<String>,
+    /// Case-insensitive regular expression searched in the remittance info.
+    pub remittance_pattern: Option<String>,
+    /// RFC 3339
+    pub created_at: String,
+    /// RFC 3339
+    pub updated_at: String,
+}
+
+impl From<(categorization_rules::Model, categories::Model)> for CategorizationRule {
+    fn from((r, category): (categorization_rules::Model, categories::Model)) -> Self {
+        CategorizationRule {
+            id: r.id,
+            name: r.name,
+            priority: r.priority,
+            category: category.into(),
+            payee: r.payee,
+            iban: r.iban,
+            creditor_id: r.creditor_id,
+            mandate_id: r.mandate_id,
+            min_amount: r.min_amount,
+            max_amount: r.max_amount,
+            transaction_type: r.transaction_type,
+            remittance_pattern: r.remittance_pattern,
+            created_at: r.created_at.to_rfc3339(),
+            updated_at: r.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+/// The category every direct debit of a mandate gets, learned from the
+/// first one a user categorized.
+#[derive(SimpleObject)]
+pub struct MandateCategory {
+    pub mandate_id: String,
+    pub category: Category,
+    /// The payee of the debit it was learned from.
+    pub description: Option<String>,
+}
+
+impl From<(mandate_categories::Model, categories::Model)> for MandateCategory {
+    fn from((m, category): (mandate_categories::Model, categories::Model)) -> Self {
+        MandateCategory {
+            mandate_id: m.creditor_mandate_id,
+            category: category.into(),
+            description: m.description,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum MandateConflictStatus {
+    Open,
+    /// The mandate now maps to the category of the debit.
+    Remapped,
+    /// The mapping stayed; the debit is an exception.
+    Kept,
+}
+
+impl From<MandateConflictStatus> for service::MandateConflictStatus {
+    fn from(status: MandateConflictStatus) -> Self {
+        match status {
+            MandateConflictStatus::Open => service::MandateConflictStatus::Open,
+            MandateConflictStatus::Remapped => service::MandateConflictStatus::Remapped,
+            MandateConflictStatus::Kept => service::MandateConflictStatus::Kept,
+        }
+    }
+}
+
+/// A direct debit a user put into another category than its mandate maps to.
+#[derive(SimpleObject)]
+pub struct MandateConflict {
+    pub id: i32,
+    pub mandate_id: String,
+    /// `open`, `remapped` or `kept`
+    pub status: String,
+    /// The category the user chose for the debit.
+    pub category: Category,
+    /// The category the mandate maps to now.
+    pub mapped_category: Option<Category>,
+    pub transaction: Option<Transaction>,
+    /// RFC 3339
+    pub detected_at: String,
+    /// RFC 3339
+    pub resolved_at: Option<String>,
+}
+
+/// Attach the debit and the current mapping of each conflict.
+pub async fn load_mandate_conflicts(
+    conn: &DatabaseConnection,
+    conflicts: Vec<(mandate_category_conflicts::Model, categories::Model)>,
+) -> Result<Vec<MandateConflict>, async_graphql::Error> {
+    let mapped: HashMap<String, categories::Model> = Query::mandate_categories(conn)
+        .await?
+        .into_iter()
+        .map(|(m, category)| (m.creditor_mandate_id, category))
+        .collect();
+    let references: Vec<String> = conflicts.iter().map(|(c, _)| c.reference.clone()).collect();
+    let transactions = Query::transactions_by_reference(conn, &references).await?;
+    let mut transactions: HashMap<String, Transaction> = load_transactions(conn, transactions)
+        .await?
+        .into_iter()
+        .map(|t| (t.reference.clone(), t))
+        .collect();
+    Ok(conflicts
+        .into_iter()
+        .map(|(c, category)| MandateConflict {
+            id: c.id,
+            mapped_category: mapped.get(&c.creditor_mandate_id).cloned().map(Category::from),
+            transaction: transactions.remove(&c.reference),
+            mandate_id: c.creditor_mandate_id,
+            status: c.status,
+            category: category.into(),
+            detected_at: c.detected_at.to_rfc3339(),
+            resolved_at: c.resolved_at.map(|at| at.to_rfc3339()),
+        })
+        .collect())
+}
+
+/// Attach rules and transaction counts.
+pub async fn load_counterparties(
+    conn: &DatabaseConnection,
+    counterparties: Vec<counterparties::Model>,
+) -> Result<Vec<Counterparty>, async_graphql::Error> {
+    let counts: HashMap<i32, usize> =
+        Query::counterparties(conn).await?.into_iter().map(|(c, count)| (c.id, count)).collect();
+    let mut rules: HashMap<i32, Vec<CounterpartyRule>> = HashMap::new();
+    for rule in Query::counterparty_rules(conn).await? {
+        rules.entry(rule.counterparty_id).or_default().push(rule.into());
+    }
+    Ok(counterparties
+        .into_iter()
+        .map(|c| Counterparty {
+            transactions: counts.get(&c.id).copied().unwrap_or_default(),
+            rules: rules.remove(&c.id).unwrap_or_default(),
+            id: c.id,
+            name: c.name,
+        })
+        .collect())
+}
+
+/// Spending with one counterparty. Both sums are positive.
+#[derive(SimpleObject)]
+pub struct PayeeTotal {
+    pub counterparty_id: i32,
+    pub name: String,
+    pub count: usize,
+    pub expenses: f64,
+    pub income: f64,
+}
+
+#[derive(SimpleObject)]
+pub struct SearchResult {
+    pub transaction: Transaction,
+    /// Higher is better; only comparable within one search.
+    pub rank: f64,
+    /// Excerpt of the remittance info and parties as escaped HTML, matches
+    /// wrapped in `<mark>`.
+    pub snippet: String,
+}
+
+/// One changed field of a transaction.
+#[derive(SimpleObject)]
+pub struct HistoryEntry {
+    /// RFC 3339
+    pub changed_at: String,
+    /// `import`, `import-run:<id>`, `user`, `categorizer`, `transfer-matcher`,
+    /// `rule:<id>` or `mandate`
+    pub source: String,
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+impl From<transaction_history::Model> for HistoryEntry {
+    fn from(h: transaction_history::Model) -> Self {
+        HistoryEntry {
+            changed_at: h.changed_at.to_rfc3339(),
+            source: h.source,
+            field: h.field,
+            old_value: h.old_value,
+            new_value: h.new_value,
+        }
+    }
+}
+
+/// Reconstructed end-of-day balance.
+#[derive(SimpleObject)]
+pub struct DailyBalance {
+    pub account_id: String,
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub amount: f64,
+    /// Stored snapshot of that day, if the importer ran.
+    pub snapshot: Option<f64>,
+    /// Snapshot minus reconstruction; non-zero hints at missing or
+    /// duplicate transactions.
+    pub difference: Option<f64>,
+}
+
+impl From<daily_balances::Model> for DailyBalance {
+    fn from(d: daily_balances::Model) -> Self {
+        let day = balance::DailyBalance {
+            date: d.date,
+            amount: d.amount,
+            snapshot: d.snapshot,
+        };
+        DailyBalance {
+            account_id: d.account_id,
+            date: d.date.to_string(),
+            amount: d.amount,
+            snapshot: d.snapshot,
+            difference: day.difference(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ImportRun {
+    pub id: i32,
+    /// `bootstrap` or `import`
+    pub kind: String,
+    pub connector: String,
+    /// RFC 3339
+    pub started_at: String,
+    /// RFC 3339; absent while running or after a crash.
+    pub finished_at: Option<String>,
+    /// `running`, `succeeded` or `failed`
+    pub status: String,
+    pub pages_fetched: i32,
+    pub error: Option<String>,
+    pub accounts: Vec<ImportRunAccount>,
+}
+
+impl From<service::ImportRun> for ImportRun {
+    fn from(r: service::ImportRun) -> Self {
+        let status = match (&r.run.finished_at, &r.run.error) {
+            (_, Some(_)) => "failed",
+            (None, None) => "running",
+            (Some(_), None) => "succeeded",
+        };
+        ImportRun {
+            id: r.run.id,
+            kind: r.run.kind,
+            connector: r.run.connector,
+            started_at: r.run.started_at.to_rfc3339(),
+            finished_at: r.run.finished_at.map(|t| t.to_rfc3339()),
+            status: status.to_string(),
+            pages_fetched: r.run.pages_fetched,
+            error: r.run.error,
+            accounts: r.accounts.into_iter().map(ImportRunAccount::from).collect(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ImportRunAccount {
+    pub account_id: String,
+    pub fetched: i32,
+    pub inserted: i32,
+    pub updated: i32,
+    pub failed: i32,
+}
+
+impl From<import_run_accounts::Model> for ImportRunAccount {
+    fn from(a: import_run_accounts::Model) -> Self {
+        ImportRunAccount {
+            account_id: a.account_id,
+            fetched: a.fetched,
+            inserted: a.inserted,
+            updated: a.updated,
+            failed: a.failed,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum FindingKind {
+    Duplicate,
+    Gap,
+}
+
+impl From<FindingKind> for service::FindingKind {
+    fn from(kind: FindingKind) -> Self {
+        match kind {
+            FindingKind::Duplicate => service::FindingKind::Duplicate,
+            FindingKind::Gap => service::FindingKind::Gap,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum FindingStatus {
+    Open,
+    Merged,
+    Ignored,
+    Kept,
+}
+
+impl From<FindingStatus> for service::FindingStatus {
+    fn from(status: FindingStatus) -> Self {
+        match status {
+            FindingStatus::Open => service::FindingStatus::Open,
+            FindingStatus::Merged => service::FindingStatus::Merged,
+            FindingStatus::Ignored => service::FindingStatus::Ignored,
+            FindingStatus::Kept => service::FindingStatus::Kept,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum TransferStatus {
+    Linked,
+    /// Unlinked by a user; the matcher leaves the legs alone.
+    Unlinked,
+}
+
+impl From<TransferStatus> for service::TransferStatus {
+    fn from(status: TransferStatus) -> Self {
+        match status {
+            TransferStatus::Linked => service::TransferStatus::Linked,
+            TransferStatus::Unlinked => service::TransferStatus::Unlinked,
+        }
+    }
+}
+
+/// Money moved between two own accounts.
+#[derive(SimpleObject)]
+pub struct Transfer {
+    pub id: i32,
+    /// `linked` or `unlinked`
+    pub status: String,
+    /// Who linked or unlinked the pair last: `user` or `transfer-matcher`.
+    pub source: String,
+    /// RFC 3339
+    pub created_at: String,
+    /// The debit on the sending account.
+    pub outgoing: Option<Transaction>,
+    /// The credit on the receiving account.
+    pub incoming: Option<Transaction>,
+}
+
+/// Attach the legs of each transfer.
+pub async fn load_transfers(
+    conn: &DatabaseConnection,
+    transfers: Vec<internal_transfers::Model>,
+) -> Result<Vec<Transfer>, async_graphql::Error> {
+    let references: Vec<String> = transfers
+        .iter()
+        .flat_map(|t| [t.outgoing_reference.clone(), t.incoming_reference.clone()])
+        .collect();
+    let transactions = Query::transactions_by_reference(conn, &references).await?;
+    let mut legs: HashMap<String, Transaction> = load_transactions(conn, transactions)
+        .await?
+        .into_iter()
+        .map(|t| (t.reference.clone(), t))
+        .collect();
+    Ok(transfers
+        .into_iter()
+        .map(|t| Transfer {
+            id: t.id,
+            status: t.status,
+            source: t.source,
+            created_at: t.created_at.to_rfc3339(),
+            outgoing: legs.remove(&t.outgoing_reference),
+            incoming: legs.remove(&t.incoming_reference),
+        })
+        .collect())
+}
+
+/// A data-quality finding for review.
+#[derive(SimpleObject)]
+pub struct Finding {
+    pub id: i32,
+    /// `duplicate` or `gap`
+    pub kind: String,
+    /// `open`, `merged`, `ignored` or `kept`
+    pub status: String,
+    pub account_id: String,
+    /// `YYYY-MM-DD`
+    pub date_from: String,
+    /// `YYYY-MM-DD`
+    pub date_to: String,
+    /// Amount of a duplicate, or the balance change a gap leaves unexplained.
+    pub amount: Option<f64>,
+    pub similarity: Option<f64>,
+    /// The transactions of a duplicate pair that still exist.
+    pub transactions: Vec<Transaction>,
+}
+
+impl Finding {
+    pub fn new(f: data_findings::Model, transactions: Vec<Transaction>) -> Self {
+        Finding {
+            id: f.id,
+            kind: f.kind,
+            status: f.status,
+            account_id: f.account_id,
+            date_from: f.date_from.to_string(),
+            date_to: f.date_to.to_string(),
+            amount: f.amount,
+            similarity: f.similarity,
+            transactions,
+        }
+    }
+}
+
+/// Attach the transactions a finding refers to.
+pub async fn load_findings(
+    conn: &DatabaseConnection,
+    findings: Vec<data_findings::Model>,
+) -> Result<Vec<Finding>, async_graphql::Error> {
+    let references: Vec<String> = findings
+        .iter()
+        .flat_map(|f| [f.reference.clone(), f.other_reference.clone()])
+        .flatten()
+        .collect();
+    let transactions = Query::transactions_by_reference(conn, &references).await?;
+    let mut transactions: Vec<(String, Transaction)> = load_transactions(conn, transactions)
+        .await?
+        .into_iter()
+        .map(|t| (t.reference.clone(), t))
+        .collect();
+    Ok(findings
+        .into_iter()
+        .map(|f| {
+            let refs = [f.reference.clone(), f.other_reference.clone()];
+            let (own, rest) = transactions
+                .drain(..)
+                .partition(|(reference, _)| refs.contains(&Some(reference.clone())));
+            transactions = rest;
+            Finding::new(f, own.into_iter().map(|(_, t)| t).collect())
+        })
+        .collect())
+}
+
+/// Count and sums of a set of transactions. Both sums are positive.
+#[derive(SimpleObject)]
+pub struct Totals {
+    pub count: usize,
+    pub income: f64,
+    pub expenses: f64,
+    pub net: f64,
+}
+
+impl From<report::Totals> for Totals {
+    fn from(t: report::Totals) -> Self {
+        Totals {
+            count: t.count,
+            income: t.income,
+            expenses: t.expenses,
+            net: t.net(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct MonthTotals {
+    /// `YYYY-MM`
+    pub month: String,
+    pub totals: Totals,
+}
+
+/// A named filter expression, like a smart folder. Totals, series and
+/// transactions are computed from the current data on every request.
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct SavedView {
+    pub id: i32,
+    pub name: String,
+    pub filter: String,
+    /// RFC 3339
+    pub created_at: String,
+    /// RFC 3339
+    pub updated_at: String,
+    #[graphql(skip)]
+    pub model: saved_views::Model,
+}
+
+impl From<saved_views::Model> for SavedView {
+    fn from(v: saved_views::Model) -> Self {
+        SavedView {
+            id: v.id,
+            name: v.name.clone(),
+            filter: v.filter.clone(),
+            created_at: v.created_at.to_rfc3339(),
+            updated_at: v.updated_at.to_rfc3339(),
+            model: v,
+        }
+    }
+}
+
+#[ComplexObject]
+impl SavedView {
+    /// Totals of the matching transactions; dates are `YYYY-MM-DD` and
+    /// inclusive. Internal transfers only count with `includeTransfers`.
+    async fn totals(
+        &self,
+        ctx: &Context<'_>,
+        from: Option<String>,
+        to: Option<String>,
+        #[graphql(default)] include_transfers: bool,
+    ) -> Result<Totals, async_graphql::Error> {
+        let transactions = self.load(ctx, from, to, !include_transfers).await?;
+        Ok(report::Totals::of(&transactions).into())
+    }
+
+    /// Totals per month from the first to the last month with a matching
+    /// transaction. Internal transfers only count with `includeTransfers`.
+    async fn monthly(
+        &self,
+        ctx: &Context<'_>,
+        from: Option<String>,
+        to: Option<String>,
+        #[graphql(default)] include_transfers: bool,
+    ) -> Result<Vec<MonthTotals>, async_graphql::Error> {
+        let transactions = self.load(ctx, from, to, !include_transfers).await?;
+        Ok(report::monthly_totals(&transactions)
+            .into_iter()
+            .map(|((year, month), totals)| MonthTotals {
+                month: format!("{year:04}-{month:02}"),
+                totals: totals.into(),
+            })
+            .collect())
+    }
+
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        from: Option<String>,
+        to: Option<String>,
+    ) -> Result<Vec<Transaction>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let transactions = self.load(ctx, from, to, false).await?;
+        load_transactions(conn, transactions).await
+    }
+}
+
+impl SavedView {
+    async fn load(
+        &self,
+        ctx: &Context<'_>,
+        from: Option<String>,
+        to: Option<String>,
+        without_transfers: bool,
+    ) -> Result<Vec<account_transactions::Model>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let filter = TransactionFilter {
+            from: parse_date(from)?,
+            to: parse_date(to)?,
+            without_transfers,
+            ..Default::default()
+        };
+        Ok(views::transactions(conn, &self.model, filter).await?)
+    }
+}
+
+/// The user the request acts for; see [`views::OWNER_HEADER`].
+pub(crate) fn owner(ctx: &Context<'_>) -> Owner {
+    ctx.data_opt::<Owner>().cloned().unwrap_or_default()
+}
+
+fn parse_date(value: Option<String>) -> Result<Option<NaiveDate>, async_graphql::Error> {
+    value
+        .map(|v| {
+            v.parse()
+                .map_err(|_| async_graphql::Error::new(format!("invalid date `{v}`, expected YYYY-MM-DD")))
+        })
+        .transpose()
+}
+
+pub(crate) fn parse_filter(value: Option<String>) -> Result<Option<FilterQuery>, async_graphql::Error> {
+    value
+        .map(|v| {
+            v.parse().map_err(|e: FilterError| {
+                let column = e.column;
+                async_graphql::Error::new(e.to_string()).extend_with(|_, ext| ext.set("column", column))
+            })
+        })
+        .transpose()
+}
+
 #[Object]
 impl QueryRoot {
     async fn hello(&self) -> &str {
         "Hello, world!"
     }
 
-    async fn reports(&self, month: String, year: String) -> Vec<Report> {
-        dbg!(&month);
-        vec![
-            Report {
-                month: month.clone(),
-                year: year.clone(),
-                category: "Groceries".to_string(),
-                total_income: 1000.0,
-                total_expenses: 500.0,
-            },
-            Report {
-                month: month.clone(),
-                year: year.clone(),
-                category: "Utilities".to_string(),
-                total_income: 1000.0,
-                total_expenses: 500.0,
-            },
-            Report {
+    /// Income and expenses per category in one month, restricted to the
+    /// transactions of a saved view with `viewId` and to those carrying all
+    /// `tags`. Internal transfers only count with `includeTransfers`.
+    async fn reports(
+        &self,
+        ctx: &Context<'_>,
+        month: String,
+        year: String,
+        view_id: Option<i32>,
+        tags: Option<Vec<String>>,
+        #[graphql(default)] include_transfers: bool,
+    ) -> Result<Vec<Report>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let month_number: u32 = month
+            .parse()
+            .map_err(|_| async_graphql::Error::new(format!("invalid month `{month}`")))?;
+        let year_number: i32 = year
+            .parse()
+            .map_err(|_| async_graphql::Error::new(format!("invalid year `{year}`")))?;
+        if report::month_range(year_number, month_number).is_none() {
+            return Err(async_graphql::Error::new(format!("invalid month `{month}`")));
+        }
+
+        let mut selection = TransactionFilter {
+            tags: tags.unwrap_or_default(),
+            without_transfers: !include_transfers,
+            ..Default::default()
+        };
+        if let Some(id) = view_id {
+            let view = Query::saved_view(conn, &owner(ctx).0, id).await?;
+            selection = selection.with_view(&view)?;
+        }
+
+        Ok(report::monthly(conn, &selection, year_number, month_number)
+            .await?
+            .into_iter()
+            .map(|total| Report {
                 month: month.clone(),
                 year: year.clone(),
-                category: "Entertainment".to_string(),
-                total_income: 1000.0,
-                total_expenses: 500.0,
-            },
-        ]
+                category: total.category,
+                total_income: total.income,
+                total_expenses: total.expenses,
+            })
+            .collect())
+    }
+
+    async fn accounts(&self, ctx: &Context<'_>) -> Result<Vec<Account>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(Query::accounts(conn).await?.into_iter().map(Account::from).collect())
+    }
+
+    async fn categories(&self, ctx: &Context<'_>) -> Result<Vec<Category>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(Query::categories(conn).await?.into_iter().map(Category::from).collect())
+    }
+
+    /// Transactions in booking order; dates are `YYYY-MM-DD` and inclusive.
+    /// `filter` takes an expression like `amount<-100 category:food
+    /// -payee~rewe`; errors carry the `column` in their extensions. With
+    /// `tags` only transactions carrying all of them. Internal transfers are
+    /// left out unless `includeTransfers` is set.
+    #[allow(clippy::too_many_arguments)]
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        account_ids: Option<Vec<String>>,
+        from: Option<String>,
+        to: Option<String>,
+        filter: Option<String>,
+        tags: Option<Vec<String>>,
+        #[graphql(default)] include_transfers: bool,
+    ) -> Result<Vec<Transaction>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let filter = TransactionFilter {
+            accounts: account_ids.unwrap_or_default(),
+            from: parse_date(from)?,
+            to: parse_date(to)?,
+            query: parse_filter(filter)?,
+            tags: tags.unwrap_or_default(),
+            without_transfers: !include_transfers,
+        };
+        load_transactions(conn, Query::transactions(conn, &filter).await?).await
+    }
+
+    /// Importer runs and session bootstraps, newest first. With `succeeded`
+    /// only finished runs without (`true`) or with (`false`) an error.
+    async fn import_runs(
+        &self,
+        ctx: &Context<'_>,
+        succeeded: Option<bool>,
+        #[graphql(default = 20)] limit: u64,
+    ) -> Result<Vec<ImportRun>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(Query::import_runs(conn, succeeded, limit)
+            .await?
+            .into_iter()
+            .map(ImportRun::from)
+            .collect())
+    }
+
+    /// Reconstructed daily balances for net-worth charts; dates are
+    /// `YYYY-MM-DD` and inclusive.
+    async fn daily_balances(
+        &self,
+        ctx: &Context<'_>,
+        account_ids: Option<Vec<String>>,
+        from: Option<String>,
+        to: Option<String>,
+    ) -> Result<Vec<DailyBalance>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let filter = TransactionFilter {
+            accounts: account_ids.unwrap_or_default(),
+            from: parse_date(from)?,
+            to: parse_date(to)?,
+            ..Default::default()
+        };
+        Ok(Query::daily_balances(conn, &filter)
+            .await?
+            .into_iter()
+            .map(DailyBalance::from)
+            .collect())
+    }
+
+    /// Findings of the data-quality checks, newest first.
+    async fn findings(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "Some(FindingStatus::Open)")] status: Option<FindingStatus>,
+        kind: Option<FindingKind>,
+    ) -> Result<Vec<Finding>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let findings = Query::findings(conn, status.map(Into::into), kind.map(Into::into)).await?;
+        load_findings(conn, findings).await
+    }
+
+    /// Internal transfers between own accounts, newest first.
+    async fn transfers(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "Some(TransferStatus::Linked)")] status: Option<TransferStatus>,
+    ) -> Result<Vec<Transfer>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        load_transfers(conn, Query::transfers(conn, status.map(Into::into)).await?).await
+    }
+
+    /// Free-text search over remittance info, remitter, creditor and deptor,
+    /// best match first. On Postgres `"phrase"`, `-excluded` and `or` work
+    /// as in web search.
+    async fn search_transactions(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        filters: Option<TransactionFilters>,
+        #[graphql(default = 50)] limit: u64,
+    ) -> Result<Vec<SearchResult>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let filter = filters.unwrap_or_default().into_filter()?;
+        let hits = search::search(conn, &query, &filter, limit).await?;
+        let transactions = hits.iter().map(|hit| hit.transaction.clone()).collect();
+        Ok(load_transactions(conn, transactions)
+            .await?
+            .into_iter()
+            .zip(hits)
+            .map(|(transaction, hit)| SearchResult {
+                transaction,
+                rank: hit.rank,
+                snippet: hit.snippet,
+            })
+            .collect())
+    }
+
+    /// Saved views of the current user by name.
+    async fn saved_views(&self, ctx: &Context<'_>) -> Result<Vec<SavedView>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(Query::saved_views(conn, &owner(ctx).0)
+            .await?
+            .into_iter()
+            .map(SavedView::from)
+            .collect())
+    }
+
+    async fn saved_view(&self, ctx: &Context<'_>, id: i32) -> Result<SavedView, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(Query::saved_view(conn, &owner(ctx).0, id).await?.into())
+    }
+
+    /// Every tag with the number of transactions carrying it.
+    /// Canonical payees by name, with their rules.
+    async fn counterparties(&self, ctx: &Context<'_>) -> Result<Vec<Counterparty>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let counterparties = Query::counterparties(conn).await?.into_iter().map(|(c, _)| c).collect();
+        load_counterparties(conn, counterparties).await
+    }
+
+    /// Categorization rules in the order they are tried.
+    async fn categorization_rules(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<CategorizationRule>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(Query::categorization_rules(conn).await?.into_iter().map(CategorizationRule::from).collect())
+    }
+
+    /// Mandates with the category their direct debits get.
+    async fn mandate_categories(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<MandateCategory>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(Query::mandate_categories(conn).await?.into_iter().map(MandateCategory::from).collect())
+    }
+
+    /// Debits categorized against their mandate, newest first.
+    async fn mandate_conflicts(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "Some(MandateConflictStatus::Open)")] status: Option<MandateConflictStatus>,
+    ) -> Result<Vec<MandateConflict>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        load_mandate_conflicts(conn, Query::mandate_conflicts(conn, status.map(Into::into)).await?).await
+    }
+
+    /// Counterparties by the money spent with them, most first. Internal
+    /// transfers are left out unless `filters` include them.
+    async fn top_payees(
+        &self,
+        ctx: &Context<'_>,
+        filters: Option<TransactionFilters>,
+        #[graphql(default = 10)] limit: usize,
+    ) -> Result<Vec<PayeeTotal>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        let filter = filters.unwrap_or_default().into_filter()?;
+        Ok(counterparty::top_payees(conn, &filter, limit)
+            .await?
+            .into_iter()
+            .map(|p| PayeeTotal {
+                counterparty_id: p.counterparty.id,
+                name: p.counterparty.name,
+                count: p.totals.count,
+                expenses: p.totals.expenses,
+                income: p.totals.income,
+            })
+            .collect())
+    }
+
+    async fn tags(&self, ctx: &Context<'_>) -> Result<Vec<Tag>, async_graphql::Error> {
+        let conn = ctx.data::<Arc<DatabaseConnection>>()?;
+        Ok(Query::tags(conn)
+            .await?
+            .into_iter()
+            .map(|(tag, transactions)| Tag {
+                name: tag.name,
+                transactions,
+            })
+            .collect())
     }
 }
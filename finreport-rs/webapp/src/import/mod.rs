@@ -0,0 +1,98 @@
+pub mod paypal;
+pub mod profile;
+
+use crate::import::profile::{ImportProfile, ParsedRow};
+use crate::service::{
+    AccountInput, ChangeSource, Mutation, Query, ServiceError, TransactionInput, UpsertSummary,
+};
+use sea_orm::DbConn;
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+/// Target account of a file import. The account is created on first import
+/// when `iban` is given.
+pub struct ImportAccount {
+    pub account_id: String,
+    pub iban: Option<String>,
+    pub bic: Option<String>,
+}
+
+/// CSV exports carry no stable booking id, so the reference is derived from
+/// the row contents. Re-importing an overlapping export therefore updates the
+/// existing rows instead of duplicating them.
+pub fn synthesize_reference(profile: &str, account_id: &str, row: &ParsedRow) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(account_id.as_bytes());
+    hasher.update(row.booking_date.to_string().as_bytes());
+    hasher.update(format!("{:.2}", row.amount).as_bytes());
+    hasher.update(row.counterparty.as_bytes());
+    hasher.update(row.remittance_info.as_bytes());
+    hasher.update(row.occurrence.to_le_bytes());
+    let digest = hex::encode(hasher.finalize());
+    format!("{profile}-{}", &digest[..24])
+}
+
+/// Create the target account unless it exists. Creating needs the IBAN.
+pub async fn ensure_account(
+    conn: &DbConn,
+    target: &ImportAccount,
+    institute: &str,
+) -> Result<(), ServiceError> {
+    if Query::find_account(conn, &target.account_id).await?.is_some() {
+        return Ok(());
+    }
+    let Some(iban) = &target.iban else {
+        return Err(ServiceError::AccountNotFound(target.account_id.clone()));
+    };
+    let input = AccountInput {
+        account_id: target.account_id.clone(),
+        display_id: target.account_id.clone(),
+        account_type: "CSV".to_string(),
+        iban: iban.clone(),
+        bic: target.bic.clone().unwrap_or_default(),
+        institute: institute.to_string(),
+    };
+    Mutation::ensure_account(conn, input).await?;
+    info!(account_id = %target.account_id, %institute, "created account");
+    Ok(())
+}
+
+/// Upsert parsed rows into `account_transactions`, keyed by the synthesized
+/// reference. Failing rows are logged and counted, not fatal.
+pub async fn import_rows(
+    conn: &DbConn,
+    profile: &ImportProfile,
+    target: &ImportAccount,
+    rows: &[ParsedRow],
+) -> Result<UpsertSummary, ServiceError> {
+    ensure_account(conn, target, &profile.institute).await?;
+
+    let inputs = rows
+        .iter()
+        .map(|row| {
+            // Comdirect stores the payer of incoming and the payee of outgoing
+            // payments in different columns; mirror that for file imports.
+            let (remitter, creditor) = if row.amount > 0.0 {
+                (row.counterparty.clone(), String::new())
+            } else {
+                (String::new(), row.counterparty.clone())
+            };
+            TransactionInput {
+                reference: synthesize_reference(&profile.name, &target.account_id, row),
+                account_id: target.account_id.clone(),
+                booking_status: row.booking_status.clone(),
+                booking_date: row.booking_date,
+                amount: row.amount,
+                remitter,
+                deptor: String::new(),
+                creditor,
+                creditor_id: row.creditor_id.clone(),
+                creditor_mandate_id: row.mandate_id.clone(),
+                remittance_info: row.remittance_info.clone(),
+                transaction_type: row.transaction_type.clone(),
+                counterparty_iban: row.counterparty_iban.clone(),
+            }
+        })
+        .collect();
+    Mutation::upsert_transactions(conn, inputs, ChangeSource::Import).await
+}
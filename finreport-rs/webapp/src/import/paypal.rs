@@ -0,0 +1,393 @@
+use crate::import::profile::{parse_amount, ProfileError, RowError};
+use chrono::NaiveDate;
+use entity::entities::{account_transactions, paypal_transactions};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DbConn, DbErr, EntityTrait, QueryFilter, Set,
+};
+use std::collections::{HashMap, HashSet};
+use tracing::{debug, info};
+
+/// SEPA creditor id PayPal (Europe) uses for all direct debits.
+pub const PAYPAL_CREDITOR_ID: &str = "LU96ZZZ0000000000000000058";
+
+/// PayPal exports the activity report with localized headers; the first
+/// name of each pair is the German one.
+const COLUMNS: &[(&str, &str)] = &[
+    ("Datum", "Date"),
+    ("Name", "Name"),
+    ("Typ", "Type"),
+    ("Status", "Status"),
+    ("Währung", "Currency"),
+    ("Brutto", "Gross"),
+    ("Gebühr", "Fee"),
+    ("Netto", "Net"),
+    ("Empfänger E-Mail-Adresse", "To Email Address"),
+    ("Transaktionscode", "Transaction ID"),
+    ("Artikelbezeichnung", "Item Title"),
+    ("Zugehöriger Transaktionscode", "Reference Txn ID"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaypalRow {
+    pub transaction_code: String,
+    pub date: NaiveDate,
+    pub name: String,
+    pub r#type: String,
+    pub status: String,
+    pub currency: String,
+    pub gross: f64,
+    pub fee: f64,
+    pub net: f64,
+    pub email: String,
+    pub item_title: String,
+    pub related_transaction_code: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedActivity {
+    pub rows: Vec<PaypalRow>,
+    pub errors: Vec<RowError>,
+}
+
+/// Parse a PayPal activity report ("Aktivitäten herunterladen", CSV with all
+/// columns) in either the German or the English layout.
+pub fn parse_activity_report(bytes: &[u8]) -> Result<ParsedActivity, ProfileError> {
+    let (text, _, _) = encoding_rs::UTF_8.decode(bytes);
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let headers = reader.headers()?.clone();
+    let position = |name: &str| headers.iter().position(|h| h.trim() == name);
+
+    let german = position(COLUMNS[0].0).is_some();
+    let (decimal_separator, date_format) = if german {
+        (',', "%d.%m.%Y")
+    } else {
+        ('.', "%m/%d/%Y")
+    };
+    let mut indices = Vec::with_capacity(COLUMNS.len());
+    for (de, en) in COLUMNS {
+        let name = if german { de } else { en };
+        indices.push(position(name).ok_or_else(|| ProfileError::MissingColumn(name.to_string()))?);
+    }
+
+    let mut activity = ParsedActivity::default();
+    for (i, record) in reader.records().enumerate() {
+        let line = i + 2;
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                activity.errors.push(RowError {
+                    line,
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+        let field = |column: usize| record.get(indices[column]).unwrap_or("").trim().to_string();
+        let amount = |column: usize| parse_amount(&field(column), decimal_separator);
+        let row = NaiveDate::parse_from_str(&field(0), date_format)
+            .map_err(|e| format!("invalid date `{}`: {e}", field(0)))
+            .and_then(|date| {
+                Ok(PaypalRow {
+                    transaction_code: field(9),
+                    date,
+                    name: field(1),
+                    r#type: field(2),
+                    status: field(3),
+                    currency: field(4),
+                    gross: amount(5)?,
+                    fee: amount(6).unwrap_or(0.0),
+                    net: amount(7)?,
+                    email: field(8),
+                    item_title: field(10),
+                    related_transaction_code: field(11),
+                })
+            });
+        match row {
+            Ok(row) if row.transaction_code.is_empty() => activity.errors.push(RowError {
+                line,
+                message: "missing transaction code".to_string(),
+            }),
+            Ok(row) => activity.rows.push(row),
+            Err(message) => activity.errors.push(RowError { line, message }),
+        }
+    }
+    Ok(activity)
+}
+
+pub async fn import_activity(conn: &DbConn, rows: &[PaypalRow]) -> Result<usize, DbErr> {
+    for row in rows {
+        let paypal_orm = paypal_transactions::ActiveModel {
+            transaction_code: Set(row.transaction_code.clone()),
+            date: Set(row.date),
+            name: Set(row.name.clone()),
+            r#type: Set(row.r#type.clone()),
+            status: Set(row.status.clone()),
+            currency: Set(row.currency.clone()),
+            gross: Set(row.gross),
+            fee: Set(row.fee),
+            net: Set(row.net),
+            email: Set(row.email.clone()),
+            item_title: Set(row.item_title.clone()),
+            related_transaction_code: Set(row.related_transaction_code.clone()),
+            ..Default::default()
+        };
+        paypal_transactions::Entity::insert(paypal_orm)
+            .on_conflict(
+                OnConflict::column(paypal_transactions::Column::TransactionCode)
+                    .update_columns([
+                        paypal_transactions::Column::Date,
+                        paypal_transactions::Column::Name,
+                        paypal_transactions::Column::Type,
+                        paypal_transactions::Column::Status,
+                        paypal_transactions::Column::Currency,
+                        paypal_transactions::Column::Gross,
+                        paypal_transactions::Column::Fee,
+                        paypal_transactions::Column::Net,
+                        paypal_transactions::Column::Email,
+                        paypal_transactions::Column::ItemTitle,
+                        paypal_transactions::Column::RelatedTransactionCode,
+                    ])
+                    .to_owned(),
+            )
+            .exec(conn)
+            .await?;
+    }
+    info!(rows = rows.len(), "[paypal] imported activity");
+    Ok(rows.len())
+}
+
+/// A merchant payment, with the amount in EUR as it was debited.
+#[derive(Debug, Clone)]
+pub struct PaypalPayment {
+    pub transaction_code: String,
+    pub date: NaiveDate,
+    pub merchant: String,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BankDebit {
+    pub reference: String,
+    pub booking_date: NaiveDate,
+    pub amount: f64,
+    pub remittance_info: String,
+}
+
+/// Merchant payments out of an activity report. Payments in a foreign
+/// currency take their EUR amount from the related conversion row.
+pub fn merchant_payments(rows: &[paypal_transactions::Model]) -> Vec<PaypalPayment> {
+    let conversions: HashMap<&str, f64> = rows
+        .iter()
+        .filter(|r| r.name.is_empty() && r.currency == "EUR" && r.gross < 0.0)
+        .filter(|r| !r.related_transaction_code.is_empty())
+        .map(|r| (r.related_transaction_code.as_str(), r.gross))
+        .collect();
+    rows.iter()
+        .filter(|r| r.gross < 0.0 && !r.name.is_empty())
+        .filter_map(|r| {
+            let amount = if r.currency == "EUR" {
+                Some(r.gross)
+            } else {
+                conversions.get(r.transaction_code.as_str()).copied()
+            }?;
+            Some(PaypalPayment {
+                transaction_code: r.transaction_code.clone(),
+                date: r.date,
+                merchant: r.name.clone(),
+                amount,
+            })
+        })
+        .collect()
+}
+
+/// Pair payments with the bank debits that funded them. A pair needs the
+/// same amount and a debit booked within `window_days` after the payment.
+/// Debits naming the merchant win over closer dates; each side is used once.
+pub fn match_payments(
+    payments: &[PaypalPayment],
+    debits: &[BankDebit],
+    window_days: u64,
+) -> Vec<(String, String)> {
+    let mut candidates = Vec::new();
+    for payment in payments {
+        let merchant = payment.merchant.to_lowercase();
+        for debit in debits {
+            // Debits booked before the payment cannot have funded it.
+            let Ok(days) = u64::try_from((debit.booking_date - payment.date).num_days()) else {
+                continue;
+            };
+            if (debit.amount - payment.amount).abs() > 0.005 || days > window_days {
+                continue;
+            }
+            let named = debit.remittance_info.to_lowercase().contains(&merchant);
+            candidates.push((!named, days, payment, debit));
+        }
+    }
+    candidates.sort_by_key(|(unnamed, days, payment, _)| (*unnamed, *days, payment.date));
+
+    let mut used_payments = HashSet::new();
+    let mut used_debits = HashSet::new();
+    let mut pairs = Vec::new();
+    for (_, _, payment, debit) in candidates {
+        if used_payments.contains(&payment.transaction_code) || used_debits.contains(&debit.reference) {
+            continue;
+        }
+        used_payments.insert(payment.transaction_code.clone());
+        used_debits.insert(debit.reference.clone());
+        pairs.push((payment.transaction_code.clone(), debit.reference.clone()));
+    }
+    pairs
+}
+
+/// Link unmatched PayPal payments to PayPal direct debits in
+/// `account_transactions`. Debits are recognized by PayPal's creditor id or
+/// name, or by one of `mandate_ids` when given.
+pub async fn link_payments(
+    conn: &DbConn,
+    window_days: u64,
+    mandate_ids: &[String],
+) -> Result<usize, DbErr> {
+    let rows = paypal_transactions::Entity::find().all(conn).await?;
+    let payments: Vec<PaypalPayment> = merchant_payments(&rows)
+        .into_iter()
+        .filter(|p| {
+            rows.iter()
+                .any(|r| r.transaction_code == p.transaction_code && r.matched_reference.is_none())
+        })
+        .collect();
+    let (Some(first), Some(last)) = (
+        payments.iter().map(|p| p.date).min(),
+        payments.iter().map(|p| p.date).max(),
+    ) else {
+        return Ok(0);
+    };
+    let linked: HashSet<&str> = rows
+        .iter()
+        .filter_map(|r| r.matched_reference.as_deref())
+        .collect();
+
+    let paypal_debit = if mandate_ids.is_empty() {
+        Condition::any()
+            .add(account_transactions::Column::CreditorId.eq(PAYPAL_CREDITOR_ID))
+            .add(account_transactions::Column::Creditor.contains("PayPal"))
+    } else {
+        Condition::all().add(account_transactions::Column::CreditorMandateId.is_in(mandate_ids))
+    };
+    let debits: Vec<BankDebit> = account_transactions::Entity::find()
+        .filter(paypal_debit)
+        .filter(account_transactions::Column::Amount.lt(0.0))
+        .filter(account_transactions::Column::BookingDate.gte(first))
+        .filter(account_transactions::Column::BookingDate.lte(last + chrono::Days::new(window_days)))
+        .all(conn)
+        .await?
+        .into_iter()
+        .filter(|t| !linked.contains(t.reference.as_str()))
+        .map(|t| BankDebit {
+            reference: t.reference,
+            booking_date: t.booking_date,
+            amount: t.amount,
+            remittance_info: t.remittance_info,
+        })
+        .collect();
+
+    let pairs = match_payments(&payments, &debits, window_days);
+    for (code, reference) in &pairs {
+        let row = rows
+            .iter()
+            .find(|r| &r.transaction_code == code)
+            .expect("matched payment comes from rows");
+        let mut active: paypal_transactions::ActiveModel = row.clone().into();
+        active.matched_reference = Set(Some(reference.clone()));
+        active.update(conn).await?;
+        debug!(%code, %reference, "[paypal] linked payment");
+    }
+    info!(
+        payments = payments.len(),
+        debits = debits.len(),
+        linked = pairs.len(),
+        "[paypal] matching done"
+    );
+    Ok(pairs.len())
+}
+
+/// Merchant behind each linked bank debit, keyed by `account_transactions.reference`.
+/// Consumers should prefer it over the "PayPal Europe" counterparty.
+pub async fn merchants_by_reference(conn: &DbConn) -> Result<HashMap<String, String>, DbErr> {
+    Ok(paypal_transactions::Entity::find()
+        .filter(paypal_transactions::Column::MatchedReference.is_not_null())
+        .all(conn)
+        .await?
+        .into_iter()
+        .filter_map(|r| r.matched_reference.map(|reference| (reference, r.name)))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::import::paypal::{match_payments, parse_activity_report, BankDebit, PaypalPayment};
+    use chrono::NaiveDate;
+
+    fn date(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_german_report() {
+        let csv = "\u{feff}\"Datum\",\"Uhrzeit\",\"Zeitzone\",\"Name\",\"Typ\",\"Status\",\"Währung\",\"Brutto\",\"Gebühr\",\"Netto\",\"Absender E-Mail-Adresse\",\"Empfänger E-Mail-Adresse\",\"Transaktionscode\",\"Artikelbezeichnung\",\"Zugehöriger Transaktionscode\"\n\
+            \"03.03.2025\",\"10:00:00\",\"CET\",\"Spotify AB\",\"Händler-Zahlung\",\"Abgeschlossen\",\"EUR\",\"-10,99\",\"0,00\",\"-10,99\",\"me@example.org\",\"billing@spotify.com\",\"1AB23456CD789012E\",\"Premium\",\"\"\n\
+            \"03.03.2025\",\"10:00:00\",\"CET\",\"\",\"Bankgutschrift auf PayPal-Konto\",\"Abgeschlossen\",\"EUR\",\"10,99\",\"0,00\",\"10,99\",\"\",\"me@example.org\",\"9ZY87654XW321098V\",\"\",\"1AB23456CD789012E\"\n";
+        let activity = parse_activity_report(csv.as_bytes()).unwrap();
+        assert!(activity.errors.is_empty(), "{:?}", activity.errors);
+        assert_eq!(activity.rows.len(), 2);
+        assert_eq!(activity.rows[0].name, "Spotify AB");
+        assert_eq!(activity.rows[0].gross, -10.99);
+        assert_eq!(activity.rows[1].related_transaction_code, "1AB23456CD789012E");
+    }
+
+    #[test]
+    fn test_match_prefers_named_merchant() {
+        let payments = vec![
+            PaypalPayment {
+                transaction_code: "A".into(),
+                date: date(1),
+                merchant: "Spotify AB".into(),
+                amount: -10.99,
+            },
+            PaypalPayment {
+                transaction_code: "B".into(),
+                date: date(1),
+                merchant: "Netflix".into(),
+                amount: -10.99,
+            },
+        ];
+        let debits = vec![
+            BankDebit {
+                reference: "r1".into(),
+                booking_date: date(2),
+                amount: -10.99,
+                remittance_info: "1041 PP.1234.PP . Netflix, Ihr Einkauf bei Netflix".into(),
+            },
+            BankDebit {
+                reference: "r2".into(),
+                booking_date: date(3),
+                amount: -10.99,
+                remittance_info: "1042 PP.1234.PP . Spotify AB, Ihr Einkauf bei Spotify AB".into(),
+            },
+            BankDebit {
+                reference: "late".into(),
+                booking_date: date(20),
+                amount: -10.99,
+                remittance_info: String::new(),
+            },
+        ];
+        let mut pairs = match_payments(&payments, &debits, 5);
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            vec![("A".into(), "r2".into()), ("B".into(), "r1".into())]
+        );
+    }
+}
@@ -1,4 +1,18 @@
+pub mod attachment;
+pub mod backup;
+pub mod balance;
+pub mod categorization;
+pub mod counterparty;
 pub mod db;
+pub mod export;
+pub mod filter;
 pub mod graphql;
+pub mod import;
 pub mod institute;
-mod service;
+pub mod quality;
+pub mod report;
+pub mod remittance;
+pub mod search;
+pub mod service;
+pub mod transfer;
+pub mod views;
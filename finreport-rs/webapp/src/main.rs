@@ -2,7 +2,7 @@ use actix_cors::Cors;
 use actix_files as fs;
 use actix_files::NamedFile;
 use actix_web::web;
-use actix_web::{get, Error, HttpResponse, Responder};
+use actix_web::{get, Error, HttpRequest, HttpResponse, Responder};
 use actix_web::{App, HttpServer};
 use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
 use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
@@ -10,10 +10,13 @@ use dotenv::dotenv;
 use sea_orm::{Database, DatabaseConnection};
 use secrecy::ExposeSecret;
 use std::sync::Arc;
-use utils::settings::Settings;
+use utils::settings::{AttachmentSettings, Settings};
 use webapp::graphql::{create_schema, AppSchema};
 use migration::{Migrator, MigratorTrait};
+use webapp::attachment::{self, Attachments};
 use webapp::db::seaql;
+use webapp::export;
+use webapp::views::Owner;
 
 #[get("/")]
 async fn root() -> Result<NamedFile, Error> {
@@ -45,8 +48,13 @@ async fn test_chart() -> impl Responder {
     }
 }
 
-async fn graphql_handler(schema: web::Data<AppSchema>, req: GraphQLRequest) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+async fn graphql_handler(
+    schema: web::Data<AppSchema>,
+    http: HttpRequest,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    let owner = Owner::from_headers(http.headers());
+    schema.execute(req.into_inner().data(owner)).await.into()
 }
 
 async fn playground() -> HttpResponse {
@@ -78,13 +86,21 @@ async fn main() -> std::io::Result<()> {
         .build()
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
 
+    let attachment_settings = config
+        .clone()
+        .try_deserialize::<AttachmentSettings>()
+        .expect("Could not load attachment settings");
+    let attachments = web::Data::new(
+        Attachments::from_settings(&attachment_settings)
+            .map_err(std::io::Error::other)?,
+    );
     let app_settings = Arc::new(
         config
             .try_deserialize::<Settings>()
             .expect("Could not load application settings"),
     );
 
-    let conn = seaql::init_db(app_settings.database_url.expose_secret()).await;
+    let conn = seaql::init_db(app_settings.database_url.expose_secret()).await?;
     // let app_settings_clone = Arc::clone(&app_settings);
 
     // // refresh session every minute
@@ -100,21 +116,28 @@ async fn main() -> std::io::Result<()> {
     //     }
     // });
 
-    let schema = create_schema();
+    let conn = web::Data::new(conn);
+    let schema = create_schema(conn.clone().into_inner());
     HttpServer::new(move || {
         App::new()
             .wrap(
                 Cors::default()
                     .allow_any_origin()
-                    .allowed_methods(vec!["GET", "POST"])
+                    .allowed_methods(vec!["GET", "POST", "DELETE"])
                     .allow_any_header(),
             )
             .app_data(web::Data::new(schema.clone()))
+            .app_data(conn.clone())
+            .app_data(attachments.clone())
             .route("/graphql", web::post().to(graphql_handler))
             .route("/playground", web::get().to(playground))
             .service(root)
             .service(data)
             .service(test_chart)
+            .service(export::http::transactions)
+            .service(attachment::http::upload)
+            .service(attachment::http::download)
+            .service(attachment::http::remove)
             .service(fs::Files::new("/assets", ".").show_files_listing())
     })
     .bind(("0.0.0.0", 8080))?
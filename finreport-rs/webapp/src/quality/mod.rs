@@ -0,0 +1,229 @@
+//! Data-quality checks over the stored bookings. The same payment can arrive
+//! through the API and a file import under different references, and rows
+//! that failed to import leave holes; both show up here as findings for
+//! review instead of being fixed silently.
+
+use crate::export;
+use crate::service::{FindingInput, FindingKind, Mutation, Query, ServiceError, TransactionFilter};
+use chrono::{Days, NaiveDate};
+use entity::entities::{account_balance, account_transactions};
+use sea_orm::DbConn;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Balance changes below a cent are rounding, not missing bookings.
+const TOLERANCE: f64 = 0.005;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DetectionSettings {
+    /// Bookings up to this many days apart can be duplicates.
+    pub window_days: u64,
+    /// Minimum share of common words in payee and remittance info, 0 to 1.
+    pub min_similarity: f64,
+}
+
+impl Default for DetectionSettings {
+    fn default() -> Self {
+        DetectionSettings {
+            window_days: 3,
+            min_similarity: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DetectionSummary {
+    pub duplicates: usize,
+    pub gaps: usize,
+    /// Findings not seen in an earlier run.
+    pub new: usize,
+}
+
+/// Pairs of bookings on the same account with the same amount, at most
+/// `window_days` apart and with similar texts.
+pub fn find_duplicates(
+    transactions: &[account_transactions::Model],
+    settings: &DetectionSettings,
+) -> Vec<FindingInput> {
+    let mut groups: HashMap<(&str, i64), Vec<&account_transactions::Model>> = HashMap::new();
+    for t in transactions {
+        let cents = (t.amount * 100.0).round() as i64;
+        groups.entry((t.account_id.as_str(), cents)).or_default().push(t);
+    }
+
+    let mut findings = Vec::new();
+    for group in groups.values_mut() {
+        group.sort_by_key(|t| (t.booking_date, t.id));
+        for (i, a) in group.iter().enumerate() {
+            let window_end = a.booking_date + Days::new(settings.window_days);
+            for b in group[i + 1..].iter().take_while(|b| b.booking_date <= window_end) {
+                let similarity = similarity(&text(a), &text(b));
+                if similarity >= settings.min_similarity {
+                    findings.push(FindingInput {
+                        kind: FindingKind::Duplicate,
+                        account_id: a.account_id.clone(),
+                        reference: Some(a.reference.clone()),
+                        other_reference: Some(b.reference.clone()),
+                        date_from: a.booking_date,
+                        date_to: b.booking_date,
+                        amount: Some(a.amount),
+                        similarity: Some(similarity),
+                    });
+                }
+            }
+        }
+    }
+    findings.sort_by(|a, b| (a.date_from, &a.reference).cmp(&(b.date_from, &b.reference)));
+    findings
+}
+
+/// Ranges between two balance snapshots of an account whose booked
+/// transactions do not add up to the balance change. The range is narrowed
+/// to the longest stretch without any booking, where the missing data most
+/// likely is.
+pub fn find_gaps(
+    transactions: &[account_transactions::Model],
+    snapshots: &[account_balance::Model],
+) -> Vec<FindingInput> {
+    let mut snapshots: Vec<&account_balance::Model> = snapshots.iter().collect();
+    snapshots.sort_by_key(|s| s.date);
+    let mut booked: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+    for t in transactions.iter().filter(|t| t.booking_status == "BOOKED") {
+        *booked.entry(t.booking_date).or_default() += t.amount;
+    }
+
+    let mut findings = Vec::new();
+    for pair in snapshots.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        let Some(first_day) = start.date.checked_add_days(Days::new(1)) else {
+            continue;
+        };
+        if first_day > end.date {
+            continue;
+        }
+        let explained: f64 = booked.range(first_day..=end.date).map(|(_, amount)| amount).sum();
+        let unexplained = end.amount - start.amount - explained;
+        if unexplained.abs() <= TOLERANCE {
+            continue;
+        }
+        let (date_from, date_to) =
+            longest_quiet_stretch(&booked, first_day, end.date).unwrap_or((first_day, end.date));
+        findings.push(FindingInput {
+            kind: FindingKind::Gap,
+            account_id: end.account_id.clone(),
+            reference: None,
+            other_reference: None,
+            date_from,
+            date_to,
+            amount: Some((unexplained * 100.0).round() / 100.0),
+            similarity: None,
+        });
+    }
+    findings
+}
+
+/// Run both checks for all accounts and store the findings.
+pub async fn detect(
+    conn: &DbConn,
+    settings: &DetectionSettings,
+) -> Result<DetectionSummary, ServiceError> {
+    let mut findings = Vec::new();
+    let mut summary = DetectionSummary::default();
+    for account in Query::accounts(conn).await? {
+        let filter = TransactionFilter {
+            accounts: vec![account.account_id.clone()],
+            ..Default::default()
+        };
+        let transactions = Query::transactions(conn, &filter).await?;
+        let snapshots = Query::balances(conn, &filter).await?;
+        let duplicates = find_duplicates(&transactions, settings);
+        let gaps = find_gaps(&transactions, &snapshots);
+        summary.duplicates += duplicates.len();
+        summary.gaps += gaps.len();
+        findings.extend(duplicates);
+        findings.extend(gaps);
+    }
+    summary.new = Mutation::replace_open_findings(conn, findings).await?;
+    Ok(summary)
+}
+
+fn longest_quiet_stretch(
+    booked: &BTreeMap<NaiveDate, f64>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Option<(NaiveDate, NaiveDate)> {
+    let mut longest: Option<(NaiveDate, NaiveDate)> = None;
+    let mut current: Option<NaiveDate> = None;
+    for day in from.iter_days().take_while(|d| *d <= to) {
+        if booked.contains_key(&day) {
+            current = None;
+            continue;
+        }
+        let start = *current.get_or_insert(day);
+        if longest.is_none_or(|(s, e)| (e - s) < (day - start)) {
+            longest = Some((start, day));
+        }
+    }
+    longest
+}
+
+fn text(t: &account_transactions::Model) -> String {
+    format!("{} {}", export::payee(t), t.remittance_info)
+}
+
+/// Share of common words (Jaccard index) of two texts, ignoring case and
+/// punctuation. Two empty texts are identical.
+fn similarity(a: &str, b: &str) -> f64 {
+    let words = |s: &str| -> HashSet<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| w.len() > 1)
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+}
+
+#[cfg(test)]
+mod test {
+    use crate::quality::{find_duplicates, find_gaps, DetectionSettings};
+    use crate::service::test::{booking, day, snapshot};
+    use entity::entities::account_transactions;
+
+    #[test]
+    fn test_duplicates_across_sources() {
+        let bill = |reference: &str, d: u32, info: &str| account_transactions::Model {
+            creditor: "Stadtwerke Nord".to_string(),
+            remittance_info: info.to_string(),
+            ..booking(reference, "A1", d, -84.0)
+        };
+        let transactions = [
+            bill("api-1", 2, "Abschlag Juni Vertrag 4711"),
+            bill("csv-1", 3, "ABSCHLAG JUNI, Vertrag 4711"),
+            // Same amount, but a different payment.
+            bill("api-2", 3, "Rechnung 2025-118 Handwerker"),
+            // Same text, but outside the window.
+            bill("api-3", 9, "Abschlag Juni Vertrag 4711"),
+        ];
+        let findings = find_duplicates(&transactions, &DetectionSettings::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].reference.as_deref(), Some("api-1"));
+        assert_eq!(findings[0].other_reference.as_deref(), Some("csv-1"));
+        assert_eq!(findings[0].similarity, Some(1.0));
+    }
+
+    #[test]
+    fn test_gap_narrowed_to_quiet_stretch() {
+        let transactions = [booking("r1", "A1", 2, -10.0), booking("r2", "A1", 9, -5.0)];
+        // 100 -> 50 explains only 15; nothing was booked from the 3rd to the 8th.
+        let findings = find_gaps(&transactions, &[snapshot(1, 100.0), snapshot(10, 50.0)]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!((findings[0].date_from, findings[0].date_to), (day(3), day(8)));
+        assert_eq!(findings[0].amount, Some(-35.0));
+
+        let complete = find_gaps(&transactions, &[snapshot(1, 100.0), snapshot(10, 85.0)]);
+        assert!(complete.is_empty());
+    }
+}
@@ -0,0 +1,323 @@
+//! Structure in the remittance info. Comdirect sends it as numbered lines
+//! padded to 35 characters ("01Miete Juni ... 02EREF+..."), SEPA transfers
+//! and direct debits carry keywords like `EREF+` and `SVWZ+` from the DFÜ
+//! agreement, and card payments name the day of the payment and, abroad, the
+//! amount in the original currency. [`parse`] turns that into typed fields,
+//! so categorization and payee matching see the purpose instead of the blob.
+
+use crate::service::{Mutation, Query, ServiceError, TransactionFilter};
+use chrono::NaiveDate;
+use regex::Regex;
+use sea_orm::DbConn;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+/// Characters per line of the numbered remittance info.
+const LINE: usize = 35;
+
+/// Value of an unset SEPA field.
+const NOT_PROVIDED: &str = "NOTPROVIDED";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    EndToEndReference,
+    MandateReference,
+    CreditorId,
+    Purpose,
+    UltimateCreditor,
+    /// Known but not kept, e.g. the ultimate debtor, which is us.
+    Other,
+}
+
+/// SEPA keywords, and the labels Comdirect writes out for some of them.
+const LABELS: [(&str, Field); 14] = [
+    ("EREF+", Field::EndToEndReference),
+    ("MREF+", Field::MandateReference),
+    ("CRED+", Field::CreditorId),
+    ("SVWZ+", Field::Purpose),
+    ("ABWA+", Field::UltimateCreditor),
+    ("KREF+", Field::Other),
+    ("DEBT+", Field::Other),
+    ("ABWE+", Field::Other),
+    ("IBAN+", Field::Other),
+    ("BIC+", Field::Other),
+    ("End-to-End-Ref.:", Field::EndToEndReference),
+    ("Mandatsref.:", Field::MandateReference),
+    ("Mandatsreferenz:", Field::MandateReference),
+    ("Gläubiger-ID:", Field::CreditorId),
+];
+
+const AMOUNT: &str = r"\d{1,3}(?:\.\d{3})+,\d{1,2}|\d+,\d{1,2}|\d+\.\d{1,2}";
+
+static CARD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)karte|visa|mastercard|maestro|girocard|debitk").expect("valid regex")
+});
+/// Timestamp of the card terminal, e.g. `2025-06-01T12:34:56`.
+static TIMESTAMP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\d{4}-\d{2}-\d{2})T\d{2}:\d{2}").expect("valid regex"));
+static GERMAN_DATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(\d{2})\.(\d{2})\.(\d{4}|\d{2})\b").expect("valid regex"));
+static FX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        concat!(
+            r"(?i:original(?:betrag)?|umsatz in|fremdw(?:ä|ae)hrung(?:sbetrag)?|betrag)\s*:?\s*",
+            r"(?:(?P<c1>[A-Z]{{3}})\s*(?P<a1>{amount})|(?P<a2>{amount})\s*(?P<c2>[A-Z]{{3}}))\b",
+        ),
+        amount = AMOUNT
+    ))
+    .expect("valid regex")
+});
+static FX_RATE: LazyLock<Regex> = LazyLock::new(|| {
+    let rate = r"(?i:wechselkurs|kurs)\s*:?\s*(\d+[.,]\d+)|1\s*EUR\s*=\s*(\d+[.,]\d+)";
+    Regex::new(rate).expect("valid regex")
+});
+
+/// An amount in the currency the card payment was made in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignAmount {
+    pub amount: f64,
+    /// ISO 4217, e.g. `USD`.
+    pub currency: String,
+    /// Units of `currency` per euro, when the bank tells.
+    pub rate: Option<f64>,
+}
+
+/// The fields found in a remittance info; `None` when absent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Remittance {
+    pub end_to_end_reference: Option<String>,
+    pub mandate_reference: Option<String>,
+    pub creditor_id: Option<String>,
+    /// The `SVWZ+` text, else the text outside any keyword.
+    pub purpose: String,
+    /// Who the payment was for when the creditor collects for others, like
+    /// a payment service for a shop.
+    pub ultimate_creditor: Option<String>,
+    /// The day a card was used, often days before the booking.
+    pub card_date: Option<NaiveDate>,
+    pub foreign_amount: Option<ForeignAmount>,
+}
+
+pub fn parse(info: &str) -> Remittance {
+    let text = unnumber(info);
+    let mut remittance = Remittance::default();
+
+    let labels = find_labels(&text);
+    let mut purpose = None;
+    let mut rest = vec![&text[..labels.first().map_or(text.len(), |(start, _, _)| *start)]];
+    for (i, (_, value_start, field)) in labels.iter().enumerate() {
+        let end = labels.get(i + 1).map_or(text.len(), |(start, _, _)| *start);
+        let value = text[*value_start..end].trim();
+        let slot = match field {
+            Field::Purpose => {
+                purpose = Some(value.to_string());
+                continue;
+            }
+            Field::UltimateCreditor => {
+                remittance.ultimate_creditor = Some(value.to_string()).filter(|v| !v.is_empty());
+                continue;
+            }
+            Field::Other => continue,
+            Field::EndToEndReference => &mut remittance.end_to_end_reference,
+            Field::MandateReference => &mut remittance.mandate_reference,
+            Field::CreditorId => &mut remittance.creditor_id,
+        };
+        // References are one word; what follows belongs to the free text.
+        let (reference, tail) = value.split_once(' ').unwrap_or((value, ""));
+        if !reference.is_empty() && reference != NOT_PROVIDED {
+            *slot = Some(reference.to_string());
+        }
+        rest.push(tail);
+    }
+    let rest: Vec<&str> = rest.into_iter().map(str::trim).filter(|r| !r.is_empty()).collect();
+    remittance.purpose = purpose.unwrap_or_else(|| rest.join(" "));
+    remittance.card_date = card_date(&text);
+    remittance.foreign_amount = foreign_amount(&text);
+    remittance
+}
+
+/// Parse the remittance info of every transaction that has not been parsed
+/// yet. Returns how many were parsed.
+pub async fn backfill(conn: &DbConn) -> Result<usize, ServiceError> {
+    let parsed: HashSet<String> = Query::remittances_by_reference(conn).await?.into_keys().collect();
+    let missing: Vec<(String, Remittance)> = Query::transactions(conn, &TransactionFilter::default())
+        .await?
+        .into_iter()
+        .filter(|t| !parsed.contains(&t.reference))
+        .map(|t| (t.reference, parse(&t.remittance_info)))
+        .collect();
+    Mutation::save_remittances(conn, &missing).await?;
+    Ok(missing.len())
+}
+
+/// The text of numbered lines, joined. Lines are padded to [`LINE`]
+/// characters, so the next number usually follows right after; a full line
+/// is continued without a space, as SEPA texts wrap in mid-word. Text that
+/// does not start with `01` is returned trimmed.
+fn unnumber(info: &str) -> String {
+    if !info.starts_with("01") {
+        return info.trim().to_string();
+    }
+    let chars: Vec<char> = info.chars().collect();
+    let mut lines: Vec<String> = Vec::new();
+    let (mut start, mut number) = (2, 2);
+    loop {
+        let marker: Vec<char> = format!("{number:02}").chars().collect();
+        let at = |i: usize| chars.get(i..i + 2) == Some(&marker[..]);
+        let end = if at(start + LINE) {
+            Some(start + LINE)
+        } else {
+            // A trimmed line: the number preceded by a space, within a line.
+            (start..(start + LINE).min(chars.len())).find(|&i| at(i) && i > start && chars[i - 1] == ' ')
+        };
+        let Some(end) = end else {
+            lines.push(chars[start..].iter().collect());
+            break;
+        };
+        lines.push(chars[start..end].iter().collect());
+        start = end + 2;
+        number += 1;
+    }
+    let mut text = String::new();
+    let mut continued = false;
+    for line in &lines {
+        if !continued && !text.is_empty() {
+            text.push(' ');
+        }
+        continued = line.chars().count() == LINE && !line.ends_with(' ');
+        text.push_str(if continued { line.trim_start() } else { line.trim() });
+    }
+    text.trim().to_string()
+}
+
+/// Start of each label, start of its value and its field, in text order.
+/// Labels count only at the start of a word.
+fn find_labels(text: &str) -> Vec<(usize, usize, Field)> {
+    let mut labels: Vec<(usize, usize, Field)> = Vec::new();
+    for (label, field) in LABELS {
+        for (start, _) in text.match_indices(label) {
+            let word_start = text[..start].chars().next_back().is_none_or(|c| !c.is_alphanumeric());
+            if word_start {
+                labels.push((start, start + label.len(), field));
+            }
+        }
+    }
+    labels.sort_by_key(|(start, _, _)| *start);
+    labels
+}
+
+/// The terminal timestamp of a card payment, else the first date of a text
+/// that mentions a card.
+fn card_date(text: &str) -> Option<NaiveDate> {
+    if let Some(date) = TIMESTAMP.captures(text).and_then(|c| c[1].parse().ok()) {
+        return Some(date);
+    }
+    if !CARD.is_match(text) {
+        return None;
+    }
+    let captures = GERMAN_DATE.captures(text)?;
+    let year: i32 = captures[3].parse().ok()?;
+    let year = if captures[3].len() == 2 { 2000 + year } else { year };
+    NaiveDate::from_ymd_opt(year, captures[2].parse().ok()?, captures[1].parse().ok()?)
+}
+
+fn foreign_amount(text: &str) -> Option<ForeignAmount> {
+    let captures = FX.captures(text)?;
+    let (amount, currency) = match (captures.name("a1"), captures.name("c1")) {
+        (Some(amount), Some(currency)) => (amount, currency),
+        _ => (captures.name("a2")?, captures.name("c2")?),
+    };
+    if currency.as_str() == "EUR" {
+        return None;
+    }
+    let rate = FX_RATE
+        .captures(text)
+        .and_then(|c| c.get(1).or_else(|| c.get(2)))
+        .and_then(|rate| number(rate.as_str()));
+    Some(ForeignAmount {
+        amount: number(amount.as_str())?,
+        currency: currency.as_str().to_string(),
+        rate,
+    })
+}
+
+/// A German (`1.234,56`) or plain (`1234.56`) decimal number.
+fn number(text: &str) -> Option<f64> {
+    if text.contains(',') {
+        text.replace('.', "").replace(',', ".").parse().ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::remittance::{parse, ForeignAmount, Remittance, LINE};
+    use chrono::NaiveDate;
+
+    /// Remittance info as Comdirect sends it: numbered, padded lines.
+    fn numbered(lines: &[&str]) -> String {
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| format!("{:02}{line:<LINE$}", i + 1))
+            .collect()
+    }
+
+    #[test]
+    fn test_sepa_direct_debit() {
+        let info = numbered(&[
+            "EREF+ABC-2025-6 MREF+M-4711 CRED+DE",
+            "98ZZZ09999999999 SVWZ+Abschlag",
+            "Juni 2025 Vertrag ABWA+Stadtwerke N",
+            "ord Vertrieb GmbH",
+        ]);
+        assert_eq!(
+            parse(&info),
+            Remittance {
+                end_to_end_reference: Some("ABC-2025-6".to_string()),
+                mandate_reference: Some("M-4711".to_string()),
+                creditor_id: Some("DE98ZZZ09999999999".to_string()),
+                purpose: "Abschlag Juni 2025 Vertrag".to_string(),
+                ultimate_creditor: Some("Stadtwerke Nord Vertrieb GmbH".to_string()),
+                card_date: None,
+                foreign_amount: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_free_text_and_not_provided() {
+        let remittance = parse("Miete Juni EREF+NOTPROVIDED");
+        assert_eq!(remittance.purpose, "Miete Juni");
+        assert_eq!(remittance.end_to_end_reference, None);
+        assert_eq!(parse("").purpose, "");
+        // Keywords only count at the start of a word.
+        assert_eq!(parse("XSVWZ+abc").purpose, "XSVWZ+abc");
+    }
+
+    #[test]
+    fn test_card_payment_abroad() {
+        let info = numbered(&[
+            "VISA Debitkartenumsatz",
+            "AMAZON.COM 2025-06-01T12:34:56",
+            "Originalbetrag 12,34 USD Kurs 1,0823",
+        ]);
+        let remittance = parse(&info);
+        assert_eq!(remittance.card_date, NaiveDate::from_ymd_opt(2025, 6, 1));
+        assert_eq!(
+            remittance.foreign_amount,
+            Some(ForeignAmount {
+                amount: 12.34,
+                currency: "USD".to_string(),
+                rate: Some(1.0823),
+            })
+        );
+        let domestic = parse("girocard REWE Markt 28.05.25 um 18:02");
+        assert_eq!(domestic.card_date, NaiveDate::from_ymd_opt(2025, 5, 28));
+        assert_eq!(domestic.foreign_amount, None);
+        assert_eq!(parse("Rechnung vom 28.05.2025").card_date, None);
+        let amount = parse("Kartenzahlung Umsatz in CHF 1.234,50 1 EUR = 0,9412 CHF").foreign_amount.unwrap();
+        assert_eq!((amount.amount, amount.rate), (1234.5, Some(0.9412)));
+    }
+}
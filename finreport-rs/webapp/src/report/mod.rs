@@ -0,0 +1,198 @@
+//! Monthly income and expense totals per category. This is the single source
+//! for the GraphQL `reports` query and the spreadsheet exports, so numbers
+//! in the UI and in the accountant's workbook always agree.
+
+use crate::export::{self, ExportData, ExportError, ExportSelection};
+use chrono::{Datelike, NaiveDate};
+use entity::entities::account_transactions;
+use sea_orm::DbConn;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Category label of transactions the categorizer has not seen yet.
+pub const UNCATEGORIZED: &str = "Uncategorized";
+
+/// Totals of one category in one month. Both sums are positive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryMonth {
+    pub year: i32,
+    pub month: u32,
+    pub category: String,
+    pub income: f64,
+    pub expenses: f64,
+}
+
+/// Category × month matrix, months in chronological order and categories
+/// sorted by name.
+#[derive(Debug, Clone, Default)]
+pub struct Pivot {
+    pub months: Vec<(i32, u32)>,
+    pub rows: Vec<(String, Vec<f64>)>,
+}
+
+/// Count and sums of a set of transactions. Both sums are positive.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Totals {
+    pub count: usize,
+    pub income: f64,
+    pub expenses: f64,
+}
+
+impl Totals {
+    pub fn of(transactions: &[account_transactions::Model]) -> Totals {
+        let mut totals = Totals::default();
+        transactions.iter().for_each(|t| totals.add(t.amount));
+        totals
+    }
+
+    pub fn add(&mut self, amount: f64) {
+        self.count += 1;
+        if amount >= 0.0 {
+            self.income += amount;
+        } else {
+            self.expenses -= amount;
+        }
+    }
+
+    pub fn net(&self) -> f64 {
+        self.income - self.expenses
+    }
+}
+
+impl Pivot {
+    pub fn month_totals(&self) -> Vec<f64> {
+        (0..self.months.len())
+            .map(|i| self.rows.iter().map(|(_, values)| values[i]).sum())
+            .collect()
+    }
+}
+
+/// First and last day of a calendar month.
+pub fn month_range(year: i32, month: u32) -> Option<(NaiveDate, NaiveDate)> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let next = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)?
+    };
+    Some((first, next.pred_opt()?))
+}
+
+/// Totals of the transactions in `selection` in one calendar month; the
+/// dates of `selection` are replaced by the month.
+pub async fn monthly(
+    conn: &DbConn,
+    selection: &ExportSelection,
+    year: i32,
+    month: u32,
+) -> Result<Vec<CategoryMonth>, ExportError> {
+    let Some((from, to)) = month_range(year, month) else {
+        return Ok(Vec::new());
+    };
+    let selection = ExportSelection {
+        from: Some(from),
+        to: Some(to),
+        ..selection.clone()
+    };
+    Ok(monthly_by_category(&export::load(conn, &selection).await?))
+}
+
+/// Totals per calendar month, without categories, from the first to the
+/// last month with a booking. Months in between without bookings are zero.
+pub fn monthly_totals(transactions: &[account_transactions::Model]) -> Vec<((i32, u32), Totals)> {
+    let mut months: BTreeMap<(i32, u32), Totals> = BTreeMap::new();
+    for t in transactions {
+        let date = t.booking_date;
+        months.entry((date.year(), date.month())).or_default().add(t.amount);
+    }
+    let (Some(&first), Some(&last)) = (months.keys().next(), months.keys().next_back()) else {
+        return Vec::new();
+    };
+    let mut series = Vec::new();
+    let mut month = first;
+    while month <= last {
+        series.push((month, months.get(&month).copied().unwrap_or_default()));
+        month = if month.1 == 12 { (month.0 + 1, 1) } else { (month.0, month.1 + 1) };
+    }
+    series
+}
+
+pub fn monthly_by_category(data: &ExportData) -> Vec<CategoryMonth> {
+    let mut totals: BTreeMap<(i32, u32, String), (f64, f64)> = BTreeMap::new();
+    for t in data.accounts.iter().flat_map(|a| &a.transactions) {
+        let date = t.transaction.booking_date;
+        // Split transactions count with each part in its own category.
+        for part in t.parts() {
+            let category = part.category.unwrap_or_else(|| UNCATEGORIZED.to_string());
+            let entry = totals
+                .entry((date.year(), date.month(), category))
+                .or_default();
+            if part.amount >= 0.0 {
+                entry.0 += part.amount;
+            } else {
+                entry.1 -= part.amount;
+            }
+        }
+    }
+    totals
+        .into_iter()
+        .map(|((year, month, category), (income, expenses))| CategoryMonth {
+            year,
+            month,
+            category,
+            income,
+            expenses,
+        })
+        .collect()
+}
+
+/// Spread `value` of each total into a category × month matrix.
+pub fn pivot(totals: &[CategoryMonth], value: impl Fn(&CategoryMonth) -> f64) -> Pivot {
+    let months: Vec<(i32, u32)> = totals
+        .iter()
+        .map(|t| (t.year, t.month))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let mut rows: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+    for total in totals {
+        let v = value(total);
+        if v == 0.0 {
+            continue;
+        }
+        let column = months
+            .binary_search(&(total.year, total.month))
+            .expect("month collected above");
+        rows.entry(&total.category)
+            .or_insert_with(|| vec![0.0; months.len()])[column] += v;
+    }
+    Pivot {
+        rows: rows
+            .into_iter()
+            .map(|(category, values)| (category.to_string(), values))
+            .collect(),
+        months,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::report::{monthly_totals, Totals};
+    use crate::service::test::booking;
+    use entity::entities::account_transactions;
+
+    #[test]
+    fn test_monthly_totals_fill_empty_months() {
+        let on = |date: &str, amount: f64| account_transactions::Model {
+            booking_date: date.parse().unwrap(),
+            ..booking(date, "A1", 1, amount)
+        };
+        let bookings = [on("2025-01-03", -20.0), on("2025-01-28", 100.0), on("2025-03-01", -5.5)];
+        let series = monthly_totals(&bookings);
+        let months: Vec<_> = series.iter().map(|(month, _)| *month).collect();
+        assert_eq!(months, [(2025, 1), (2025, 2), (2025, 3)]);
+        assert_eq!(series[0].1, Totals { count: 2, income: 100.0, expenses: 20.0 });
+        assert_eq!(series[1].1, Totals::default());
+        assert_eq!(series[2].1.net(), -5.5);
+        assert!(monthly_totals(&[]).is_empty());
+    }
+}
@@ -0,0 +1,321 @@
+//! Free-text search over the remittance info and the parties of a
+//! transaction. Postgres combines German full-text search, which finds
+//! "Erstattung" for "Erstattungen", with trigram word similarity, which
+//! forgives typos like "amazn". Queries use the web search syntax of
+//! Postgres: `"exact phrase"`, `-excluded` and `or`. SQLite has neither and
+//! falls back to LIKE on every word of the query.
+
+use crate::filter;
+use crate::service::{Query, ServiceError, TransactionFilter};
+use entity::entities::account_transactions;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DbBackend, DbConn, EntityTrait, Order, QueryFilter,
+    QueryOrder, QuerySelect,
+};
+use std::collections::HashMap;
+
+/// The searched text on Postgres. The indexes of the
+/// `m20261019_150000_transaction_search` migration are built on exactly this
+/// expression.
+pub const DOCUMENT: &str = "remittance_info || ' ' || remitter || ' ' || creditor || ' ' || deptor";
+
+const MARK_START: &str = "<mark>";
+const MARK_END: &str = "</mark>";
+/// Characters of context shown before the first match.
+const SNIPPET_LEAD: usize = 20;
+const SNIPPET_CHARS: usize = 80;
+
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub transaction: account_transactions::Model,
+    /// Higher is better; only comparable within one search.
+    pub rank: f64,
+    /// Excerpt of the searched text, HTML-escaped, with the matches wrapped
+    /// in `<mark>`.
+    pub snippet: String,
+}
+
+/// Transactions in `filter` matching `text`, best first. An empty query
+/// finds nothing.
+pub async fn search(
+    conn: &DbConn,
+    text: &str,
+    filter: &TransactionFilter,
+    limit: u64,
+) -> Result<Vec<SearchHit>, ServiceError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+    match conn.get_database_backend() {
+        DbBackend::Postgres => search_postgres(conn, text, filter, limit).await,
+        _ => search_like(conn, text, filter, limit).await,
+    }
+}
+
+async fn search_postgres(
+    conn: &DbConn,
+    text: &str,
+    filter: &TransactionFilter,
+    limit: u64,
+) -> Result<Vec<SearchHit>, ServiceError> {
+    let tsquery = "websearch_to_tsquery('german', $1)";
+    let mut matches = format!("to_tsvector('german', {DOCUMENT}) @@ {tsquery}");
+    // A fuzzy match would ignore phrases and excluded words.
+    let operators = text.contains('"') || text.split_whitespace().any(|w| w.starts_with('-'));
+    if !operators {
+        matches.push_str(&format!(" OR $1 OPERATOR(extensions.<%) ({DOCUMENT})"));
+    }
+    let ranked: Vec<(String, f64, String)> = account_transactions::Entity::find()
+        .select_only()
+        .column(account_transactions::Column::Reference)
+        .expr_as(
+            Expr::cust_with_values(
+                format!(
+                    "(ts_rank(to_tsvector('german', {DOCUMENT}), {tsquery}) \
+                     + extensions.word_similarity($1, {DOCUMENT}))::float8"
+                ),
+                [text],
+            ),
+            "rank",
+        )
+        .expr_as(
+            Expr::cust_with_values(
+                format!(
+                    "ts_headline('german', {}, {tsquery}, \
+                     'StartSel={MARK_START}, StopSel={MARK_END}, MinWords=5, MaxWords=15')",
+                    escaped(DOCUMENT)
+                ),
+                [text],
+            ),
+            "snippet",
+        )
+        .filter(filter.condition())
+        .filter(Expr::cust_with_values(format!("({matches})"), [text]))
+        .order_by(Expr::cust("rank"), Order::Desc)
+        .order_by_desc(account_transactions::Column::BookingDate)
+        .limit(limit)
+        .into_tuple()
+        .all(conn)
+        .await?;
+
+    let references: Vec<String> = ranked.iter().map(|(reference, _, _)| reference.clone()).collect();
+    let mut transactions: HashMap<String, account_transactions::Model> =
+        Query::transactions_by_reference(conn, &references)
+            .await?
+            .into_iter()
+            .map(|t| (t.reference.clone(), t))
+            .collect();
+    Ok(ranked
+        .into_iter()
+        .filter_map(|(reference, rank, snippet)| {
+            Some(SearchHit {
+                transaction: transactions.remove(&reference)?,
+                rank,
+                snippet,
+            })
+        })
+        .collect())
+}
+
+/// Every word of the query must occur in one of the texts, ignoring ASCII
+/// case. Ranked by the share of the text the matches cover.
+async fn search_like(
+    conn: &DbConn,
+    text: &str,
+    filter: &TransactionFilter,
+    limit: u64,
+) -> Result<Vec<SearchHit>, ServiceError> {
+    let terms: Vec<&str> = text.split_whitespace().collect();
+    let mut condition = filter.condition();
+    for term in &terms {
+        let like = || filter::contains(term);
+        condition = condition.add(
+            Condition::any()
+                .add(account_transactions::Column::RemittanceInfo.like(like()))
+                .add(account_transactions::Column::Remitter.like(like()))
+                .add(account_transactions::Column::Creditor.like(like()))
+                .add(account_transactions::Column::Deptor.like(like())),
+        );
+    }
+    let mut hits: Vec<SearchHit> = account_transactions::Entity::find()
+        .filter(condition)
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|t| {
+            let (snippet, rank) = highlight(&document(&t), &terms);
+            SearchHit {
+                transaction: t,
+                rank,
+                snippet,
+            }
+        })
+        .collect();
+    hits.sort_by(|a, b| {
+        b.rank
+            .total_cmp(&a.rank)
+            .then(b.transaction.booking_date.cmp(&a.transaction.booking_date))
+    });
+    hits.truncate(limit as usize);
+    Ok(hits)
+}
+
+/// `expression` with the characters HTML gives a meaning replaced by their
+/// entities, so only the marks of the headline are markup. The parser of
+/// `ts_headline` keeps the entities in one piece.
+fn escaped(expression: &str) -> String {
+    format!(
+        "replace(replace(replace(replace({expression}, '&', '&amp;'), '<', '&lt;'), '>', '&gt;'), \
+         '\"', '&quot;')"
+    )
+}
+
+fn document(t: &account_transactions::Model) -> String {
+    [&t.remittance_info, &t.remitter, &t.creditor, &t.deptor]
+        .into_iter()
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.trim())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// HTML-escaped excerpt of `document` around the first match with every
+/// case-insensitive occurrence of `terms` marked, and the share of
+/// `document` the matches cover.
+fn highlight(document: &str, terms: &[&str]) -> (String, f64) {
+    let chars: Vec<char> = document.chars().collect();
+    let folded: Vec<char> = chars.iter().map(|c| fold(*c)).collect();
+
+    let mut matches: Vec<(usize, usize)> = Vec::new();
+    for term in terms {
+        let term: Vec<char> = term.chars().map(fold).collect();
+        if term.is_empty() || term.len() > folded.len() {
+            continue;
+        }
+        for start in 0..=folded.len() - term.len() {
+            if folded[start..start + term.len()] == term[..] {
+                matches.push((start, start + term.len()));
+            }
+        }
+    }
+    matches.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in matches {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    let covered: usize = merged.iter().map(|(start, end)| end - start).sum();
+    let rank = match chars.len() {
+        0 => 0.0,
+        n => covered as f64 / n as f64,
+    };
+
+    let from = merged.first().map_or(0, |(start, _)| start.saturating_sub(SNIPPET_LEAD));
+    let to = (from + SNIPPET_CHARS).min(chars.len());
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    let mut position = from;
+    for &(start, end) in merged.iter().filter(|(start, end)| *end > from && *start < to) {
+        let (start, end) = (start.max(from), end.min(to));
+        escape(&mut snippet, &chars[position..start]);
+        snippet.push_str(MARK_START);
+        escape(&mut snippet, &chars[start..end]);
+        snippet.push_str(MARK_END);
+        position = end;
+    }
+    escape(&mut snippet, &chars[position..to]);
+    if to < chars.len() {
+        snippet.push('…');
+    }
+    (snippet, rank)
+}
+
+fn escape(out: &mut String, chars: &[char]) {
+    for &c in chars {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Lower case that keeps one char per char, so positions stay aligned.
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::search::{highlight, search};
+    use crate::service::test::{account, db, transaction};
+    use crate::service::{ChangeSource, Mutation, TransactionFilter};
+
+    #[test]
+    fn test_highlight_marks_every_term() {
+        let (snippet, rank) = highlight("Erstattung AMAZON EU Bestellung 302", &["amazon", "bestellung"]);
+        assert_eq!(snippet, "Erstattung <mark>AMAZON</mark> EU <mark>Bestellung</mark> 302");
+        assert!(rank > 0.4 && rank < 0.5);
+    }
+
+    #[test]
+    fn test_highlight_cuts_long_texts() {
+        let text = format!("{} Amazon {}", "a".repeat(40), "b".repeat(100));
+        let (snippet, _) = highlight(&text, &["amazon"]);
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.contains("<mark>Amazon</mark>"));
+    }
+
+    #[tokio::test]
+    async fn test_like_search_requires_every_word() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        let mut refund = transaction("r1", "A1", 25.0);
+        refund.remittance_info = "Erstattung Bestellung 302-1".to_string();
+        refund.creditor = "AMAZON EU S.A R.L.".to_string();
+        let mut order = transaction("r2", "A1", -25.0);
+        order.remittance_info = "Bestellung 302-1".to_string();
+        order.creditor = "AMAZON EU S.A R.L.".to_string();
+        let other = transaction("r3", "A1", -12.0);
+        for t in [refund, order, other] {
+            Mutation::upsert_transaction(&db, t, ChangeSource::Import).await.unwrap();
+        }
+
+        let hits = search(&db, "amazon erstattung", &TransactionFilter::default(), 10)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].transaction.reference, "r1");
+        assert!(hits[0].snippet.contains("<mark>Erstattung</mark>"));
+
+        let hits = search(&db, "amazon", &TransactionFilter::default(), 10).await.unwrap();
+        // The shorter text is covered more by the match.
+        let references: Vec<_> = hits.iter().map(|h| h.transaction.reference.as_str()).collect();
+        assert_eq!(references, ["r2", "r1"]);
+        assert!(search(&db, "  ", &TransactionFilter::default(), 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_snippets_escape_markup() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        let mut t = transaction("r1", "A1", -5.0);
+        t.remittance_info = "<script>alert(\"Miete\")</script> & Co".to_string();
+        Mutation::upsert_transaction(&db, t, ChangeSource::Import).await.unwrap();
+
+        let hits = search(&db, "miete", &TransactionFilter::default(), 10).await.unwrap();
+        assert_eq!(
+            hits[0].snippet,
+            "&lt;script&gt;alert(&quot;<mark>Miete</mark>&quot;)&lt;/script&gt; &amp; Co REWE Markt GmbH"
+        );
+    }
+}
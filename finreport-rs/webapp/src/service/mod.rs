@@ -1 +1,585 @@
-pub mod mutation;
\ No newline at end of file
+//! Service layer over the SeaORM entities: `Query` reads, `Mutation` writes.
+//! Importers, GraphQL resolvers and CLI tools go through here, so the upsert
+//! rules for accounts, balances, transactions and categories live in one
+//! place.
+
+mod mutation;
+mod query;
+
+pub use mutation::Mutation;
+pub use query::Query;
+
+#[cfg(test)]
+pub(crate) use mutation::test;
+
+use crate::filter::{self, FilterError, FilterQuery};
+use categorizer::categorize::CategorizeAiResponse;
+use categorizer::rules::Criteria;
+use chrono::NaiveDate;
+use comdirect_rs::comdirect::balance_model::AccountBalance;
+use comdirect_rs::comdirect::transaction::Transaction;
+use entity::entities::{
+    account_transactions, categories, import_run_accounts, import_runs, saved_views, transaction_splits,
+};
+use sea_orm::{ColumnTrait, Condition, DbErr};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum ServiceError {
+    Db(DbErr),
+    AccountNotFound(String),
+    /// A category pair that is not in the taxonomy.
+    UnknownCategory {
+        category: String,
+        subcategory: String,
+    },
+    InvalidTransaction {
+        reference: String,
+        message: String,
+    },
+    FindingNotFound(i32),
+    /// The action does not apply to the finding, e.g. merging a gap.
+    InvalidFindingAction {
+        id: i32,
+        message: String,
+    },
+    /// A saved view, by id or name, that does not exist or belongs to
+    /// someone else.
+    ViewNotFound(String),
+    InvalidView(String),
+    InvalidFilter(FilterError),
+    /// A tag that is empty or contains whitespace.
+    InvalidTag(String),
+    CounterpartyNotFound(i32),
+    /// A name that is empty or taken, or a rule that does not parse or
+    /// belongs to another counterparty.
+    InvalidCounterparty(String),
+    AttachmentNotFound(i32),
+    CategorizationRuleNotFound(i32),
+    /// A rule without a name or criteria, or with criteria that cannot match.
+    InvalidCategorizationRule(String),
+    /// A mandate conflict that does not exist, or is resolved already when
+    /// resolving it.
+    MandateConflictNotFound(i32),
+}
+
+impl Display for ServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServiceError::Db(e) => write!(f, "database error: {e}"),
+            ServiceError::AccountNotFound(account_id) => {
+                write!(f, "account {account_id} does not exist")
+            }
+            ServiceError::UnknownCategory {
+                category,
+                subcategory,
+            } => write!(f, "unknown category {category}/{subcategory}"),
+            ServiceError::InvalidTransaction { reference, message } => {
+                write!(f, "invalid transaction {reference}: {message}")
+            }
+            ServiceError::FindingNotFound(id) => write!(f, "finding {id} does not exist"),
+            ServiceError::InvalidFindingAction { id, message } => {
+                write!(f, "cannot resolve finding {id}: {message}")
+            }
+            ServiceError::ViewNotFound(view) => write!(f, "view {view} does not exist"),
+            ServiceError::InvalidView(message) => write!(f, "invalid view: {message}"),
+            ServiceError::InvalidFilter(e) => write!(f, "invalid filter: {e}"),
+            ServiceError::InvalidTag(tag) => write!(f, "invalid tag `{tag}`"),
+            ServiceError::CounterpartyNotFound(id) => write!(f, "counterparty {id} does not exist"),
+            ServiceError::InvalidCounterparty(message) => write!(f, "invalid counterparty: {message}"),
+            ServiceError::AttachmentNotFound(id) => write!(f, "attachment {id} does not exist"),
+            ServiceError::CategorizationRuleNotFound(id) => {
+                write!(f, "categorization rule {id} does not exist")
+            }
+            ServiceError::InvalidCategorizationRule(message) => {
+                write!(f, "invalid categorization rule: {message}")
+            }
+            ServiceError::MandateConflictNotFound(id) => {
+                write!(f, "mandate conflict {id} does not exist or is resolved")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+impl From<DbErr> for ServiceError {
+    fn from(value: DbErr) -> Self {
+        ServiceError::Db(value)
+    }
+}
+
+impl From<FilterError> for ServiceError {
+    fn from(value: FilterError) -> Self {
+        ServiceError::InvalidFilter(value)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AccountInput {
+    pub account_id: String,
+    pub display_id: String,
+    pub account_type: String,
+    pub iban: String,
+    pub bic: String,
+    pub institute: String,
+}
+
+impl AccountInput {
+    pub fn from_comdirect(account: &AccountBalance) -> Self {
+        AccountInput {
+            account_id: account.account.account_id.clone(),
+            display_id: account.account.display_id.clone(),
+            account_type: account.account.account_type.text.clone(),
+            iban: account.account.iban.clone(),
+            bic: account.account.bic.clone(),
+            institute: "COMDIRECT".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BalanceInput {
+    pub account_id: String,
+    pub date: NaiveDate,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionInput {
+    pub reference: String,
+    pub account_id: String,
+    pub booking_status: String,
+    pub booking_date: NaiveDate,
+    pub amount: f64,
+    pub remitter: String,
+    pub deptor: String,
+    pub creditor: String,
+    pub creditor_id: String,
+    pub creditor_mandate_id: String,
+    pub remittance_info: String,
+    pub transaction_type: String,
+    /// IBAN of the other party; empty when the source does not tell.
+    pub counterparty_iban: String,
+}
+
+impl TransactionInput {
+    pub fn from_comdirect(account_id: &str, transaction: &Transaction) -> Result<Self, ServiceError> {
+        let invalid = |message: String| ServiceError::InvalidTransaction {
+            reference: transaction.reference.clone(),
+            message,
+        };
+        Ok(TransactionInput {
+            reference: transaction.reference.clone(),
+            account_id: account_id.to_string(),
+            booking_status: transaction.booking_status.clone(),
+            booking_date: transaction.booking_date.parse().map_err(|e| {
+                invalid(format!("booking date `{}`: {e}", transaction.booking_date))
+            })?,
+            amount: transaction
+                .amount
+                .value
+                .parse()
+                .map_err(|e| invalid(format!("amount `{}`: {e}", transaction.amount.value)))?,
+            remitter: transaction
+                .remitter
+                .as_ref()
+                .map(|r| r.holder_name.clone())
+                .unwrap_or_default(),
+            deptor: transaction.deptor.clone().unwrap_or_default(),
+            creditor: transaction
+                .creditor
+                .as_ref()
+                .map(|c| c.holder_name.clone())
+                .unwrap_or_default(),
+            creditor_id: transaction.direct_debit_creditor_id.clone().unwrap_or_default(),
+            creditor_mandate_id: transaction.direct_debit_mandate_id.clone().unwrap_or_default(),
+            remittance_info: transaction.remittance_info.clone(),
+            transaction_type: transaction.transaction_type.text.clone(),
+            // The API only reports the IBAN of the creditor.
+            counterparty_iban: transaction
+                .creditor
+                .as_ref()
+                .map(|c| c.iban.clone())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// Which transactions to read. Empty `accounts` means all accounts; the date
+/// range is inclusive on both ends. `query` only applies to transactions,
+/// not to balances.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    pub accounts: Vec<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub query: Option<FilterQuery>,
+    /// Only transactions carrying all of these tags, on the transaction or
+    /// on one of its split parts.
+    pub tags: Vec<String>,
+    /// Leave out both legs of linked internal transfers, as income and
+    /// expense reports do.
+    pub without_transfers: bool,
+}
+
+impl TransactionFilter {
+    /// The filter as a condition on `account_transactions`.
+    pub fn condition(&self) -> Condition {
+        let mut condition = Condition::all();
+        if !self.accounts.is_empty() {
+            condition = condition.add(account_transactions::Column::AccountId.is_in(&self.accounts));
+        }
+        if let Some(from) = self.from {
+            condition = condition.add(account_transactions::Column::BookingDate.gte(from));
+        }
+        if let Some(to) = self.to {
+            condition = condition.add(account_transactions::Column::BookingDate.lte(to));
+        }
+        if let Some(query) = &self.query {
+            condition = condition.add(query.condition());
+        }
+        for tag in &self.tags {
+            condition = condition.add(filter::has_tag(tag));
+        }
+        if self.without_transfers {
+            condition = condition.add(filter::is_transfer().not());
+        }
+        condition
+    }
+
+    /// Narrow the filter to the transactions of a saved view.
+    pub fn with_view(mut self, view: &saved_views::Model) -> Result<Self, ServiceError> {
+        let query: FilterQuery = view.filter.parse()?;
+        self.query = Some(match self.query.take() {
+            Some(own) => own.and(query),
+            None => query,
+        });
+        Ok(self)
+    }
+}
+
+/// Tags are stored lower case and must not contain whitespace, so that the
+/// filter language can match them as `tag:name`.
+pub fn normalize_tag(tag: &str) -> Result<String, ServiceError> {
+    let tag = tag.trim();
+    if tag.is_empty() || tag.contains(char::is_whitespace) {
+        return Err(ServiceError::InvalidTag(tag.to_string()));
+    }
+    Ok(tag.to_lowercase())
+}
+
+/// Name and filter expression of a saved view.
+#[derive(Debug, Clone)]
+pub struct SavedViewInput {
+    pub owner: String,
+    pub name: String,
+    pub filter: String,
+}
+
+/// A categorization rule; see [`categorizer::rules`]. The category is
+/// given by name.
+#[derive(Debug, Clone)]
+pub struct CategorizationRuleInput {
+    pub name: String,
+    pub priority: i32,
+    pub category: String,
+    pub subcategory: String,
+    pub criteria: Criteria,
+}
+
+/// A stored file to attach to a transaction; see [`crate::attachment`].
+#[derive(Debug, Clone)]
+pub struct AttachmentInput {
+    pub reference: String,
+    /// Hex SHA-256 of the content, its key in the blob store.
+    pub sha256: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+}
+
+/// Who changed a transaction, as recorded in `transaction_history.source`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeSource {
+    /// A file import, which has no `import_runs` row.
+    Import,
+    ImportRun(i32),
+    User,
+    Categorizer,
+    /// Internal transfers found by [`crate::transfer`].
+    TransferMatcher,
+    /// The categorization rule with this id.
+    Rule(i32),
+    /// A direct debit categorized by its mandate, see [`crate::categorization`].
+    Mandate,
+}
+
+impl Display for ChangeSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeSource::Import => f.write_str("import"),
+            ChangeSource::ImportRun(id) => write!(f, "import-run:{id}"),
+            ChangeSource::User => f.write_str("user"),
+            ChangeSource::Categorizer => f.write_str("categorizer"),
+            ChangeSource::TransferMatcher => f.write_str("transfer-matcher"),
+            ChangeSource::Rule(id) => write!(f, "rule:{id}"),
+            ChangeSource::Mandate => f.write_str("mandate"),
+        }
+    }
+}
+
+/// A category for one transaction, by name. Names match case-insensitively.
+#[derive(Debug, Clone)]
+pub struct CategoryAssignment {
+    pub reference: String,
+    pub category: String,
+    pub subcategory: String,
+    pub reasoning: Option<String>,
+    pub confidence: Option<f64>,
+    pub source: ChangeSource,
+}
+
+impl From<CategorizeAiResponse> for CategoryAssignment {
+    fn from(response: CategorizeAiResponse) -> Self {
+        CategoryAssignment {
+            reference: response.reference,
+            category: response.category,
+            subcategory: response.subcategory,
+            reasoning: Some(response.reasoning),
+            confidence: Some(f64::from(response.confidence)),
+            source: ChangeSource::Categorizer,
+        }
+    }
+}
+
+/// One part of a split transaction. Amounts carry the sign of the booking;
+/// the parts of a transaction sum to its amount.
+#[derive(Debug, Clone)]
+pub struct SplitInput {
+    pub amount: f64,
+    pub category: String,
+    pub subcategory: String,
+    pub note: Option<String>,
+    pub tag: Option<String>,
+}
+
+/// A stored part with its category.
+pub type Split = (transaction_splits::Model, categories::Model);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    Inserted,
+    Updated,
+    Unchanged,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct UpsertSummary {
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+}
+
+impl UpsertSummary {
+    pub fn written(&self) -> usize {
+        self.inserted + self.updated + self.unchanged
+    }
+
+    fn count(&mut self, outcome: UpsertOutcome) {
+        match outcome {
+            UpsertOutcome::Inserted => self.inserted += 1,
+            UpsertOutcome::Updated => self.updated += 1,
+            UpsertOutcome::Unchanged => self.unchanged += 1,
+        }
+    }
+}
+
+/// What an `import_runs` row records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportRunKind {
+    /// Acquiring a bank session; fails when e.g. the TAN is not approved.
+    Bootstrap,
+    Import,
+}
+
+impl Display for ImportRunKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ImportRunKind::Bootstrap => "bootstrap",
+            ImportRunKind::Import => "import",
+        })
+    }
+}
+
+/// An import run with its per-account counts.
+#[derive(Debug, Clone)]
+pub struct ImportRun {
+    pub run: import_runs::Model,
+    pub accounts: Vec<import_run_accounts::Model>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingKind {
+    /// Two bookings that are probably the same payment.
+    Duplicate,
+    /// A date range whose bookings do not explain the balance change.
+    Gap,
+}
+
+impl Display for FindingKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FindingKind::Duplicate => "duplicate",
+            FindingKind::Gap => "gap",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingStatus {
+    Open,
+    /// The duplicate was deleted.
+    Merged,
+    /// Dismissed without a decision.
+    Ignored,
+    /// Reviewed and found to be genuine, e.g. two identical payments.
+    Kept,
+}
+
+impl Display for FindingStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FindingStatus::Open => "open",
+            FindingStatus::Merged => "merged",
+            FindingStatus::Ignored => "ignored",
+            FindingStatus::Kept => "kept",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferStatus {
+    Linked,
+    /// Unlinked by a user; the legs are not matched again.
+    Unlinked,
+}
+
+impl Display for TransferStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TransferStatus::Linked => "linked",
+            TransferStatus::Unlinked => "unlinked",
+        })
+    }
+}
+
+/// A debit put into another category than its mandate maps to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MandateConflictStatus {
+    Open,
+    /// The mandate was mapped to the category of the debit.
+    Remapped,
+    /// The mapping stays; the debit is an exception.
+    Kept,
+}
+
+impl Display for MandateConflictStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MandateConflictStatus::Open => "open",
+            MandateConflictStatus::Remapped => "remapped",
+            MandateConflictStatus::Kept => "kept",
+        })
+    }
+}
+
+/// What a counterparty rule matches; see [`crate::counterparty`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterpartyRuleKind {
+    /// A payee name, compared after normalization.
+    Alias,
+    /// A case-insensitive regular expression over the payee name.
+    Regex,
+    /// The IBAN of the other party.
+    Iban,
+    /// The SEPA creditor ID of a direct debit.
+    CreditorId,
+}
+
+impl CounterpartyRuleKind {
+    pub const ALL: [CounterpartyRuleKind; 4] = [
+        CounterpartyRuleKind::Alias,
+        CounterpartyRuleKind::Regex,
+        CounterpartyRuleKind::Iban,
+        CounterpartyRuleKind::CreditorId,
+    ];
+}
+
+impl Display for CounterpartyRuleKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CounterpartyRuleKind::Alias => "alias",
+            CounterpartyRuleKind::Regex => "regex",
+            CounterpartyRuleKind::Iban => "iban",
+            CounterpartyRuleKind::CreditorId => "creditor_id",
+        })
+    }
+}
+
+/// The two legs of an internal transfer, by reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferPair {
+    /// The debit on the sending account.
+    pub outgoing: String,
+    /// The credit on the receiving account.
+    pub incoming: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingAction {
+    /// Delete one transaction of a duplicate pair, keeping its category on
+    /// the survivor.
+    Merge,
+    Ignore,
+    Keep,
+}
+
+/// A finding as detected; see [`crate::quality`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FindingInput {
+    pub kind: FindingKind,
+    pub account_id: String,
+    pub reference: Option<String>,
+    pub other_reference: Option<String>,
+    pub date_from: NaiveDate,
+    pub date_to: NaiveDate,
+    /// Amount of a duplicate, or the balance change a gap leaves unexplained.
+    pub amount: Option<f64>,
+    pub similarity: Option<f64>,
+}
+
+impl FindingInput {
+    /// Identity across detection runs. Duplicates are the same pair in any
+    /// order; gaps the same range with the same unexplained amount.
+    pub fn fingerprint(&self) -> String {
+        match self.kind {
+            FindingKind::Duplicate => {
+                let mut pair = [
+                    self.reference.as_deref().unwrap_or_default(),
+                    self.other_reference.as_deref().unwrap_or_default(),
+                ];
+                pair.sort();
+                format!("duplicate:{}:{}", pair[0], pair[1])
+            }
+            FindingKind::Gap => format!(
+                "gap:{}:{}:{}:{:.2}",
+                self.account_id,
+                self.date_from,
+                self.date_to,
+                self.amount.unwrap_or_default()
+            ),
+        }
+    }
+}
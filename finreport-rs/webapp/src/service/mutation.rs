@@ -1,13 +1,2234 @@
-use sea_orm::DbConn;
+use crate::balance::DailyBalance;
+use crate::counterparty;
+use crate::service::{
+    AccountInput, AttachmentInput, BalanceInput, CategorizationRuleInput, CategoryAssignment, ChangeSource,
+    CounterpartyRuleKind, FindingAction, FindingInput, FindingStatus, ImportRunKind, MandateConflictStatus,
+    Query, SavedViewInput, ServiceError, Split, SplitInput, TransactionInput, TransferPair, TransferStatus,
+    UpsertOutcome, UpsertSummary, normalize_tag,
+};
+use crate::filter::FilterQuery;
+use crate::remittance::{self, Remittance};
+use categorizer::categorize::Category;
+use chrono::Utc;
+use comdirect_rs::comdirect::transaction::Transaction;
+use entity::entities::{
+    account, account_balance, account_transactions, attachments, categories, categorization_rules,
+    counterparties, counterparty_rules, daily_balances, data_findings, import_run_accounts, import_runs,
+    internal_transfers, mandate_categories, mandate_category_conflicts, saved_views, tags,
+    transaction_categories, transaction_counterparties, transaction_history, transaction_notes,
+    transaction_remittance, transaction_splits, transaction_tags,
+};
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DbConn, EntityTrait, ModelTrait,
+    QueryFilter, QueryOrder, Set, TransactionTrait, Unchanged,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use tracing::{debug, error};
 
+/// Split parts have to add up to the booking to the cent.
+const CENT: f64 = 0.005;
 
 pub struct Mutation;
 
 impl Mutation {
-    pub fn add_account(
-        db:&DbConn,
-        // data: entity::account::ActiveModel,
-    ) {
+    /// Create the account unless an account with the same `account_id`
+    /// exists; existing accounts are left as they are.
+    pub async fn ensure_account(
+        db: &DbConn,
+        input: AccountInput,
+    ) -> Result<account::Model, ServiceError> {
+        let account_id = input.account_id.clone();
+        let model = account::ActiveModel {
+            account_id: Set(input.account_id),
+            display_id: Set(input.display_id),
+            account_type: Set(input.account_type),
+            iban: Set(input.iban),
+            bic: Set(input.bic),
+            institute: Set(input.institute),
+            ..Default::default()
+        };
+        account::Entity::insert(model)
+            .on_conflict(
+                OnConflict::column(account::Column::AccountId)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec(db)
+            .await?;
+        Query::find_account(db, &account_id)
+            .await?
+            .ok_or(ServiceError::AccountNotFound(account_id))
+    }
+
+    /// Store a balance snapshot. The first snapshot of a day wins.
+    pub async fn record_balance(db: &DbConn, input: BalanceInput) -> Result<(), ServiceError> {
+        let model = account_balance::ActiveModel {
+            account_id: Set(input.account_id),
+            amount: Set(input.amount),
+            date: Set(input.date),
+            ..Default::default()
+        };
+        account_balance::Entity::insert(model)
+            .on_conflict(
+                OnConflict::columns([
+                    account_balance::Column::AccountId,
+                    account_balance::Column::Date,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .do_nothing()
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Insert a transaction or update the booking keyed by its `reference`;
+    /// banks revise pending bookings, so everything but the account may change.
+    /// Every changed field of an existing booking is appended to
+    /// `transaction_history` in the same database transaction, and the
+    /// remittance info is parsed again.
+    pub async fn upsert_transaction(
+        db: &DbConn,
+        input: TransactionInput,
+        source: ChangeSource,
+    ) -> Result<UpsertOutcome, ServiceError> {
+        let txn = db.begin().await?;
+        let existing = account_transactions::Entity::find()
+            .filter(account_transactions::Column::Reference.eq(&input.reference))
+            .one(&txn)
+            .await?;
+        if let Some(existing) = &existing {
+            let changes = changed_fields(existing, &input);
+            if changes.is_empty() {
+                txn.commit().await?;
+                return Ok(UpsertOutcome::Unchanged);
+            }
+            for (field, old, new) in changes {
+                record_change(&txn, &input.reference, source, field, Some(old), Some(new)).await?;
+            }
+        }
+
+        let input_amount = input.amount;
+        let parsed = remittance::parse(&input.remittance_info);
+        let reference = input.reference.clone();
+        let model = account_transactions::ActiveModel {
+            reference: Set(input.reference),
+            account_id: Set(input.account_id),
+            booking_status: Set(input.booking_status),
+            booking_date: Set(input.booking_date),
+            amount: Set(input.amount),
+            remitter: Set(input.remitter),
+            deptor: Set(input.deptor),
+            creditor: Set(input.creditor),
+            creditor_id: Set(input.creditor_id),
+            creditor_mandate_id: Set(input.creditor_mandate_id),
+            remittance_info: Set(input.remittance_info),
+            transaction_type: Set(input.transaction_type),
+            counterparty_iban: Set(input.counterparty_iban),
+            ..Default::default()
+        };
+        account_transactions::Entity::insert(model)
+            .on_conflict(
+                OnConflict::column(account_transactions::Column::Reference)
+                    .update_columns([
+                        account_transactions::Column::BookingStatus,
+                        account_transactions::Column::BookingDate,
+                        account_transactions::Column::Amount,
+                        account_transactions::Column::Remitter,
+                        account_transactions::Column::Deptor,
+                        account_transactions::Column::Creditor,
+                        account_transactions::Column::CreditorId,
+                        account_transactions::Column::CreditorMandateId,
+                        account_transactions::Column::RemittanceInfo,
+                        account_transactions::Column::TransactionType,
+                        account_transactions::Column::CounterpartyIban,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&txn)
+            .await?;
+        upsert_remittances(&txn, vec![remittance_model(reference, &parsed)]).await?;
+        if let Some(existing) = &existing {
+            rescale_splits(&txn, &existing.reference, existing.amount, input_amount, source).await?;
+        }
+        txn.commit().await?;
+        Ok(match existing {
+            Some(_) => UpsertOutcome::Updated,
+            None => UpsertOutcome::Inserted,
+        })
+    }
+
+    /// Upsert a batch; failing rows are logged and counted, not fatal.
+    pub async fn upsert_transactions(
+        db: &DbConn,
+        inputs: Vec<TransactionInput>,
+        source: ChangeSource,
+    ) -> Result<UpsertSummary, ServiceError> {
+        let mut summary = UpsertSummary::default();
+        for input in inputs {
+            let reference = input.reference.clone();
+            match Self::upsert_transaction(db, input, source).await {
+                Ok(outcome) => {
+                    debug!(%reference, ?outcome, "upserted transaction");
+                    summary.count(outcome);
+                }
+                Err(e) => {
+                    error!(%reference, %e, "failed to upsert transaction");
+                    summary.failed += 1;
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Upsert transactions fetched from the Comdirect API; rows that fail to
+    /// convert count as failed like rows that fail to store.
+    pub async fn upsert_comdirect(
+        db: &DbConn,
+        account_id: &str,
+        transactions: &[Transaction],
+        source: ChangeSource,
+    ) -> Result<UpsertSummary, ServiceError> {
+        let mut inputs = Vec::with_capacity(transactions.len());
+        let mut invalid = 0;
+        for transaction in transactions {
+            match TransactionInput::from_comdirect(account_id, transaction) {
+                Ok(input) => inputs.push(input),
+                Err(e) => {
+                    error!(%e, "skipping transaction");
+                    invalid += 1;
+                }
+            }
+        }
+        let mut summary = Self::upsert_transactions(db, inputs, source).await?;
+        summary.failed += invalid;
+        Ok(summary)
+    }
+
+    pub async fn start_import_run(
+        db: &DbConn,
+        kind: ImportRunKind,
+        connector: &str,
+    ) -> Result<import_runs::Model, ServiceError> {
+        Ok(import_runs::ActiveModel {
+            kind: Set(kind.to_string()),
+            connector: Set(connector.to_string()),
+            started_at: Set(Utc::now().fixed_offset()),
+            finished_at: Set(None),
+            pages_fetched: Set(0),
+            error: Set(None),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+
+    pub async fn record_import_account(
+        db: &DbConn,
+        import_run_id: i32,
+        account_id: &str,
+        fetched: usize,
+        summary: &UpsertSummary,
+    ) -> Result<(), ServiceError> {
+        import_run_accounts::ActiveModel {
+            import_run_id: Set(import_run_id),
+            account_id: Set(account_id.to_string()),
+            fetched: Set(fetched as i32),
+            // Unchanged rows were seen again, not written; they count as
+            // neither inserted nor updated.
+            inserted: Set(summary.inserted as i32),
+            updated: Set(summary.updated as i32),
+            failed: Set(summary.failed as i32),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Close a run; a run with an `error` counts as failed.
+    pub async fn finish_import_run(
+        db: &DbConn,
+        import_run_id: i32,
+        pages_fetched: u32,
+        error: Option<String>,
+    ) -> Result<(), ServiceError> {
+        import_runs::ActiveModel {
+            id: Unchanged(import_run_id),
+            finished_at: Set(Some(Utc::now().fixed_offset())),
+            pages_fetched: Set(pages_fetched as i32),
+            error: Set(error),
+            ..Default::default()
+        }
+        .update(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Replace the reconstructed balance series of an account.
+    pub async fn replace_daily_balances(
+        db: &DbConn,
+        account_id: &str,
+        days: &[DailyBalance],
+    ) -> Result<(), ServiceError> {
+        let txn = db.begin().await?;
+        daily_balances::Entity::delete_many()
+            .filter(daily_balances::Column::AccountId.eq(account_id))
+            .exec(&txn)
+            .await?;
+        // Stay below SQLite's limit of bind parameters per statement.
+        for chunk in days.chunks(200) {
+            let models = chunk.iter().map(|d| daily_balances::ActiveModel {
+                account_id: Set(account_id.to_string()),
+                date: Set(d.date),
+                amount: Set(d.amount),
+                snapshot: Set(d.snapshot),
+                ..Default::default()
+            });
+            daily_balances::Entity::insert_many(models).exec(&txn).await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Store the findings of a detection run. Open findings that were not
+    /// detected again are dropped; reviewed ones stay as they are and are not
+    /// raised again. Returns the number of new findings.
+    pub async fn replace_open_findings(
+        db: &DbConn,
+        findings: Vec<FindingInput>,
+    ) -> Result<usize, ServiceError> {
+        let txn = db.begin().await?;
+        let detected: HashSet<String> = findings.iter().map(FindingInput::fingerprint).collect();
+        let mut known = HashSet::new();
+        for existing in data_findings::Entity::find().all(&txn).await? {
+            if existing.status == FindingStatus::Open.to_string()
+                && !detected.contains(&existing.fingerprint)
+            {
+                existing.delete(&txn).await?;
+            } else {
+                known.insert(existing.fingerprint);
+            }
+        }
+
+        let now = Utc::now().fixed_offset();
+        let mut created = 0;
+        for finding in findings {
+            let fingerprint = finding.fingerprint();
+            if !known.insert(fingerprint.clone()) {
+                continue;
+            }
+            data_findings::ActiveModel {
+                fingerprint: Set(fingerprint),
+                kind: Set(finding.kind.to_string()),
+                account_id: Set(finding.account_id),
+                reference: Set(finding.reference),
+                other_reference: Set(finding.other_reference),
+                date_from: Set(finding.date_from),
+                date_to: Set(finding.date_to),
+                amount: Set(finding.amount),
+                similarity: Set(finding.similarity),
+                status: Set(FindingStatus::Open.to_string()),
+                detected_at: Set(now),
+                resolved_at: Set(None),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+            created += 1;
+        }
+        txn.commit().await?;
+        Ok(created)
+    }
+
+    /// Act on an open finding. Merging deletes the duplicate that is not
+    /// `keep_reference` (by default the earlier booking, `reference`), moves
+    /// its category over if the survivor has none and notes the merge in the
+    /// history of the deleted booking.
+    pub async fn resolve_finding(
+        db: &DbConn,
+        id: i32,
+        action: FindingAction,
+        keep_reference: Option<String>,
+    ) -> Result<data_findings::Model, ServiceError> {
+        let finding = data_findings::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or(ServiceError::FindingNotFound(id))?;
+        let invalid = |message: String| ServiceError::InvalidFindingAction { id, message };
+        if finding.status != FindingStatus::Open.to_string() {
+            return Err(invalid(format!("already {}", finding.status)));
+        }
+
+        let txn = db.begin().await?;
+        let status = match action {
+            FindingAction::Ignore => FindingStatus::Ignored,
+            FindingAction::Keep => FindingStatus::Kept,
+            FindingAction::Merge => {
+                let (Some(reference), Some(other)) = (&finding.reference, &finding.other_reference)
+                else {
+                    return Err(invalid(format!("a {} cannot be merged", finding.kind)));
+                };
+                let keep = keep_reference.unwrap_or_else(|| reference.clone());
+                let drop = if &keep == reference {
+                    other.clone()
+                } else if &keep == other {
+                    reference.clone()
+                } else {
+                    return Err(invalid(format!("{keep} is not part of the finding")));
+                };
+                merge_duplicate(&txn, &keep, &drop).await?;
+                // Other findings about the deleted booking are moot now.
+                data_findings::Entity::delete_many()
+                    .filter(data_findings::Column::Id.ne(id))
+                    .filter(data_findings::Column::Status.eq(FindingStatus::Open.to_string()))
+                    .filter(
+                        Condition::any()
+                            .add(data_findings::Column::Reference.eq(&drop))
+                            .add(data_findings::Column::OtherReference.eq(&drop)),
+                    )
+                    .exec(&txn)
+                    .await?;
+                FindingStatus::Merged
+            }
+        };
+        let mut finding: data_findings::ActiveModel = finding.into();
+        finding.status = Set(status.to_string());
+        finding.resolved_at = Set(Some(Utc::now().fixed_offset()));
+        let finding = finding.update(&txn).await?;
+        txn.commit().await?;
+        Ok(finding)
+    }
+
+    /// Add pairs from `prompts/categories.json` that are not stored yet, so
+    /// the prompt never offers a category the database cannot hold.
+    pub async fn sync_categories(db: &DbConn, taxonomy: &[Category]) -> Result<(), ServiceError> {
+        let models: Vec<categories::ActiveModel> = taxonomy
+            .iter()
+            .flat_map(|c| {
+                c.subcategories.iter().map(|sub| categories::ActiveModel {
+                    category: Set(c.category.clone()),
+                    subcategory: Set(sub.clone()),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        if models.is_empty() {
+            return Ok(());
+        }
+        categories::Entity::insert_many(models)
+            .on_conflict(
+                OnConflict::columns([categories::Column::Category, categories::Column::Subcategory])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Set the category of a transaction, replacing an earlier one. A
+    /// changed category is appended to `transaction_history`. Returns the
+    /// category that was stored. Split transactions are refused.
+    pub async fn assign_category(
+        db: &DbConn,
+        input: CategoryAssignment,
+    ) -> Result<categories::Model, ServiceError> {
+        let category = Query::find_category(db, &input.category, &input.subcategory).await?;
+        if !Query::splits(db, &input.reference).await?.is_empty() {
+            return Err(ServiceError::InvalidTransaction {
+                reference: input.reference,
+                message: "the transaction is split, categorize its parts".to_string(),
+            });
+        }
+        let txn = db.begin().await?;
+        let previous = transaction_categories::Entity::find_by_id(input.reference.as_str())
+            .find_also_related(categories::Entity)
+            .one(&txn)
+            .await?
+            .and_then(|(_, previous)| previous);
+        // A user choosing the category a transaction has makes it theirs.
+        if previous.as_ref().map(|p| p.id) != Some(category.id) || input.source == ChangeSource::User {
+            record_change(
+                &txn,
+                &input.reference,
+                input.source,
+                "category",
+                previous.map(|p| category_label(&p)),
+                Some(category_label(&category)),
+            )
+            .await?;
+        }
+
+        let model = transaction_categories::ActiveModel {
+            reference: Set(input.reference),
+            category_id: Set(category.id),
+            reasoning: Set(input.reasoning),
+            confidence: Set(input.confidence),
+        };
+        transaction_categories::Entity::insert(model)
+            .on_conflict(
+                OnConflict::column(transaction_categories::Column::Reference)
+                    .update_columns([
+                        transaction_categories::Column::CategoryId,
+                        transaction_categories::Column::Reasoning,
+                        transaction_categories::Column::Confidence,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(category)
+    }
+
+    /// Accept the category a transaction has; from then on it counts as
+    /// chosen by `source`, like a category assigned by it.
+    pub async fn confirm_category(
+        db: &DbConn,
+        reference: &str,
+        source: ChangeSource,
+    ) -> Result<categories::Model, ServiceError> {
+        let Some(category) = Query::category(db, reference).await? else {
+            return Err(ServiceError::InvalidTransaction {
+                reference: reference.to_string(),
+                message: "the transaction has no category to confirm".to_string(),
+            });
+        };
+        let label = Some(category_label(&category));
+        record_change(db, reference, source, "category", label.clone(), label).await?;
+        Ok(category)
+    }
+
+    /// Divide a transaction into parts, replacing earlier parts and its
+    /// category: from then on the parts are what reports and the categorizer
+    /// go by. The parts must sum to the amount of the booking. No parts
+    /// remove the split and leave the transaction uncategorized.
+    pub async fn split_transaction(
+        db: &DbConn,
+        reference: &str,
+        parts: Vec<SplitInput>,
+        source: ChangeSource,
+    ) -> Result<Vec<Split>, ServiceError> {
+        let invalid = |message: String| ServiceError::InvalidTransaction {
+            reference: reference.to_string(),
+            message,
+        };
+        let transaction = account_transactions::Entity::find()
+            .filter(account_transactions::Column::Reference.eq(reference))
+            .one(db)
+            .await?
+            .ok_or_else(|| invalid("unknown reference".to_string()))?;
+        if parts.len() == 1 {
+            return Err(invalid("a split needs at least two parts".to_string()));
+        }
+        if let Some(part) = parts.iter().find(|p| !p.amount.is_finite() || p.amount.abs() < CENT) {
+            return Err(invalid(format!("part amount {} is not a non-zero amount", part.amount)));
+        }
+        let sum: f64 = parts.iter().map(|p| p.amount).sum();
+        if !parts.is_empty() && (sum - transaction.amount).abs() >= CENT {
+            return Err(invalid(format!(
+                "parts sum to {sum:.2}, the transaction to {:.2}",
+                transaction.amount
+            )));
+        }
+        let mut resolved = Vec::with_capacity(parts.len());
+        for part in parts {
+            let category = Query::find_category(db, &part.category, &part.subcategory).await?;
+            resolved.push((part, category));
+        }
+
+        let previous = splits_label(&Query::splits(db, reference).await?);
+        let txn = db.begin().await?;
+        transaction_splits::Entity::delete_many()
+            .filter(transaction_splits::Column::Reference.eq(reference))
+            .exec(&txn)
+            .await?;
+        let mut splits = Vec::with_capacity(resolved.len());
+        for (position, (part, category)) in resolved.into_iter().enumerate() {
+            let split = transaction_splits::ActiveModel {
+                reference: Set(reference.to_string()),
+                position: Set(position as i32),
+                amount: Set(part.amount),
+                category_id: Set(category.id),
+                note: Set(part.note.filter(|n| !n.trim().is_empty())),
+                tag: Set(part.tag.filter(|t| !t.trim().is_empty())),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+            splits.push((split, category));
+        }
+
+        if !splits.is_empty() {
+            let category = transaction_categories::Entity::find_by_id(reference)
+                .find_also_related(categories::Entity)
+                .one(&txn)
+                .await?;
+            if let Some((assignment, category)) = category {
+                assignment.delete(&txn).await?;
+                let label = category.map(|c| category_label(&c));
+                record_change(&txn, reference, source, "category", label, None).await?;
+            }
+        }
+        let current = splits_label(&splits);
+        if previous != current {
+            record_change(&txn, reference, source, "splits", previous, current).await?;
+        }
+        txn.commit().await?;
+        Ok(splits)
+    }
+
+    /// Link two bookings, in either order, as the legs of an internal
+    /// transfer. Earlier unlinked pairs of either leg are dropped; a leg that
+    /// is linked to another booking has to be unlinked first.
+    pub async fn link_transfer(
+        db: &DbConn,
+        reference: &str,
+        other_reference: &str,
+        source: ChangeSource,
+    ) -> Result<internal_transfers::Model, ServiceError> {
+        let invalid = |message: String| ServiceError::InvalidTransaction {
+            reference: reference.to_string(),
+            message,
+        };
+        let legs = Query::transactions_by_reference(db, &[reference.to_string(), other_reference.to_string()])
+            .await?;
+        let find = |r: &str| legs.iter().find(|t| t.reference == r).cloned();
+        let (Some(leg), Some(other)) = (find(reference), find(other_reference)) else {
+            return Err(invalid(format!("{other_reference} or {reference} does not exist")));
+        };
+        if leg.account_id == other.account_id {
+            return Err(invalid("both legs are on the same account".to_string()));
+        }
+        if (leg.amount + other.amount).abs() >= CENT || leg.amount == 0.0 {
+            return Err(invalid(format!(
+                "amounts {:.2} and {:.2} do not cancel out",
+                leg.amount, other.amount
+            )));
+        }
+        let (outgoing, incoming) = match leg.amount < 0.0 {
+            true => (leg, other),
+            false => (other, leg),
+        };
+        let pair = TransferPair {
+            outgoing: outgoing.reference,
+            incoming: incoming.reference,
+        };
+
+        let existing = transfers_of(db, &[pair.outgoing.clone(), pair.incoming.clone()]).await?;
+        if let Some(linked) = existing.iter().find(|t| t.status == TransferStatus::Linked.to_string()) {
+            if linked.outgoing_reference == pair.outgoing && linked.incoming_reference == pair.incoming {
+                return Ok(linked.clone());
+            }
+            return Err(invalid(format!(
+                "already linked: {} to {}",
+                linked.outgoing_reference, linked.incoming_reference
+            )));
+        }
+        let txn = db.begin().await?;
+        for unlinked in existing {
+            unlinked.delete(&txn).await?;
+        }
+        let transfer = insert_transfer(&txn, &pair, source).await?;
+        txn.commit().await?;
+        Ok(transfer)
+    }
+
+    /// Link pairs found by the matcher. Pairs with a leg that is already part
+    /// of a transfer, linked or unlinked, are skipped. Returns how many pairs
+    /// were linked.
+    pub async fn link_detected_transfers(db: &DbConn, pairs: &[TransferPair]) -> Result<usize, ServiceError> {
+        let references: Vec<String> =
+            pairs.iter().flat_map(|p| [p.outgoing.clone(), p.incoming.clone()]).collect();
+        let mut taken: HashSet<String> = transfers_of(db, &references)
+            .await?
+            .into_iter()
+            .flat_map(|t| [t.outgoing_reference, t.incoming_reference])
+            .collect();
+        let txn = db.begin().await?;
+        let mut linked = 0;
+        for pair in pairs {
+            if taken.contains(&pair.outgoing) || taken.contains(&pair.incoming) {
+                continue;
+            }
+            insert_transfer(&txn, pair, ChangeSource::TransferMatcher).await?;
+            taken.extend([pair.outgoing.clone(), pair.incoming.clone()]);
+            linked += 1;
+        }
+        txn.commit().await?;
+        Ok(linked)
+    }
+
+    /// Unlink the internal transfer `reference` is a leg of. The pair is kept
+    /// as unlinked, so the matcher leaves it alone.
+    pub async fn unlink_transfer(
+        db: &DbConn,
+        reference: &str,
+        source: ChangeSource,
+    ) -> Result<internal_transfers::Model, ServiceError> {
+        let linked = transfers_of(db, &[reference.to_string()])
+            .await?
+            .into_iter()
+            .find(|t| t.status == TransferStatus::Linked.to_string())
+            .ok_or_else(|| ServiceError::InvalidTransaction {
+                reference: reference.to_string(),
+                message: "not part of an internal transfer".to_string(),
+            })?;
+        let txn = db.begin().await?;
+        let (outgoing, incoming) = (linked.outgoing_reference.clone(), linked.incoming_reference.clone());
+        let mut active: internal_transfers::ActiveModel = linked.into();
+        active.status = Set(TransferStatus::Unlinked.to_string());
+        active.source = Set(source.to_string());
+        let transfer = active.update(&txn).await?;
+        record_change(&txn, &outgoing, source, "transfer", Some(incoming.clone()), None).await?;
+        record_change(&txn, &incoming, source, "transfer", Some(outgoing), None).await?;
+        txn.commit().await?;
+        Ok(transfer)
+    }
+
+    /// Add tags to transactions, creating tags that do not exist yet.
+    /// Returns how many tags were new on a transaction.
+    pub async fn add_tags(
+        db: &DbConn,
+        references: &[String],
+        tags: &[String],
+        source: ChangeSource,
+    ) -> Result<usize, ServiceError> {
+        let names = tags.iter().map(|t| normalize_tag(t)).collect::<Result<BTreeSet<_>, _>>()?;
+        let txn = db.begin().await?;
+        let mut ids = Vec::with_capacity(names.len());
+        for name in &names {
+            let existing = tags::Entity::find()
+                .filter(tags::Column::Name.eq(name))
+                .one(&txn)
+                .await?;
+            let tag = match existing {
+                Some(tag) => tag,
+                None => {
+                    tags::ActiveModel {
+                        name: Set(name.clone()),
+                        ..Default::default()
+                    }
+                    .insert(&txn)
+                    .await?
+                }
+            };
+            ids.push(tag.id);
+        }
+
+        let mut added = 0;
+        for (reference, current) in current_tags(&txn, references).await? {
+            let missing: Vec<(i32, &String)> = ids
+                .iter()
+                .zip(&names)
+                .filter(|(_, name)| !current.contains(*name))
+                .map(|(id, name)| (*id, name))
+                .collect();
+            if missing.is_empty() {
+                continue;
+            }
+            for (tag_id, _) in &missing {
+                transaction_tags::ActiveModel {
+                    reference: Set(reference.clone()),
+                    tag_id: Set(*tag_id),
+                }
+                .insert(&txn)
+                .await?;
+            }
+            added += missing.len();
+            let mut updated = current.clone();
+            updated.extend(missing.into_iter().map(|(_, name)| name.clone()));
+            let (old, new) = (tags_label(&current), tags_label(&updated));
+            record_change(&txn, &reference, source, "tags", old, new).await?;
+        }
+        txn.commit().await?;
+        Ok(added)
+    }
+
+    /// Remove tags from transactions. Tags left on no transaction are kept
+    /// for reuse. Returns how many tags were removed from a transaction.
+    pub async fn remove_tags(
+        db: &DbConn,
+        references: &[String],
+        tags: &[String],
+        source: ChangeSource,
+    ) -> Result<usize, ServiceError> {
+        let names = tags.iter().map(|t| normalize_tag(t)).collect::<Result<BTreeSet<_>, _>>()?;
+        let ids: Vec<i32> = tags::Entity::find()
+            .filter(tags::Column::Name.is_in(&names))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        let txn = db.begin().await?;
+        let mut removed = 0;
+        for (reference, current) in current_tags(&txn, references).await? {
+            let updated: BTreeSet<String> = current.difference(&names).cloned().collect();
+            if updated.len() == current.len() {
+                continue;
+            }
+            transaction_tags::Entity::delete_many()
+                .filter(transaction_tags::Column::Reference.eq(&reference))
+                .filter(transaction_tags::Column::TagId.is_in(ids.clone()))
+                .exec(&txn)
+                .await?;
+            removed += current.len() - updated.len();
+            let (old, new) = (tags_label(&current), tags_label(&updated));
+            record_change(&txn, &reference, source, "tags", old, new).await?;
+        }
+        txn.commit().await?;
+        Ok(removed)
+    }
+
+    /// Set the note of transactions; `None` or a blank note removes it.
+    /// Returns how many notes changed.
+    pub async fn set_note(
+        db: &DbConn,
+        references: &[String],
+        note: Option<String>,
+        source: ChangeSource,
+    ) -> Result<usize, ServiceError> {
+        let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+        let txn = db.begin().await?;
+        let mut notes: HashMap<String, transaction_notes::Model> = transaction_notes::Entity::find()
+            .filter(transaction_notes::Column::Reference.is_in(references))
+            .all(&txn)
+            .await?
+            .into_iter()
+            .map(|n| (n.reference.clone(), n))
+            .collect();
+        let mut changed = 0;
+        for reference in known_references(&txn, references).await? {
+            let previous = notes.remove(&reference);
+            if previous.as_ref().map(|p| &p.note) == note.as_ref() {
+                continue;
+            }
+            let old_value = previous.as_ref().map(|p| p.note.clone());
+            match (&note, previous) {
+                (Some(note), Some(previous)) => {
+                    let mut previous: transaction_notes::ActiveModel = previous.into();
+                    previous.note = Set(note.clone());
+                    previous.updated_at = Set(Utc::now().fixed_offset());
+                    previous.update(&txn).await?;
+                }
+                (Some(note), None) => {
+                    transaction_notes::ActiveModel {
+                        reference: Set(reference.clone()),
+                        note: Set(note.clone()),
+                        updated_at: Set(Utc::now().fixed_offset()),
+                    }
+                    .insert(&txn)
+                    .await?;
+                }
+                (None, Some(previous)) => {
+                    previous.delete(&txn).await?;
+                }
+                (None, None) => continue,
+            }
+            record_change(&txn, &reference, source, "note", old_value, note.clone()).await?;
+            changed += 1;
+        }
+        txn.commit().await?;
+        Ok(changed)
+    }
+
+    /// Attach stored content to a transaction. The content is not checked;
+    /// [`crate::attachment::attach`] stores it first.
+    pub async fn add_attachment(
+        db: &DbConn,
+        input: AttachmentInput,
+        source: ChangeSource,
+    ) -> Result<attachments::Model, ServiceError> {
+        let txn = db.begin().await?;
+        known_references(&txn, std::slice::from_ref(&input.reference)).await?;
+        let attachment = attachments::ActiveModel {
+            reference: Set(input.reference),
+            sha256: Set(input.sha256),
+            file_name: Set(input.file_name),
+            content_type: Set(input.content_type),
+            size: Set(input.size),
+            created_at: Set(Utc::now().fixed_offset()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        let file_name = Some(attachment.file_name.clone());
+        record_change(&txn, &attachment.reference, source, "attachment", None, file_name).await?;
+        txn.commit().await?;
+        Ok(attachment)
+    }
+
+    /// Remove an attachment; whether its content can go is up to the caller.
+    pub async fn delete_attachment(
+        db: &DbConn,
+        id: i32,
+        source: ChangeSource,
+    ) -> Result<attachments::Model, ServiceError> {
+        let attachment = Query::find_attachment(db, id).await?;
+        let txn = db.begin().await?;
+        attachment.clone().delete(&txn).await?;
+        let file_name = Some(attachment.file_name.clone());
+        record_change(&txn, &attachment.reference, source, "attachment", file_name, None).await?;
+        txn.commit().await?;
+        Ok(attachment)
+    }
+
+    /// Store a view, or rename and refilter the owner's view `id`. The filter
+    /// must parse and names are unique per owner.
+    pub async fn save_view(
+        db: &DbConn,
+        id: Option<i32>,
+        input: SavedViewInput,
+    ) -> Result<saved_views::Model, ServiceError> {
+        let name = input.name.trim().to_string();
+        if name.is_empty() {
+            return Err(ServiceError::InvalidView("the name is empty".to_string()));
+        }
+        input.filter.parse::<FilterQuery>()?;
+
+        let txn = db.begin().await?;
+        let mut taken = saved_views::Entity::find()
+            .filter(saved_views::Column::Owner.eq(&input.owner))
+            .filter(saved_views::Column::Name.eq(&name));
+        if let Some(id) = id {
+            taken = taken.filter(saved_views::Column::Id.ne(id));
+        }
+        if taken.one(&txn).await?.is_some() {
+            return Err(ServiceError::InvalidView(format!("a view named {name} exists")));
+        }
+
+        let now = Utc::now().fixed_offset();
+        let view = match id {
+            Some(id) => {
+                let view = saved_views::Entity::find_by_id(id)
+                    .filter(saved_views::Column::Owner.eq(&input.owner))
+                    .one(&txn)
+                    .await?
+                    .ok_or_else(|| ServiceError::ViewNotFound(id.to_string()))?;
+                let mut view: saved_views::ActiveModel = view.into();
+                view.name = Set(name);
+                view.filter = Set(input.filter);
+                view.updated_at = Set(now);
+                view.update(&txn).await?
+            }
+            None => {
+                saved_views::ActiveModel {
+                    owner: Set(input.owner),
+                    name: Set(name),
+                    filter: Set(input.filter),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await?
+            }
+        };
+        txn.commit().await?;
+        Ok(view)
+    }
+
+    pub async fn delete_view(db: &DbConn, owner: &str, id: i32) -> Result<(), ServiceError> {
+        let result = saved_views::Entity::delete_many()
+            .filter(saved_views::Column::Id.eq(id))
+            .filter(saved_views::Column::Owner.eq(owner))
+            .exec(db)
+            .await?;
+        if result.rows_affected == 0 {
+            return Err(ServiceError::ViewNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Create counterparties for payee names no rule knows, each with its
+    /// alias so the next resolution finds it; `names` maps aliases to names.
+    /// An alias whose name exists already is added to that counterparty.
+    /// Returns the counterparty id by alias.
+    pub async fn create_counterparties(
+        db: &DbConn,
+        names: &BTreeMap<String, String>,
+    ) -> Result<HashMap<String, i32>, ServiceError> {
+        let mut existing: HashMap<String, i32> = counterparties::Entity::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|c| (c.name, c.id))
+            .collect();
+        let txn = db.begin().await?;
+        let mut ids = HashMap::new();
+        for (alias, name) in names {
+            let id = match existing.get(name) {
+                Some(id) => *id,
+                None => {
+                    let counterparty = counterparties::ActiveModel {
+                        name: Set(name.clone()),
+                        created_at: Set(Utc::now().fixed_offset()),
+                        ..Default::default()
+                    }
+                    .insert(&txn)
+                    .await?;
+                    existing.insert(counterparty.name, counterparty.id);
+                    counterparty.id
+                }
+            };
+            counterparty_rules::ActiveModel {
+                counterparty_id: Set(id),
+                kind: Set(CounterpartyRuleKind::Alias.to_string()),
+                pattern: Set(alias.clone()),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+            ids.insert(alias.clone(), id);
+        }
+        txn.commit().await?;
+        Ok(ids)
+    }
+
+    /// Point every transaction in `links` at its counterparty; transactions
+    /// missing from `links` lose theirs. Returns how many links changed.
+    pub async fn set_counterparties(
+        db: &DbConn,
+        links: &HashMap<String, i32>,
+    ) -> Result<usize, ServiceError> {
+        let current: HashMap<String, i32> = transaction_counterparties::Entity::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|link| (link.reference, link.counterparty_id))
+            .collect();
+        let stale: Vec<&String> = current.keys().filter(|r| !links.contains_key(*r)).collect();
+        let txn = db.begin().await?;
+        let mut changed = stale.len();
+        if !stale.is_empty() {
+            transaction_counterparties::Entity::delete_many()
+                .filter(transaction_counterparties::Column::Reference.is_in(stale))
+                .exec(&txn)
+                .await?;
+        }
+        for (reference, id) in links {
+            if current.get(reference) == Some(id) {
+                continue;
+            }
+            let link = transaction_counterparties::ActiveModel {
+                reference: Set(reference.clone()),
+                counterparty_id: Set(*id),
+            };
+            transaction_counterparties::Entity::insert(link)
+                .on_conflict(
+                    OnConflict::column(transaction_counterparties::Column::Reference)
+                        .update_column(transaction_counterparties::Column::CounterpartyId)
+                        .to_owned(),
+                )
+                .exec(&txn)
+                .await?;
+            changed += 1;
+        }
+        txn.commit().await?;
+        Ok(changed)
+    }
+
+    pub async fn rename_counterparty(
+        db: &DbConn,
+        id: i32,
+        name: &str,
+    ) -> Result<counterparties::Model, ServiceError> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(ServiceError::InvalidCounterparty("the name is empty".to_string()));
+        }
+        let counterparty = Query::find_counterparty(db, id).await?;
+        let taken = counterparties::Entity::find()
+            .filter(counterparties::Column::Name.eq(&name))
+            .filter(counterparties::Column::Id.ne(id))
+            .one(db)
+            .await?;
+        if let Some(other) = taken {
+            return Err(ServiceError::InvalidCounterparty(format!(
+                "{name} exists as counterparty {}; merge them instead",
+                other.id
+            )));
+        }
+        let mut active: counterparties::ActiveModel = counterparty.into();
+        active.name = Set(name);
+        Ok(active.update(db).await?)
+    }
+
+    /// Fold the counterparties `ids` into `into`: their rules and
+    /// transactions move over and they are deleted.
+    pub async fn merge_counterparties(
+        db: &DbConn,
+        ids: &[i32],
+        into: i32,
+    ) -> Result<counterparties::Model, ServiceError> {
+        let target = Query::find_counterparty(db, into).await?;
+        let ids: BTreeSet<i32> = ids.iter().copied().filter(|id| *id != into).collect();
+        for id in &ids {
+            Query::find_counterparty(db, *id).await?;
+        }
+        let txn = db.begin().await?;
+        counterparty_rules::Entity::update_many()
+            .col_expr(counterparty_rules::Column::CounterpartyId, Expr::value(into))
+            .filter(counterparty_rules::Column::CounterpartyId.is_in(ids.clone()))
+            .exec(&txn)
+            .await?;
+        transaction_counterparties::Entity::update_many()
+            .col_expr(transaction_counterparties::Column::CounterpartyId, Expr::value(into))
+            .filter(transaction_counterparties::Column::CounterpartyId.is_in(ids.clone()))
+            .exec(&txn)
+            .await?;
+        counterparties::Entity::delete_many()
+            .filter(counterparties::Column::Id.is_in(ids))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(target)
+    }
+
+    /// Add a rule to a counterparty. The pattern is normalized for its kind;
+    /// a rule of another counterparty with the same pattern is an error.
+    pub async fn add_counterparty_rule(
+        db: &DbConn,
+        counterparty_id: i32,
+        kind: CounterpartyRuleKind,
+        pattern: &str,
+    ) -> Result<counterparty_rules::Model, ServiceError> {
+        Query::find_counterparty(db, counterparty_id).await?;
+        let pattern = counterparty::rule_pattern(kind, pattern)?;
+        let existing = counterparty_rules::Entity::find()
+            .filter(counterparty_rules::Column::Kind.eq(kind.to_string()))
+            .filter(counterparty_rules::Column::Pattern.eq(&pattern))
+            .one(db)
+            .await?;
+        match existing {
+            Some(rule) if rule.counterparty_id == counterparty_id => Ok(rule),
+            Some(rule) => Err(ServiceError::InvalidCounterparty(format!(
+                "{kind} `{pattern}` belongs to counterparty {}",
+                rule.counterparty_id
+            ))),
+            None => Ok(counterparty_rules::ActiveModel {
+                counterparty_id: Set(counterparty_id),
+                kind: Set(kind.to_string()),
+                pattern: Set(pattern),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?),
+        }
+    }
+
+    /// Delete a rule; returns it.
+    pub async fn remove_counterparty_rule(
+        db: &DbConn,
+        id: i32,
+    ) -> Result<counterparty_rules::Model, ServiceError> {
+        let rule = counterparty_rules::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| ServiceError::InvalidCounterparty(format!("rule {id} does not exist")))?;
+        rule.clone().delete(db).await?;
+        Ok(rule)
+    }
+
+    /// Create a categorization rule, or replace rule `id`.
+    pub async fn save_categorization_rule(
+        db: &DbConn,
+        id: Option<i32>,
+        input: CategorizationRuleInput,
+    ) -> Result<categorization_rules::Model, ServiceError> {
+        let name = input.name.trim().to_string();
+        if name.is_empty() {
+            return Err(ServiceError::InvalidCategorizationRule("the name is empty".to_string()));
+        }
+        let criteria = input
+            .criteria
+            .normalized()
+            .map_err(|e| ServiceError::InvalidCategorizationRule(e.to_string()))?;
+        let category = Query::find_category(db, &input.category, &input.subcategory).await?;
+        let now = Utc::now().fixed_offset();
+        let mut rule = match id {
+            Some(id) => Query::find_categorization_rule(db, id).await?.0.into(),
+            None => categorization_rules::ActiveModel {
+                created_at: Set(now),
+                ..Default::default()
+            },
+        };
+        rule.name = Set(name);
+        rule.priority = Set(input.priority);
+        rule.category_id = Set(category.id);
+        rule.payee = Set(criteria.payee);
+        rule.iban = Set(criteria.iban);
+        rule.creditor_id = Set(criteria.creditor_id);
+        rule.mandate_id = Set(criteria.mandate_id);
+        rule.min_amount = Set(criteria.min_amount);
+        rule.max_amount = Set(criteria.max_amount);
+        rule.transaction_type = Set(criteria.transaction_type);
+        rule.remittance_pattern = Set(criteria.remittance_pattern);
+        rule.updated_at = Set(now);
+        Ok(match id {
+            Some(_) => rule.update(db).await?,
+            None => rule.insert(db).await?,
+        })
+    }
+
+    /// Delete a categorization rule; transactions it categorized keep their
+    /// category.
+    pub async fn delete_categorization_rule(
+        db: &DbConn,
+        id: i32,
+    ) -> Result<categorization_rules::Model, ServiceError> {
+        let (rule, _) = Query::find_categorization_rule(db, id).await?;
+        rule.clone().delete(db).await?;
+        Ok(rule)
+    }
+
+    /// Map a mandate to a category, replacing an earlier mapping.
+    /// `description` is for people, e.g. the payee of the debit.
+    pub async fn save_mandate_category(
+        db: &DbConn,
+        mandate_id: &str,
+        category_id: i32,
+        description: Option<String>,
+    ) -> Result<mandate_categories::Model, ServiceError> {
+        let mapping = mandate_categories::Model {
+            creditor_mandate_id: mandate_id.to_string(),
+            category_id,
+            description,
+        };
+        mandate_categories::Entity::insert(mandate_categories::ActiveModel::from(mapping.clone()))
+            .on_conflict(
+                OnConflict::column(mandate_categories::Column::CreditorMandateId)
+                    .update_columns([
+                        mandate_categories::Column::CategoryId,
+                        mandate_categories::Column::Description,
+                    ])
+                    .to_owned(),
+            )
+            .exec(db)
+            .await?;
+        Ok(mapping)
+    }
+
+    /// Note that the debit `reference` was put into `category_id` although
+    /// its mandate maps elsewhere. A debit has at most one open conflict per
+    /// mandate; flagging it again updates the category.
+    pub async fn flag_mandate_conflict(
+        db: &DbConn,
+        mandate_id: &str,
+        reference: &str,
+        category_id: i32,
+    ) -> Result<mandate_category_conflicts::Model, ServiceError> {
+        let open = mandate_category_conflicts::Entity::find()
+            .filter(mandate_category_conflicts::Column::CreditorMandateId.eq(mandate_id))
+            .filter(mandate_category_conflicts::Column::Reference.eq(reference))
+            .filter(mandate_category_conflicts::Column::Status.eq(MandateConflictStatus::Open.to_string()))
+            .one(db)
+            .await?;
+        let mut conflict = match &open {
+            Some(conflict) => conflict.clone().into(),
+            None => mandate_category_conflicts::ActiveModel {
+                creditor_mandate_id: Set(mandate_id.to_string()),
+                reference: Set(reference.to_string()),
+                status: Set(MandateConflictStatus::Open.to_string()),
+                ..Default::default()
+            },
+        };
+        conflict.category_id = Set(category_id);
+        conflict.detected_at = Set(Utc::now().fixed_offset());
+        Ok(match open {
+            Some(_) => conflict.update(db).await?,
+            None => conflict.insert(db).await?,
+        })
+    }
+
+    /// Drop the open conflicts of a debit that is back in line with its
+    /// mandate; returns how many there were.
+    pub async fn dismiss_mandate_conflicts(db: &DbConn, reference: &str) -> Result<u64, ServiceError> {
+        let deleted = mandate_category_conflicts::Entity::delete_many()
+            .filter(mandate_category_conflicts::Column::Reference.eq(reference))
+            .filter(mandate_category_conflicts::Column::Status.eq(MandateConflictStatus::Open.to_string()))
+            .exec(db)
+            .await?;
+        Ok(deleted.rows_affected)
+    }
+
+    /// Close an open conflict. `remap` maps the mandate to the category of
+    /// the debit, which also settles other open conflicts of the mandate
+    /// with that category; otherwise the mapping stays and the debit keeps
+    /// its category as an exception. Debits are not recategorized here.
+    pub async fn resolve_mandate_conflict(
+        db: &DbConn,
+        id: i32,
+        remap: bool,
+    ) -> Result<mandate_category_conflicts::Model, ServiceError> {
+        let open = MandateConflictStatus::Open.to_string();
+        let conflict = mandate_category_conflicts::Entity::find_by_id(id)
+            .filter(mandate_category_conflicts::Column::Status.eq(&open))
+            .one(db)
+            .await?
+            .ok_or(ServiceError::MandateConflictNotFound(id))?;
+        let now = Utc::now().fixed_offset();
+
+        let txn = db.begin().await?;
+        let status = if remap {
+            mandate_categories::Entity::update_many()
+                .col_expr(mandate_categories::Column::CategoryId, Expr::value(conflict.category_id))
+                .filter(mandate_categories::Column::CreditorMandateId.eq(&conflict.creditor_mandate_id))
+                .exec(&txn)
+                .await?;
+            mandate_category_conflicts::Entity::update_many()
+                .col_expr(
+                    mandate_category_conflicts::Column::Status,
+                    Expr::value(MandateConflictStatus::Remapped.to_string()),
+                )
+                .col_expr(mandate_category_conflicts::Column::ResolvedAt, Expr::value(now))
+                .filter(mandate_category_conflicts::Column::Id.ne(id))
+                .filter(
+                    mandate_category_conflicts::Column::CreditorMandateId.eq(&conflict.creditor_mandate_id),
+                )
+                .filter(mandate_category_conflicts::Column::CategoryId.eq(conflict.category_id))
+                .filter(mandate_category_conflicts::Column::Status.eq(&open))
+                .exec(&txn)
+                .await?;
+            MandateConflictStatus::Remapped
+        } else {
+            MandateConflictStatus::Kept
+        };
+        let mut conflict: mandate_category_conflicts::ActiveModel = conflict.into();
+        conflict.status = Set(status.to_string());
+        conflict.resolved_at = Set(Some(now));
+        let conflict = conflict.update(&txn).await?;
+        txn.commit().await?;
+        Ok(conflict)
+    }
+
+    /// Store parsed remittance infos, replacing earlier parses.
+    pub async fn save_remittances(
+        db: &DbConn,
+        remittances: &[(String, Remittance)],
+    ) -> Result<(), ServiceError> {
+        let txn = db.begin().await?;
+        // Stay below SQLite's limit of bind parameters per statement.
+        for chunk in remittances.chunks(90) {
+            let models = chunk
+                .iter()
+                .map(|(reference, remittance)| remittance_model(reference.clone(), remittance))
+                .collect();
+            upsert_remittances(&txn, models).await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+}
+
+fn remittance_model(reference: String, remittance: &Remittance) -> transaction_remittance::ActiveModel {
+    let foreign = remittance.foreign_amount.as_ref();
+    transaction_remittance::ActiveModel {
+        reference: Set(reference),
+        end_to_end_reference: Set(remittance.end_to_end_reference.clone()),
+        mandate_reference: Set(remittance.mandate_reference.clone()),
+        creditor_id: Set(remittance.creditor_id.clone()),
+        purpose: Set(remittance.purpose.clone()),
+        ultimate_creditor: Set(remittance.ultimate_creditor.clone()),
+        card_date: Set(remittance.card_date),
+        fx_amount: Set(foreign.map(|f| f.amount)),
+        fx_currency: Set(foreign.map(|f| f.currency.clone())),
+        fx_rate: Set(foreign.and_then(|f| f.rate)),
+    }
+}
+
+async fn upsert_remittances<C: ConnectionTrait>(
+    db: &C,
+    models: Vec<transaction_remittance::ActiveModel>,
+) -> Result<(), ServiceError> {
+    if models.is_empty() {
+        return Ok(());
+    }
+    transaction_remittance::Entity::insert_many(models)
+        .on_conflict(
+            OnConflict::column(transaction_remittance::Column::Reference)
+                .update_columns([
+                    transaction_remittance::Column::EndToEndReference,
+                    transaction_remittance::Column::MandateReference,
+                    transaction_remittance::Column::CreditorId,
+                    transaction_remittance::Column::Purpose,
+                    transaction_remittance::Column::UltimateCreditor,
+                    transaction_remittance::Column::CardDate,
+                    transaction_remittance::Column::FxAmount,
+                    transaction_remittance::Column::FxCurrency,
+                    transaction_remittance::Column::FxRate,
+                ])
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+async fn merge_duplicate<C: ConnectionTrait>(
+    db: &C,
+    keep: &str,
+    drop: &str,
+) -> Result<(), ServiceError> {
+    let kept_category = transaction_categories::Entity::find_by_id(keep).one(db).await?;
+    let dropped_category = transaction_categories::Entity::find_by_id(drop).one(db).await?;
+    if let (None, Some(category)) = (kept_category, dropped_category) {
+        transaction_categories::ActiveModel {
+            reference: Set(keep.to_string()),
+            category_id: Set(category.category_id),
+            reasoning: Set(category.reasoning),
+            confidence: Set(category.confidence),
+        }
+        .insert(db)
+        .await?;
+    }
+    record_change(db, drop, ChangeSource::User, "merged_into", None, Some(keep.to_string())).await?;
+    // The category of the deleted booking goes with it (ON DELETE CASCADE).
+    account_transactions::Entity::delete_many()
+        .filter(account_transactions::Column::Reference.eq(drop))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+fn category_label(category: &categories::Model) -> String {
+    format!("{}/{}", category.category, category.subcategory)
+}
+
+/// `food/groceries 30.00, household/cleaning 12.50`; `None` for no split.
+fn splits_label(splits: &[Split]) -> Option<String> {
+    (!splits.is_empty()).then(|| {
+        splits
+            .iter()
+            .map(|(split, category)| format!("{} {:.2}", category_label(category), split.amount))
+            .collect::<Vec<_>>()
+            .join(", ")
+    })
+}
+
+/// Transfers, linked or not, with one of `references` as a leg.
+async fn transfers_of<C: ConnectionTrait>(
+    db: &C,
+    references: &[String],
+) -> Result<Vec<internal_transfers::Model>, ServiceError> {
+    Ok(internal_transfers::Entity::find()
+        .filter(
+            Condition::any()
+                .add(internal_transfers::Column::OutgoingReference.is_in(references))
+                .add(internal_transfers::Column::IncomingReference.is_in(references)),
+        )
+        .all(db)
+        .await?)
+}
+
+async fn insert_transfer<C: ConnectionTrait>(
+    db: &C,
+    pair: &TransferPair,
+    source: ChangeSource,
+) -> Result<internal_transfers::Model, ServiceError> {
+    let transfer = internal_transfers::ActiveModel {
+        outgoing_reference: Set(pair.outgoing.clone()),
+        incoming_reference: Set(pair.incoming.clone()),
+        status: Set(TransferStatus::Linked.to_string()),
+        source: Set(source.to_string()),
+        created_at: Set(Utc::now().fixed_offset()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    record_change(db, &pair.outgoing, source, "transfer", None, Some(pair.incoming.clone())).await?;
+    record_change(db, &pair.incoming, source, "transfer", None, Some(pair.outgoing.clone())).await?;
+    Ok(transfer)
+}
+
+/// `references` without duplicates; fails on the first that does not exist.
+async fn known_references<C: ConnectionTrait>(
+    db: &C,
+    references: &[String],
+) -> Result<BTreeSet<String>, ServiceError> {
+    let known: HashSet<String> = account_transactions::Entity::find()
+        .filter(account_transactions::Column::Reference.is_in(references))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|t| t.reference)
+        .collect();
+    if let Some(unknown) = references.iter().find(|r| !known.contains(*r)) {
+        return Err(ServiceError::InvalidTransaction {
+            reference: unknown.clone(),
+            message: "unknown reference".to_string(),
+        });
+    }
+    Ok(references.iter().cloned().collect())
+}
+
+/// The tags of each of `references`, which must all exist.
+async fn current_tags<C: ConnectionTrait>(
+    db: &C,
+    references: &[String],
+) -> Result<BTreeMap<String, BTreeSet<String>>, ServiceError> {
+    let mut current: BTreeMap<String, BTreeSet<String>> = known_references(db, references)
+        .await?
+        .into_iter()
+        .map(|reference| (reference, BTreeSet::new()))
+        .collect();
+    for (link, tag) in transaction_tags::Entity::find()
+        .filter(transaction_tags::Column::Reference.is_in(references))
+        .find_also_related(tags::Entity)
+        .all(db)
+        .await?
+    {
+        if let (Some(tags), Some(tag)) = (current.get_mut(&link.reference), tag) {
+            tags.insert(tag.name);
+        }
+    }
+    Ok(current)
+}
+
+fn tags_label(tags: &BTreeSet<String>) -> Option<String> {
+    (!tags.is_empty()).then(|| tags.iter().cloned().collect::<Vec<_>>().join(", "))
+}
+
+/// Keep the parts of a booking whose amount changed, e.g. a card payment
+/// booked with a different exchange rate, summing to the new amount: they
+/// are scaled, the last part takes the rounding difference.
+async fn rescale_splits<C: ConnectionTrait>(
+    db: &C,
+    reference: &str,
+    old_amount: f64,
+    new_amount: f64,
+    source: ChangeSource,
+) -> Result<(), ServiceError> {
+    if (old_amount - new_amount).abs() < CENT {
+        return Ok(());
+    }
+    let splits = transaction_splits::Entity::find()
+        .filter(transaction_splits::Column::Reference.eq(reference))
+        .order_by_asc(transaction_splits::Column::Position)
+        .all(db)
+        .await?;
+    let Some((last, parts)) = splits.split_last() else {
+        return Ok(());
+    };
+    let mut rest = new_amount;
+    let mut scaled = Vec::with_capacity(splits.len());
+    for part in parts {
+        let amount = match old_amount.abs() < CENT {
+            true => part.amount,
+            false => (part.amount * new_amount / old_amount * 100.0).round() / 100.0,
+        };
+        rest -= amount;
+        scaled.push((part, amount));
+    }
+    scaled.push((last, rest));
+
+    let label = |amounts: Vec<f64>| amounts.iter().map(|a| format!("{a:.2}")).collect::<Vec<_>>().join(", ");
+    let old_label = label(splits.iter().map(|s| s.amount).collect());
+    let new_label = label(scaled.iter().map(|(_, amount)| *amount).collect());
+    for (part, amount) in scaled {
+        let mut part: transaction_splits::ActiveModel = part.clone().into();
+        part.amount = Set(amount);
+        part.update(db).await?;
+    }
+    record_change(db, reference, source, "split_amounts", Some(old_label), Some(new_label)).await
+}
+
+/// Fields of `existing` that `input` changes, as (field, old, new).
+fn changed_fields(
+    existing: &account_transactions::Model,
+    input: &TransactionInput,
+) -> Vec<(&'static str, String, String)> {
+    let mut changes = Vec::new();
+    let mut compare = |field: &'static str, old: String, new: String| {
+        if old != new {
+            changes.push((field, old, new));
+        }
+    };
+    compare("booking_status", existing.booking_status.clone(), input.booking_status.clone());
+    compare("booking_date", existing.booking_date.to_string(), input.booking_date.to_string());
+    compare("amount", existing.amount.to_string(), input.amount.to_string());
+    compare("remitter", existing.remitter.clone(), input.remitter.clone());
+    compare("deptor", existing.deptor.clone(), input.deptor.clone());
+    compare("creditor", existing.creditor.clone(), input.creditor.clone());
+    compare("creditor_id", existing.creditor_id.clone(), input.creditor_id.clone());
+    compare(
+        "creditor_mandate_id",
+        existing.creditor_mandate_id.clone(),
+        input.creditor_mandate_id.clone(),
+    );
+    compare("remittance_info", existing.remittance_info.clone(), input.remittance_info.clone());
+    compare("transaction_type", existing.transaction_type.clone(), input.transaction_type.clone());
+    compare("counterparty_iban", existing.counterparty_iban.clone(), input.counterparty_iban.clone());
+    changes
+}
+
+async fn record_change<C: ConnectionTrait>(
+    db: &C,
+    reference: &str,
+    source: ChangeSource,
+    field: &str,
+    old_value: Option<String>,
+    new_value: Option<String>,
+) -> Result<(), ServiceError> {
+    transaction_history::ActiveModel {
+        reference: Set(reference.to_string()),
+        changed_at: Set(Utc::now().fixed_offset()),
+        source: Set(source.to_string()),
+        field: Set(field.to_string()),
+        old_value: Set(old_value),
+        new_value: Set(new_value),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::{counterparty, remittance};
+    use crate::service::{
+        AccountInput, BalanceInput, CategoryAssignment, ChangeSource, CounterpartyRuleKind, FindingAction,
+        FindingInput, FindingKind, FindingStatus, ImportRunKind, Mutation, Query, SavedViewInput,
+        ServiceError, SplitInput, TransactionFilter, TransactionInput, TransferPair, TransferStatus,
+        UpsertOutcome,
+    };
+    use crate::filter::FilterQuery;
+    use chrono::NaiveDate;
+    use entity::entities::{account_balance, account_transactions, transaction_remittance};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{Database, DatabaseConnection, EntityTrait};
+
+    /// Fresh in-memory SQLite database with all migrations applied.
+    pub(crate) async fn db() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        db
+    }
+
+    pub(crate) fn account(account_id: &str) -> AccountInput {
+        AccountInput {
+            account_id: account_id.to_string(),
+            display_id: format!("{account_id}-display"),
+            account_type: "Girokonto".to_string(),
+            iban: format!("DE00{account_id}"),
+            bic: "COBADEHD001".to_string(),
+            institute: "COMDIRECT".to_string(),
+        }
+    }
+
+    pub(crate) fn transaction(reference: &str, account_id: &str, amount: f64) -> TransactionInput {
+        TransactionInput {
+            reference: reference.to_string(),
+            account_id: account_id.to_string(),
+            booking_status: "BOOKED".to_string(),
+            booking_date: day(2),
+            amount,
+            remitter: String::new(),
+            deptor: String::new(),
+            creditor: "REWE Markt GmbH".to_string(),
+            creditor_id: String::new(),
+            creditor_mandate_id: String::new(),
+            remittance_info: "Einkauf".to_string(),
+            transaction_type: "Lastschrift".to_string(),
+            counterparty_iban: String::new(),
+        }
+    }
+
+    /// A day of June 2025, the month the fixtures are booked in.
+    pub(crate) fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 6, d).unwrap()
+    }
+
+    /// A stored booking for the functions that work on rows instead of the
+    /// database; the texts are empty.
+    pub(crate) fn booking(
+        reference: &str,
+        account_id: &str,
+        d: u32,
+        amount: f64,
+    ) -> account_transactions::Model {
+        account_transactions::Model {
+            id: 0,
+            reference: reference.to_string(),
+            account_id: account_id.to_string(),
+            booking_status: "BOOKED".to_string(),
+            booking_date: day(d),
+            amount,
+            remitter: String::new(),
+            deptor: String::new(),
+            creditor: String::new(),
+            creditor_id: String::new(),
+            creditor_mandate_id: String::new(),
+            remittance_info: String::new(),
+            transaction_type: String::new(),
+            counterparty_iban: String::new(),
+        }
+    }
+
+    /// A balance snapshot of account `A1`.
+    pub(crate) fn snapshot(d: u32, amount: f64) -> account_balance::Model {
+        account_balance::Model {
+            id: 0,
+            amount,
+            date: day(d),
+            account_id: "A1".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ensure_account_keeps_existing() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        let mut changed = account("A1");
+        changed.display_id = "renamed".to_string();
+        let stored = Mutation::ensure_account(&db, changed).await.unwrap();
+        assert_eq!(stored.display_id, "A1-display");
+        assert_eq!(Query::accounts(&db).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_record_balance_keeps_first_of_day() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        let date = day(2);
+        for amount in [100.0, 80.0] {
+            let input = BalanceInput {
+                account_id: "A1".to_string(),
+                date,
+                amount,
+            };
+            Mutation::record_balance(&db, input).await.unwrap();
+        }
+        let balances = Query::balances(&db, &TransactionFilter::default()).await.unwrap();
+        assert_eq!(balances.len(), 1);
+        assert_eq!(balances[0].amount, 100.0);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_transaction_updates_booking() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        let mut pending = transaction("r1", "A1", -10.0);
+        pending.booking_status = "NOTBOOKED".to_string();
+        Mutation::upsert_transaction(&db, pending, ChangeSource::Import)
+            .await
+            .unwrap();
+        Mutation::upsert_transaction(&db, transaction("r1", "A1", -12.5), ChangeSource::Import)
+            .await
+            .unwrap();
+
+        let stored = Query::transactions(&db, &TransactionFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].booking_status, "BOOKED");
+        assert_eq!(stored[0].amount, -12.5);
+
+        let history: Vec<_> = Query::history(&db, "r1")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|h| (h.source, h.field, h.old_value.unwrap(), h.new_value.unwrap()))
+            .collect();
+        assert_eq!(
+            history,
+            [
+                ("import", "booking_status", "NOTBOOKED", "BOOKED"),
+                ("import", "amount", "-10", "-12.5"),
+            ]
+            .map(|(s, f, o, n)| (s.to_string(), f.to_string(), o.to_string(), n.to_string()))
+        );
+
+        // Re-importing an unchanged booking leaves no trace.
+        let outcome =
+            Mutation::upsert_transaction(&db, transaction("r1", "A1", -12.5), ChangeSource::Import)
+                .await
+                .unwrap();
+        assert_eq!(outcome, UpsertOutcome::Unchanged);
+        assert_eq!(Query::history(&db, "r1").await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_transactions_counts_failures() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        let summary = Mutation::upsert_transactions(
+            &db,
+            vec![transaction("r1", "A1", -1.0), transaction("r2", "missing", -1.0)],
+            ChangeSource::Import,
+        )
+        .await
+        .unwrap();
+        assert_eq!((summary.inserted, summary.failed), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_assign_category() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        Mutation::upsert_transaction(&db, transaction("r1", "A1", -10.0), ChangeSource::Import)
+            .await
+            .unwrap();
+        let assignment = |category: &str, subcategory: &str| CategoryAssignment {
+            reference: "r1".to_string(),
+            category: category.to_string(),
+            subcategory: subcategory.to_string(),
+            reasoning: None,
+            confidence: Some(0.9),
+            source: ChangeSource::User,
+        };
+
+        let unknown = Mutation::assign_category(&db, assignment("food", "caviar")).await;
+        assert!(matches!(unknown, Err(ServiceError::UnknownCategory { .. })));
+        assert_eq!(Query::uncategorized(&db, None).await.unwrap().len(), 1);
+
+        Mutation::assign_category(&db, assignment("Food", "Restaurants"))
+            .await
+            .unwrap();
+        Mutation::assign_category(&db, assignment("food", "groceries"))
+            .await
+            .unwrap();
+        let categories = Query::categories_by_reference(&db).await.unwrap();
+        assert_eq!(categories["r1"].subcategory, "groceries");
+        assert!(Query::uncategorized(&db, None).await.unwrap().is_empty());
+
+        let history = Query::history(&db, "r1").await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].old_value, None);
+        assert_eq!(history[1].old_value.as_deref(), Some("food/restaurants"));
+        assert_eq!(history[1].new_value.as_deref(), Some("food/groceries"));
+    }
+
+    #[tokio::test]
+    async fn test_import_runs() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        let failed = Mutation::start_import_run(&db, ImportRunKind::Bootstrap, "comdirect")
+            .await
+            .unwrap();
+        Mutation::finish_import_run(&db, failed.id, 0, Some("TAN not approved".to_string()))
+            .await
+            .unwrap();
+
+        let run = Mutation::start_import_run(&db, ImportRunKind::Import, "comdirect")
+            .await
+            .unwrap();
+        let source = ChangeSource::ImportRun(run.id);
+        let summary =
+            Mutation::upsert_transactions(&db, vec![transaction("r1", "A1", -1.0)], source)
+                .await
+                .unwrap();
+        Mutation::record_import_account(&db, run.id, "A1", 1, &summary)
+            .await
+            .unwrap();
+        Mutation::finish_import_run(&db, run.id, 1, None).await.unwrap();
+
+        let runs = Query::import_runs(&db, None, 10).await.unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].run.id, run.id, "newest first");
+        assert_eq!(runs[0].accounts[0].inserted, 1);
+        assert!(runs[1].accounts.is_empty());
+
+        let succeeded = Query::import_runs(&db, Some(true), 10).await.unwrap();
+        assert_eq!(succeeded.len(), 1);
+        assert_eq!(succeeded[0].run.kind, "import");
+        assert_eq!(Query::import_runs(&db, Some(false), 10).await.unwrap()[0].run.id, failed.id);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_duplicate_finding() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        for reference in ["api-1", "csv-1"] {
+            Mutation::upsert_transaction(&db, transaction(reference, "A1", -5.0), ChangeSource::Import)
+                .await
+                .unwrap();
+        }
+        let categorized = CategoryAssignment {
+            reference: "csv-1".to_string(),
+            category: "food".to_string(),
+            subcategory: "groceries".to_string(),
+            reasoning: None,
+            confidence: None,
+            source: ChangeSource::User,
+        };
+        Mutation::assign_category(&db, categorized).await.unwrap();
+        let date = day(2);
+        let duplicate = FindingInput {
+            kind: FindingKind::Duplicate,
+            account_id: "A1".to_string(),
+            reference: Some("api-1".to_string()),
+            other_reference: Some("csv-1".to_string()),
+            date_from: date,
+            date_to: date,
+            amount: Some(-5.0),
+            similarity: Some(1.0),
+        };
+        let gap = FindingInput {
+            kind: FindingKind::Gap,
+            reference: None,
+            other_reference: None,
+            ..duplicate.clone()
+        };
+        let created = Mutation::replace_open_findings(&db, vec![duplicate.clone(), gap.clone()])
+            .await
+            .unwrap();
+        assert_eq!(created, 2);
+        let findings = Query::findings(&db, Some(FindingStatus::Open), None).await.unwrap();
+        let id = |kind: &str| findings.iter().find(|f| f.kind == kind).unwrap().id;
+
+        let merge_gap = Mutation::resolve_finding(&db, id("gap"), FindingAction::Merge, None).await;
+        assert!(matches!(merge_gap, Err(ServiceError::InvalidFindingAction { .. })));
+        Mutation::resolve_finding(&db, id("gap"), FindingAction::Ignore, None)
+            .await
+            .unwrap();
+        let merged = Mutation::resolve_finding(&db, id("duplicate"), FindingAction::Merge, None)
+            .await
+            .unwrap();
+        assert_eq!(merged.status, "merged");
+
+        let remaining = Query::transactions(&db, &TransactionFilter::default()).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].reference, "api-1");
+        let categories = Query::categories_by_reference(&db).await.unwrap();
+        assert_eq!(categories["api-1"].subcategory, "groceries");
+
+        // Reviewed findings are not raised again by the next detection run.
+        let created = Mutation::replace_open_findings(&db, vec![duplicate, gap]).await.unwrap();
+        assert_eq!(created, 0);
+        assert!(Query::findings(&db, Some(FindingStatus::Open), None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_save_view() {
+        let db = db().await;
+        let view = |owner: &str, name: &str, filter: &str| SavedViewInput {
+            owner: owner.to_string(),
+            name: name.to_string(),
+            filter: filter.to_string(),
+        };
+        let food = Mutation::save_view(&db, None, view("ann", "Food", "category:food"))
+            .await
+            .unwrap();
+        let invalid = Mutation::save_view(&db, None, view("ann", "Big", "amount<abc")).await;
+        assert!(matches!(invalid, Err(ServiceError::InvalidFilter(e)) if e.column == 8));
+        let taken = Mutation::save_view(&db, None, view("ann", "Food", "payee:rewe")).await;
+        assert!(matches!(taken, Err(ServiceError::InvalidView(_))));
+        // Names are unique per owner only.
+        Mutation::save_view(&db, None, view("bob", "Food", "payee:rewe")).await.unwrap();
+
+        let renamed = Mutation::save_view(&db, Some(food.id), view("ann", "Groceries", "category:food/groceries"))
+            .await
+            .unwrap();
+        assert_eq!((renamed.id, renamed.created_at), (food.id, food.created_at));
+        assert_eq!(renamed.filter, "category:food/groceries");
+
+        let foreign = Mutation::save_view(&db, Some(food.id), view("bob", "Mine", "amazon")).await;
+        assert!(matches!(foreign, Err(ServiceError::ViewNotFound(_))));
+        assert!(Query::saved_view(&db, "bob", food.id).await.is_err());
+        let views = Query::saved_views(&db, "ann").await.unwrap();
+        assert_eq!(views.iter().map(|v| v.name.as_str()).collect::<Vec<_>>(), ["Groceries"]);
+
+        assert!(Mutation::delete_view(&db, "bob", food.id).await.is_err());
+        Mutation::delete_view(&db, "ann", food.id).await.unwrap();
+        assert!(Query::saved_views(&db, "ann").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_split_transaction() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        Mutation::upsert_transaction(&db, transaction("r1", "A1", -42.5), ChangeSource::Import)
+            .await
+            .unwrap();
+        Mutation::assign_category(
+            &db,
+            CategoryAssignment {
+                reference: "r1".to_string(),
+                category: "food".to_string(),
+                subcategory: "groceries".to_string(),
+                reasoning: None,
+                confidence: None,
+                source: ChangeSource::Categorizer,
+            },
+        )
+        .await
+        .unwrap();
+        let part = |amount: f64, subcategory: &str| SplitInput {
+            amount,
+            category: "food".to_string(),
+            subcategory: subcategory.to_string(),
+            note: Some(format!("note {subcategory}")),
+            tag: None,
+        };
+
+        let short = Mutation::split_transaction(
+            &db,
+            "r1",
+            vec![part(-30.0, "groceries"), part(-10.0, "restaurants")],
+            ChangeSource::User,
+        )
+        .await;
+        assert!(matches!(short, Err(ServiceError::InvalidTransaction { .. })));
+        let single = vec![part(-42.5, "groceries")];
+        assert!(Mutation::split_transaction(&db, "r1", single, ChangeSource::User).await.is_err());
+
+        let splits = Mutation::split_transaction(
+            &db,
+            "r1",
+            vec![part(-30.0, "groceries"), part(-12.5, "restaurants")],
+            ChangeSource::User,
+        )
+        .await
+        .unwrap();
+        assert_eq!(splits.len(), 2);
+        // The parts replace the category and keep the categorizer away.
+        assert!(Query::categories_by_reference(&db).await.unwrap().is_empty());
+        assert!(Query::uncategorized(&db, None).await.unwrap().is_empty());
+        assert!(Query::is_categorized(&db, "r1").await.unwrap());
+        let reassign = Mutation::assign_category(
+            &db,
+            CategoryAssignment {
+                reference: "r1".to_string(),
+                category: "food".to_string(),
+                subcategory: "groceries".to_string(),
+                reasoning: None,
+                confidence: None,
+                source: ChangeSource::Categorizer,
+            },
+        )
+        .await;
+        assert!(reassign.is_err());
+        let fields: Vec<_> = Query::history(&db, "r1").await.unwrap().into_iter().map(|h| h.field).collect();
+        assert!(fields.ends_with(&["category".to_string(), "splits".to_string()]));
+
+        // A rebooked amount scales the parts.
+        Mutation::upsert_transaction(&db, transaction("r1", "A1", -85.0), ChangeSource::Import)
+            .await
+            .unwrap();
+        let amounts: Vec<_> = Query::splits(&db, "r1").await.unwrap().iter().map(|(s, _)| s.amount).collect();
+        assert_eq!(amounts, [-60.0, -25.0]);
+
+        Mutation::split_transaction(&db, "r1", Vec::new(), ChangeSource::User).await.unwrap();
+        assert_eq!(Query::uncategorized(&db, None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_tags_and_notes() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        for reference in ["r1", "r2", "r3"] {
+            Mutation::upsert_transaction(&db, transaction(reference, "A1", -10.0), ChangeSource::Import)
+                .await
+                .unwrap();
+        }
+        let refs = |refs: &[&str]| refs.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+        let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        let new = tags(&[" Urlaub ", "italien"]);
+        let added = Mutation::add_tags(&db, &refs(&["r1", "r2"]), &new, ChangeSource::User).await.unwrap();
+        assert_eq!(added, 4);
+        // Known tags are not added twice.
+        let added = Mutation::add_tags(&db, &refs(&["r1", "r3"]), &tags(&["urlaub"]), ChangeSource::User)
+            .await
+            .unwrap();
+        assert_eq!(added, 1);
+        let unknown = Mutation::add_tags(&db, &refs(&["r1", "r9"]), &tags(&["x"]), ChangeSource::User).await;
+        assert!(matches!(unknown, Err(ServiceError::InvalidTransaction { .. })));
+        let blank = Mutation::add_tags(&db, &refs(&["r1"]), &tags(&[" "]), ChangeSource::User).await;
+        assert!(matches!(blank, Err(ServiceError::InvalidTag(_))));
+
+        let removed = Mutation::remove_tags(&db, &refs(&["r2"]), &tags(&["URLAUB"]), ChangeSource::User)
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+        let by_reference = Query::tags_by_reference(&db).await.unwrap();
+        assert_eq!(by_reference["r1"], ["italien", "urlaub"]);
+        assert_eq!(by_reference["r2"], ["italien"]);
+        let counts: Vec<_> = Query::tags(&db).await.unwrap().into_iter().map(|(t, n)| (t.name, n)).collect();
+        assert_eq!(counts, [("italien".to_string(), 2), ("urlaub".to_string(), 2)]);
+
+        let filter = TransactionFilter {
+            tags: tags(&["urlaub", "italien"]),
+            ..Default::default()
+        };
+        let conn = &db;
+        let found = |filter: TransactionFilter| async move {
+            let transactions = Query::transactions(conn, &filter).await.unwrap();
+            transactions.into_iter().map(|t| t.reference).collect::<Vec<_>>()
+        };
+        assert_eq!(found(filter).await, ["r1"]);
+        let filter = TransactionFilter {
+            query: Some("-tag:urlaub".parse::<FilterQuery>().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(found(filter).await, ["r2"]);
+
+        let note = Some("mit Anna teilen".to_string());
+        let changed = Mutation::set_note(&db, &refs(&["r1", "r2"]), note, ChangeSource::User).await.unwrap();
+        assert_eq!(changed, 2);
+        Mutation::set_note(&db, &refs(&["r2"]), Some("  ".to_string()), ChangeSource::User).await.unwrap();
+        let notes = Query::notes_by_reference(&db).await.unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes["r1"], "mit Anna teilen");
+        let fields: Vec<_> = Query::history(&db, "r2").await.unwrap().into_iter().map(|h| h.field).collect();
+        assert_eq!(fields[fields.len() - 4..], ["tags", "tags", "note", "note"]);
+    }
+
+    #[tokio::test]
+    async fn test_link_and_unlink_transfer() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        Mutation::ensure_account(&db, account("A2")).await.unwrap();
+        for (reference, account, amount) in [("g1", "A1", -500.0), ("t1", "A2", 500.0), ("g2", "A1", 500.0)] {
+            let input = transaction(reference, account, amount);
+            Mutation::upsert_transaction(&db, input, ChangeSource::Import).await.unwrap();
+        }
+        let same_account = Mutation::link_transfer(&db, "g1", "g2", ChangeSource::User).await;
+        assert!(matches!(same_account, Err(ServiceError::InvalidTransaction { .. })));
+        let not_cancelling = Mutation::link_transfer(&db, "t1", "g2", ChangeSource::User).await;
+        assert!(matches!(not_cancelling, Err(ServiceError::InvalidTransaction { .. })));
+
+        // The legs are ordered by sign, whichever is named first.
+        let linked = Mutation::link_transfer(&db, "t1", "g1", ChangeSource::User).await.unwrap();
+        assert_eq!((linked.outgoing_reference.as_str(), linked.incoming_reference.as_str()), ("g1", "t1"));
+        let again = Mutation::link_transfer(&db, "g1", "t1", ChangeSource::User).await.unwrap();
+        assert_eq!(again.id, linked.id);
+        let filter = TransactionFilter {
+            without_transfers: true,
+            ..Default::default()
+        };
+        let left = Query::transactions(&db, &filter).await.unwrap();
+        assert_eq!(left.into_iter().map(|t| t.reference).collect::<Vec<_>>(), ["g2"]);
+
+        let unlinked = Mutation::unlink_transfer(&db, "t1", ChangeSource::User).await.unwrap();
+        assert_eq!(unlinked.status, TransferStatus::Unlinked.to_string());
+        assert!(Query::transfers_by_reference(&db).await.unwrap().is_empty());
+        // The matcher does not link again what a user unlinked.
+        let pair = TransferPair {
+            outgoing: "g1".to_string(),
+            incoming: "t1".to_string(),
+        };
+        assert_eq!(Mutation::link_detected_transfers(&db, &[pair]).await.unwrap(), 0);
+        let fields: Vec<_> = Query::history(&db, "g1").await.unwrap().into_iter().map(|h| h.field).collect();
+        assert_eq!(fields[fields.len() - 2..], ["transfer", "transfer"]);
+    }
+
+    #[tokio::test]
+    async fn test_counterparties() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        let bookings = [
+            ("r1", "REWE Markt GmbH", -20.0),
+            ("r2", "REWE SAGT DANKE 1234", -30.0),
+            ("r3", "Rewe Center Berlin", -5.0),
+            ("r4", "Edeka Center", -15.0),
+            ("r5", "Stadtwerke Nord", -80.0),
+            ("r6", "SWN Abschlag", -80.0),
+        ];
+        for (reference, creditor, amount) in bookings {
+            let mut input = transaction(reference, "A1", amount);
+            input.creditor = creditor.to_string();
+            if creditor.starts_with("SWN") {
+                input.creditor_id = "DE98ZZZ09999999999".to_string();
+            }
+            Mutation::upsert_transaction(&db, input, ChangeSource::Import).await.unwrap();
+        }
+        let summary = counterparty::resolve(&db).await.unwrap();
+        assert_eq!((summary.changed, summary.created), (6, 5));
+        let payees = |db| async move {
+            let by_reference = Query::counterparties_by_reference(db).await.unwrap();
+            let mut names: Vec<(String, String)> =
+                by_reference.into_iter().map(|(reference, c)| (reference, c.name)).collect();
+            names.sort();
+            names.into_iter().map(|(_, name)| name).collect::<Vec<_>>()
+        };
+        let expected = [
+            "REWE Markt GmbH",
+            "REWE Markt GmbH",
+            "Rewe Center Berlin",
+            "Edeka Center",
+            "Stadtwerke Nord",
+            "SWN Abschlag",
+        ];
+        assert_eq!(payees(&db).await, expected);
+        // Resolving again changes nothing.
+        assert_eq!(counterparty::resolve(&db).await.unwrap(), counterparty::ResolveSummary::default());
+
+        let id = |name: &str| {
+            let db = &db;
+            let name = name.to_string();
+            async move {
+                let all = Query::counterparties(db).await.unwrap();
+                all.into_iter().find(|(c, _)| c.name == name).unwrap().0.id
+            }
+        };
+        let rewe = id("REWE Markt GmbH").await;
+        let stadtwerke = id("Stadtwerke Nord").await;
+        Mutation::merge_counterparties(&db, &[id("Rewe Center Berlin").await], rewe).await.unwrap();
+        Mutation::rename_counterparty(&db, rewe, " REWE ").await.unwrap();
+        let taken = Mutation::rename_counterparty(&db, rewe, "Edeka Center").await;
+        assert!(matches!(taken, Err(ServiceError::InvalidCounterparty(_))));
+        let kind = CounterpartyRuleKind::CreditorId;
+        Mutation::add_counterparty_rule(&db, stadtwerke, kind, "de98 zzz0 9999 9999 99").await.unwrap();
+        let bad = Mutation::add_counterparty_rule(&db, stadtwerke, CounterpartyRuleKind::Regex, "(").await;
+        assert!(matches!(bad, Err(ServiceError::InvalidCounterparty(_))));
+        let summary = counterparty::resolve(&db).await.unwrap();
+        assert_eq!((summary.changed, summary.created), (1, 0));
+        let expected = ["REWE", "REWE", "REWE", "Edeka Center", "Stadtwerke Nord", "Stadtwerke Nord"];
+        assert_eq!(payees(&db).await, expected);
+
+        // The merged alias resolves new bookings to the target.
+        let mut later = transaction("r7", "A1", -7.0);
+        later.creditor = "REWE Center Berlin 0815".to_string();
+        Mutation::upsert_transaction(&db, later, ChangeSource::Import).await.unwrap();
+        counterparty::resolve(&db).await.unwrap();
+        let filter = TransactionFilter::default();
+        let top = counterparty::top_payees(&db, &filter, 2).await.unwrap();
+        let top: Vec<_> = top.into_iter().map(|p| (p.counterparty.name, p.totals.count)).collect();
+        assert_eq!(top, [("Stadtwerke Nord".to_string(), 2), ("REWE".to_string(), 4)]);
+    }
+    #[tokio::test]
+    async fn test_remittance_is_parsed_on_upsert_and_backfilled() {
+        let db = db().await;
+        Mutation::ensure_account(&db, account("A1")).await.unwrap();
+        let mut input = transaction("r1", "A1", -25.0);
+        input.creditor = "PayPal Europe S.a.r.l.".to_string();
+        input.remittance_info = "EREF+1043 MREF+5GJX CRED+LU96ZZZ0000000000000000058 SVWZ+Ihr Einkauf \
+                                 ABWA+Buchhandlung Lesezeit"
+            .to_string();
+        Mutation::upsert_transaction(&db, input.clone(), ChangeSource::Import).await.unwrap();
+        let parsed = Query::remittances_by_reference(&db).await.unwrap().remove("r1").unwrap();
+        assert_eq!(parsed.mandate_reference.as_deref(), Some("5GJX"));
+        assert_eq!(parsed.creditor_id.as_deref(), Some("LU96ZZZ0000000000000000058"));
+        assert_eq!(parsed.purpose, "Ihr Einkauf");
+
+        // A revised booking is parsed again.
+        input.remittance_info = "SVWZ+Ihr Einkauf bei Lesezeit".to_string();
+        Mutation::upsert_transaction(&db, input, ChangeSource::Import).await.unwrap();
+        let parsed = Query::remittances_by_reference(&db).await.unwrap().remove("r1").unwrap();
+        assert_eq!((parsed.purpose.as_str(), parsed.mandate_reference), ("Ihr Einkauf bei Lesezeit", None));
+
+        // Bookings from before the parser are parsed by the backfill.
+        transaction_remittance::Entity::delete_many().exec(&db).await.unwrap();
+        assert_eq!(remittance::backfill(&db).await.unwrap(), 1);
+        assert_eq!(remittance::backfill(&db).await.unwrap(), 0);
 
+        // The ultimate creditor names the payee.
+        let mut shop = transaction("r2", "A1", -12.0);
+        shop.creditor = "PayPal Europe S.a.r.l.".to_string();
+        shop.remittance_info = "SVWZ+Bestellung 1234 ABWA+Buchhandlung Lesezeit".to_string();
+        Mutation::upsert_transaction(&db, shop, ChangeSource::Import).await.unwrap();
+        counterparty::resolve(&db).await.unwrap();
+        let payees = Query::counterparties_by_reference(&db).await.unwrap();
+        assert_eq!(payees["r2"].name, "Buchhandlung Lesezeit");
+        assert_eq!(payees["r1"].name, "PayPal Europe S.a.r.l.");
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,573 @@
+use crate::service::{
+    FindingKind, FindingStatus, ImportRun, MandateConflictStatus, ServiceError, Split, TransactionFilter,
+    TransferStatus,
+};
+use entity::entities::{
+    account, account_balance, account_transactions, attachments, categories, categorization_rules,
+    counterparties, counterparty_rules, daily_balances, data_findings, import_run_accounts, import_runs,
+    internal_transfers, mandate_categories, mandate_category_conflicts, saved_views, tags,
+    transaction_categories, transaction_counterparties, transaction_history, transaction_notes,
+    transaction_remittance, transaction_splits, transaction_tags,
+};
+use sea_orm::sea_query::{Expr, Func, Query as SelectQuery};
+use sea_orm::{
+    ColumnTrait, DbConn, EntityTrait, JoinType, LoaderTrait, QueryFilter, QueryOrder, QuerySelect,
+    RelationTrait,
+};
+use std::collections::HashMap;
+
+pub struct Query;
+
+impl Query {
+    pub async fn accounts(db: &DbConn) -> Result<Vec<account::Model>, ServiceError> {
+        Ok(account::Entity::find()
+            .order_by_asc(account::Column::DisplayId)
+            .all(db)
+            .await?)
+    }
+
+    pub async fn find_account(
+        db: &DbConn,
+        account_id: &str,
+    ) -> Result<Option<account::Model>, ServiceError> {
+        Ok(account::Entity::find()
+            .filter(account::Column::AccountId.eq(account_id))
+            .one(db)
+            .await?)
+    }
+
+    /// Balance snapshots in the filter range, oldest first.
+    pub async fn balances(
+        db: &DbConn,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<account_balance::Model>, ServiceError> {
+        let mut query = account_balance::Entity::find().order_by_asc(account_balance::Column::Date);
+        if !filter.accounts.is_empty() {
+            query = query.filter(account_balance::Column::AccountId.is_in(&filter.accounts));
+        }
+        if let Some(from) = filter.from {
+            query = query.filter(account_balance::Column::Date.gte(from));
+        }
+        if let Some(to) = filter.to {
+            query = query.filter(account_balance::Column::Date.lte(to));
+        }
+        Ok(query.all(db).await?)
+    }
+
+    /// Reconstructed end-of-day balances in the filter range, per account by
+    /// date.
+    pub async fn daily_balances(
+        db: &DbConn,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<daily_balances::Model>, ServiceError> {
+        let mut query = daily_balances::Entity::find()
+            .order_by_asc(daily_balances::Column::AccountId)
+            .order_by_asc(daily_balances::Column::Date);
+        if !filter.accounts.is_empty() {
+            query = query.filter(daily_balances::Column::AccountId.is_in(&filter.accounts));
+        }
+        if let Some(from) = filter.from {
+            query = query.filter(daily_balances::Column::Date.gte(from));
+        }
+        if let Some(to) = filter.to {
+            query = query.filter(daily_balances::Column::Date.lte(to));
+        }
+        Ok(query.all(db).await?)
+    }
+
+    /// Transactions in the filter range in booking order.
+    pub async fn transactions(
+        db: &DbConn,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<account_transactions::Model>, ServiceError> {
+        Ok(account_transactions::Entity::find()
+            .filter(filter.condition())
+            .order_by_asc(account_transactions::Column::BookingDate)
+            .order_by_asc(account_transactions::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    /// Transactions without a category, oldest first.
+    pub async fn uncategorized(
+        db: &DbConn,
+        limit: Option<u64>,
+    ) -> Result<Vec<account_transactions::Model>, ServiceError> {
+        Ok(account_transactions::Entity::find()
+            .join(
+                JoinType::LeftJoin,
+                account_transactions::Relation::TransactionCategories.def(),
+            )
+            .filter(transaction_categories::Column::Reference.is_null())
+            .filter(
+                account_transactions::Column::Reference.not_in_subquery(
+                    SelectQuery::select()
+                        .column(transaction_splits::Column::Reference)
+                        .from(transaction_splits::Entity)
+                        .to_owned(),
+                ),
+            )
+            .order_by_asc(account_transactions::Column::BookingDate)
+            .order_by_asc(account_transactions::Column::Id)
+            .limit(limit)
+            .all(db)
+            .await?)
+    }
+
+    pub async fn categories(db: &DbConn) -> Result<Vec<categories::Model>, ServiceError> {
+        Ok(categories::Entity::find()
+            .order_by_asc(categories::Column::Category)
+            .order_by_asc(categories::Column::Subcategory)
+            .all(db)
+            .await?)
+    }
+
+    /// Look up a category case-insensitively; the LLM tends to capitalize.
+    pub async fn find_category(
+        db: &DbConn,
+        category: &str,
+        subcategory: &str,
+    ) -> Result<categories::Model, ServiceError> {
+        categories::Entity::find()
+            .filter(
+                Expr::expr(Func::lower(Expr::col(categories::Column::Category)))
+                    .eq(category.trim().to_lowercase()),
+            )
+            .filter(
+                Expr::expr(Func::lower(Expr::col(categories::Column::Subcategory)))
+                    .eq(subcategory.trim().to_lowercase()),
+            )
+            .one(db)
+            .await?
+            .ok_or_else(|| ServiceError::UnknownCategory {
+                category: category.to_string(),
+                subcategory: subcategory.to_string(),
+            })
+    }
+
+    /// The category of every categorized transaction, by reference.
+    pub async fn categories_by_reference(
+        db: &DbConn,
+    ) -> Result<HashMap<String, categories::Model>, ServiceError> {
+        Ok(transaction_categories::Entity::find()
+            .find_also_related(categories::Entity)
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|(tc, category)| category.map(|c| (tc.reference, c)))
+            .collect())
+    }
+
+    /// The category of one transaction; `None` if it has none or is split.
+    pub async fn category(db: &DbConn, reference: &str) -> Result<Option<categories::Model>, ServiceError> {
+        Ok(transaction_categories::Entity::find_by_id(reference)
+            .find_also_related(categories::Entity)
+            .one(db)
+            .await?
+            .and_then(|(_, category)| category))
+    }
+
+    /// The parts of every split transaction in order, by reference.
+    pub async fn splits_by_reference(db: &DbConn) -> Result<HashMap<String, Vec<Split>>, ServiceError> {
+        let mut splits: HashMap<String, Vec<Split>> = HashMap::new();
+        for (split, category) in transaction_splits::Entity::find()
+            .find_also_related(categories::Entity)
+            .order_by_asc(transaction_splits::Column::Reference)
+            .order_by_asc(transaction_splits::Column::Position)
+            .all(db)
+            .await?
+        {
+            if let Some(category) = category {
+                splits.entry(split.reference.clone()).or_default().push((split, category));
+            }
+        }
+        Ok(splits)
+    }
+
+    pub async fn splits(db: &DbConn, reference: &str) -> Result<Vec<Split>, ServiceError> {
+        Ok(transaction_splits::Entity::find()
+            .filter(transaction_splits::Column::Reference.eq(reference))
+            .find_also_related(categories::Entity)
+            .order_by_asc(transaction_splits::Column::Position)
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|(split, category)| Some((split, category?)))
+            .collect())
+    }
+
+    /// Every tag with the number of transactions carrying it, by name.
+    pub async fn tags(db: &DbConn) -> Result<Vec<(tags::Model, usize)>, ServiceError> {
+        let mut counts: HashMap<i32, usize> = HashMap::new();
+        for link in transaction_tags::Entity::find().all(db).await? {
+            *counts.entry(link.tag_id).or_default() += 1;
+        }
+        Ok(tags::Entity::find()
+            .order_by_asc(tags::Column::Name)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|tag| {
+                let count = counts.get(&tag.id).copied().unwrap_or_default();
+                (tag, count)
+            })
+            .collect())
+    }
+
+    /// The tag names of every tagged transaction, sorted, by reference. Tags
+    /// of split parts are not included.
+    pub async fn tags_by_reference(db: &DbConn) -> Result<HashMap<String, Vec<String>>, ServiceError> {
+        let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+        for (link, tag) in transaction_tags::Entity::find()
+            .find_also_related(tags::Entity)
+            .order_by_asc(transaction_tags::Column::Reference)
+            .all(db)
+            .await?
+        {
+            if let Some(tag) = tag {
+                tags.entry(link.reference).or_default().push(tag.name);
+            }
+        }
+        tags.values_mut().for_each(|names| names.sort());
+        Ok(tags)
+    }
+
+    pub async fn notes_by_reference(db: &DbConn) -> Result<HashMap<String, String>, ServiceError> {
+        Ok(transaction_notes::Entity::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|n| (n.reference, n.note))
+            .collect())
+    }
+
+    /// Attachments of every transaction that has some, oldest first.
+    pub async fn attachments_by_reference(
+        db: &DbConn,
+    ) -> Result<HashMap<String, Vec<attachments::Model>>, ServiceError> {
+        let mut by_reference: HashMap<String, Vec<attachments::Model>> = HashMap::new();
+        for attachment in attachments::Entity::find().order_by_asc(attachments::Column::Id).all(db).await? {
+            by_reference.entry(attachment.reference.clone()).or_default().push(attachment);
+        }
+        Ok(by_reference)
+    }
+
+    pub async fn find_attachment(db: &DbConn, id: i32) -> Result<attachments::Model, ServiceError> {
+        attachments::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or(ServiceError::AttachmentNotFound(id))
+    }
+
+    /// Whether any attachment still points at the content `sha256`.
+    pub async fn attachment_content_in_use(db: &DbConn, sha256: &str) -> Result<bool, ServiceError> {
+        Ok(attachments::Entity::find()
+            .filter(attachments::Column::Sha256.eq(sha256))
+            .one(db)
+            .await?
+            .is_some())
+    }
+
+    /// Internal transfers, newest first, optionally only those with `status`.
+    pub async fn transfers(
+        db: &DbConn,
+        status: Option<TransferStatus>,
+    ) -> Result<Vec<internal_transfers::Model>, ServiceError> {
+        let mut query = internal_transfers::Entity::find().order_by_desc(internal_transfers::Column::Id);
+        if let Some(status) = status {
+            query = query.filter(internal_transfers::Column::Status.eq(status.to_string()));
+        }
+        Ok(query.all(db).await?)
+    }
+
+    /// The other leg of every linked internal transfer, by reference, in
+    /// both directions.
+    pub async fn transfers_by_reference(db: &DbConn) -> Result<HashMap<String, String>, ServiceError> {
+        let mut legs = HashMap::new();
+        for transfer in Self::transfers(db, Some(TransferStatus::Linked)).await? {
+            legs.insert(transfer.outgoing_reference.clone(), transfer.incoming_reference.clone());
+            legs.insert(transfer.incoming_reference, transfer.outgoing_reference);
+        }
+        Ok(legs)
+    }
+
+    /// Every counterparty with the number of transactions resolved to it,
+    /// by name.
+    pub async fn counterparties(db: &DbConn) -> Result<Vec<(counterparties::Model, usize)>, ServiceError> {
+        let mut counts: HashMap<i32, usize> = HashMap::new();
+        for link in transaction_counterparties::Entity::find().all(db).await? {
+            *counts.entry(link.counterparty_id).or_default() += 1;
+        }
+        Ok(counterparties::Entity::find()
+            .order_by_asc(counterparties::Column::Name)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|counterparty| {
+                let count = counts.get(&counterparty.id).copied().unwrap_or_default();
+                (counterparty, count)
+            })
+            .collect())
+    }
+
+    pub async fn find_counterparty(db: &DbConn, id: i32) -> Result<counterparties::Model, ServiceError> {
+        counterparties::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or(ServiceError::CounterpartyNotFound(id))
+    }
+
+    /// Categorization rules with their category, in the order they are
+    /// tried: highest priority first, older rules first on a tie.
+    pub async fn categorization_rules(
+        db: &DbConn,
+    ) -> Result<Vec<(categorization_rules::Model, categories::Model)>, ServiceError> {
+        Ok(categorization_rules::Entity::find()
+            .find_also_related(categories::Entity)
+            .order_by_desc(categorization_rules::Column::Priority)
+            .order_by_asc(categorization_rules::Column::Id)
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|(rule, category)| Some((rule, category?)))
+            .collect())
+    }
+
+    pub async fn find_categorization_rule(
+        db: &DbConn,
+        id: i32,
+    ) -> Result<(categorization_rules::Model, categories::Model), ServiceError> {
+        categorization_rules::Entity::find_by_id(id)
+            .find_also_related(categories::Entity)
+            .one(db)
+            .await?
+            .and_then(|(rule, category)| Some((rule, category?)))
+            .ok_or(ServiceError::CategorizationRuleNotFound(id))
+    }
+
+    /// Mandates with the category their direct debits get, by mandate ID.
+    pub async fn mandate_categories(
+        db: &DbConn,
+    ) -> Result<Vec<(mandate_categories::Model, categories::Model)>, ServiceError> {
+        Ok(mandate_categories::Entity::find()
+            .find_also_related(categories::Entity)
+            .order_by_asc(mandate_categories::Column::CreditorMandateId)
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|(mandate, category)| Some((mandate, category?)))
+            .collect())
+    }
+
+    pub async fn find_mandate_category(
+        db: &DbConn,
+        mandate_id: &str,
+    ) -> Result<Option<mandate_categories::Model>, ServiceError> {
+        Ok(mandate_categories::Entity::find_by_id(mandate_id).one(db).await?)
+    }
+
+    /// Mandate conflicts with the category the debit was put into, newest
+    /// first.
+    pub async fn mandate_conflicts(
+        db: &DbConn,
+        status: Option<MandateConflictStatus>,
+    ) -> Result<Vec<(mandate_category_conflicts::Model, categories::Model)>, ServiceError> {
+        let mut query = mandate_category_conflicts::Entity::find()
+            .find_also_related(categories::Entity)
+            .order_by_desc(mandate_category_conflicts::Column::DetectedAt)
+            .order_by_asc(mandate_category_conflicts::Column::Id);
+        if let Some(status) = status {
+            query = query.filter(mandate_category_conflicts::Column::Status.eq(status.to_string()));
+        }
+        Ok(query
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|(conflict, category)| Some((conflict, category?)))
+            .collect())
+    }
+
+    pub async fn find_mandate_conflict(
+        db: &DbConn,
+        id: i32,
+    ) -> Result<(mandate_category_conflicts::Model, categories::Model), ServiceError> {
+        mandate_category_conflicts::Entity::find_by_id(id)
+            .find_also_related(categories::Entity)
+            .one(db)
+            .await?
+            .and_then(|(conflict, category)| Some((conflict, category?)))
+            .ok_or(ServiceError::MandateConflictNotFound(id))
+    }
+
+    /// Who last changed the category of each transaction, by reference, as
+    /// recorded in `transaction_history`.
+    pub async fn category_sources(db: &DbConn) -> Result<HashMap<String, String>, ServiceError> {
+        Ok(transaction_history::Entity::find()
+            .filter(transaction_history::Column::Field.eq("category"))
+            .order_by_asc(transaction_history::Column::ChangedAt)
+            .order_by_asc(transaction_history::Column::Id)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|change| (change.reference, change.source))
+            .collect())
+    }
+
+    /// All rules, oldest first.
+    pub async fn counterparty_rules(db: &DbConn) -> Result<Vec<counterparty_rules::Model>, ServiceError> {
+        Ok(counterparty_rules::Entity::find()
+            .order_by_asc(counterparty_rules::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    /// The counterparty of every resolved transaction, by reference.
+    pub async fn counterparties_by_reference(
+        db: &DbConn,
+    ) -> Result<HashMap<String, counterparties::Model>, ServiceError> {
+        Ok(transaction_counterparties::Entity::find()
+            .find_also_related(counterparties::Entity)
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|(link, counterparty)| Some((link.reference, counterparty?)))
+            .collect())
+    }
+
+    /// The parsed remittance info of every transaction, by reference.
+    pub async fn remittances_by_reference(
+        db: &DbConn,
+    ) -> Result<HashMap<String, transaction_remittance::Model>, ServiceError> {
+        Ok(transaction_remittance::Entity::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|remittance| (remittance.reference.clone(), remittance))
+            .collect())
+    }
+
+    /// Whether the transaction has a category or is split.
+    pub async fn is_categorized(db: &DbConn, reference: &str) -> Result<bool, ServiceError> {
+        if transaction_categories::Entity::find_by_id(reference)
+            .one(db)
+            .await?
+            .is_some()
+        {
+            return Ok(true);
+        }
+        Ok(transaction_splits::Entity::find()
+            .filter(transaction_splits::Column::Reference.eq(reference))
+            .one(db)
+            .await?
+            .is_some())
+    }
+
+    /// Recorded changes of one transaction, oldest first.
+    pub async fn history(
+        db: &DbConn,
+        reference: &str,
+    ) -> Result<Vec<transaction_history::Model>, ServiceError> {
+        Ok(transaction_history::Entity::find()
+            .filter(transaction_history::Column::Reference.eq(reference))
+            .order_by_asc(transaction_history::Column::ChangedAt)
+            .order_by_asc(transaction_history::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    /// Import runs newest first. `succeeded` keeps only finished runs without
+    /// (`true`) or with (`false`) an error.
+    pub async fn import_runs(
+        db: &DbConn,
+        succeeded: Option<bool>,
+        limit: u64,
+    ) -> Result<Vec<ImportRun>, ServiceError> {
+        let mut query = import_runs::Entity::find()
+            .order_by_desc(import_runs::Column::StartedAt)
+            .order_by_desc(import_runs::Column::Id)
+            .limit(limit);
+        match succeeded {
+            Some(true) => {
+                query = query
+                    .filter(import_runs::Column::FinishedAt.is_not_null())
+                    .filter(import_runs::Column::Error.is_null())
+            }
+            Some(false) => query = query.filter(import_runs::Column::Error.is_not_null()),
+            None => {}
+        }
+        let runs = query.all(db).await?;
+        let accounts = runs.load_many(import_run_accounts::Entity, db).await?;
+        Ok(runs
+            .into_iter()
+            .zip(accounts)
+            .map(|(run, accounts)| ImportRun { run, accounts })
+            .collect())
+    }
+
+    /// Findings for review, newest first.
+    pub async fn findings(
+        db: &DbConn,
+        status: Option<FindingStatus>,
+        kind: Option<FindingKind>,
+    ) -> Result<Vec<data_findings::Model>, ServiceError> {
+        let mut query = data_findings::Entity::find()
+            .order_by_desc(data_findings::Column::DetectedAt)
+            .order_by_asc(data_findings::Column::Id);
+        if let Some(status) = status {
+            query = query.filter(data_findings::Column::Status.eq(status.to_string()));
+        }
+        if let Some(kind) = kind {
+            query = query.filter(data_findings::Column::Kind.eq(kind.to_string()));
+        }
+        Ok(query.all(db).await?)
+    }
+
+    pub async fn transactions_by_reference(
+        db: &DbConn,
+        references: &[String],
+    ) -> Result<Vec<account_transactions::Model>, ServiceError> {
+        Ok(account_transactions::Entity::find()
+            .filter(account_transactions::Column::Reference.is_in(references))
+            .order_by_asc(account_transactions::Column::BookingDate)
+            .order_by_asc(account_transactions::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    /// Views of one owner by name.
+    pub async fn saved_views(
+        db: &DbConn,
+        owner: &str,
+    ) -> Result<Vec<saved_views::Model>, ServiceError> {
+        Ok(saved_views::Entity::find()
+            .filter(saved_views::Column::Owner.eq(owner))
+            .order_by_asc(saved_views::Column::Name)
+            .all(db)
+            .await?)
+    }
+
+    pub async fn saved_view(
+        db: &DbConn,
+        owner: &str,
+        id: i32,
+    ) -> Result<saved_views::Model, ServiceError> {
+        saved_views::Entity::find_by_id(id)
+            .filter(saved_views::Column::Owner.eq(owner))
+            .one(db)
+            .await?
+            .ok_or_else(|| ServiceError::ViewNotFound(id.to_string()))
+    }
+
+    pub async fn saved_view_by_name(
+        db: &DbConn,
+        owner: &str,
+        name: &str,
+    ) -> Result<saved_views::Model, ServiceError> {
+        saved_views::Entity::find()
+            .filter(saved_views::Column::Owner.eq(owner))
+            .filter(saved_views::Column::Name.eq(name))
+            .one(db)
+            .await?
+            .ok_or_else(|| ServiceError::ViewNotFound(name.to_string()))
+    }
+}
@@ -0,0 +1,198 @@
+//! Internal transfers between our own accounts. Moving money from the Giro
+//! to the Tagesgeld account books a debit on one and a credit on the other;
+//! counted as expense and income they would inflate both totals. The matcher
+//! pairs such legs by amount and date and needs at least one hint that the
+//! money stayed with us: the other party's IBAN is one of our accounts, or
+//! the remittance info names one of them or reads like a transfer.
+
+use crate::service::{Mutation, Query, ServiceError, TransactionFilter, TransferPair};
+use entity::entities::{account, account_transactions};
+use sea_orm::DbConn;
+use std::collections::{HashMap, HashSet};
+
+/// Amounts have to cancel out to the cent.
+const CENT: f64 = 0.005;
+
+/// Remittance info of transfers between own accounts, lower case.
+const HINTS: [&str; 5] = ["umbuchung", "übertrag", "uebertrag", "eigenes konto", "transfer"];
+
+#[derive(Debug, Clone, Copy)]
+pub struct TransferSettings {
+    /// Legs may be booked up to this many days apart.
+    pub window_days: u64,
+}
+
+impl Default for TransferSettings {
+    fn default() -> Self {
+        TransferSettings { window_days: 3 }
+    }
+}
+
+/// Pairs of a debit and a credit on two different accounts that cancel out,
+/// at most `window_days` apart and with a hint that they are a transfer.
+/// Every booking is used at most once; pairs with stronger hints and closer
+/// dates win.
+pub fn find_transfers(
+    transactions: &[account_transactions::Model],
+    accounts: &[account::Model],
+    settings: &TransferSettings,
+) -> Vec<TransferPair> {
+    let ibans: HashMap<&str, String> = accounts
+        .iter()
+        .map(|a| (a.account_id.as_str(), compact(&a.iban)))
+        .filter(|(_, iban)| !iban.is_empty())
+        .collect();
+    let booked = transactions.iter().filter(|t| t.booking_status == "BOOKED");
+    let (outgoing, incoming): (Vec<_>, Vec<_>) = booked.partition(|t| t.amount < 0.0);
+
+    let mut candidates = Vec::new();
+    for o in &outgoing {
+        for i in incoming.iter().filter(|i| i.account_id != o.account_id) {
+            if (o.amount + i.amount).abs() >= CENT {
+                continue;
+            }
+            let days = (i.booking_date - o.booking_date).num_days().unsigned_abs();
+            if days > settings.window_days {
+                continue;
+            }
+            let score = score(o, i, &ibans) + score(i, o, &ibans);
+            if score > 0 {
+                candidates.push((score, days, *o, *i));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then(a.1.cmp(&b.1))
+            .then(a.2.reference.cmp(&b.2.reference))
+            .then(a.3.reference.cmp(&b.3.reference))
+    });
+
+    let mut used: HashSet<&str> = HashSet::new();
+    let mut pairs = Vec::new();
+    for (_, _, o, i) in candidates {
+        if used.contains(o.reference.as_str()) || used.contains(i.reference.as_str()) {
+            continue;
+        }
+        used.extend([o.reference.as_str(), i.reference.as_str()]);
+        pairs.push(TransferPair {
+            outgoing: o.reference.clone(),
+            incoming: i.reference.clone(),
+        });
+    }
+    pairs.sort_by(|a, b| a.outgoing.cmp(&b.outgoing));
+    pairs
+}
+
+/// Match the bookings of all accounts and link the pairs found. Bookings
+/// that already are, or were, part of a transfer are left alone. Returns how
+/// many pairs were linked.
+pub async fn detect(conn: &DbConn, settings: &TransferSettings) -> Result<usize, ServiceError> {
+    let accounts = Query::accounts(conn).await?;
+    let taken: HashSet<String> = Query::transfers(conn, None)
+        .await?
+        .into_iter()
+        .flat_map(|t| [t.outgoing_reference, t.incoming_reference])
+        .collect();
+    let transactions: Vec<account_transactions::Model> =
+        Query::transactions(conn, &TransactionFilter::default())
+            .await?
+            .into_iter()
+            .filter(|t| !taken.contains(&t.reference))
+            .collect();
+    let pairs = find_transfers(&transactions, &accounts, settings);
+    Mutation::link_detected_transfers(conn, &pairs).await
+}
+
+/// How strongly `leg` points at the account of `other`: 2 when its other
+/// party has the IBAN of that account, 1 when its remittance info names the
+/// account or reads like a transfer.
+fn score(
+    leg: &account_transactions::Model,
+    other: &account_transactions::Model,
+    ibans: &HashMap<&str, String>,
+) -> u32 {
+    let Some(iban) = ibans.get(other.account_id.as_str()) else {
+        return 0;
+    };
+    if compact(&leg.counterparty_iban) == *iban {
+        return 2;
+    }
+    let info = leg.remittance_info.to_lowercase();
+    if compact(&info).contains(iban.as_str()) || HINTS.iter().any(|hint| info.contains(hint)) {
+        return 1;
+    }
+    0
+}
+
+/// Upper case without whitespace, as IBANs are compared.
+fn compact(value: &str) -> String {
+    value.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::service::TransferPair;
+    use crate::service::test::booking;
+    use crate::transfer::{find_transfers, TransferSettings};
+    use entity::entities::account;
+
+    fn account(account_id: &str, iban: &str) -> account::Model {
+        account::Model {
+            id: 0,
+            account_id: account_id.to_string(),
+            display_id: account_id.to_string(),
+            account_type: String::new(),
+            iban: iban.to_string(),
+            bic: String::new(),
+            institute: "COMDIRECT".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_pairs_legs_with_hints() {
+        let accounts = [
+            account("giro", "DE02 1203 0000 0000 2020 51"),
+            account("tagesgeld", "DE89370400440532013000"),
+        ];
+        let mut to_savings = booking("g1", "giro", 2, -500.0);
+        to_savings.counterparty_iban = "DE89370400440532013000".to_string();
+        let savings_in = booking("t1", "tagesgeld", 3, 500.0);
+        let mut back = booking("t2", "tagesgeld", 10, -200.0);
+        back.remittance_info = "Umbuchung".to_string();
+        let giro_in = booking("g2", "giro", 10, 200.0);
+        // Same amount but no hint: a refund is no transfer.
+        let card = booking("g3", "giro", 20, -50.0);
+        let refund = booking("t3", "tagesgeld", 20, 50.0);
+        // A hint, but outside the window.
+        let mut late = booking("g4", "giro", 1, -75.0);
+        late.remittance_info = "Übertrag".to_string();
+        let late_in = booking("t4", "tagesgeld", 9, 75.0);
+
+        let transactions = [to_savings, savings_in, back, giro_in, card, refund, late, late_in];
+        let pair = |outgoing: &str, incoming: &str| TransferPair {
+            outgoing: outgoing.to_string(),
+            incoming: incoming.to_string(),
+        };
+        assert_eq!(
+            find_transfers(&transactions, &accounts, &TransferSettings::default()),
+            [pair("g1", "t1"), pair("t2", "g2")]
+        );
+    }
+
+    #[test]
+    fn test_iban_beats_closer_date() {
+        let accounts = [
+            account("giro", "DE02120300000000202051"),
+            account("tagesgeld", "DE89370400440532013000"),
+        ];
+        let mut hinted = booking("g1", "giro", 5, -100.0);
+        hinted.counterparty_iban = "DE89370400440532013000".to_string();
+        let mut worded = booking("g2", "giro", 4, -100.0);
+        worded.remittance_info = "Umbuchung".to_string();
+        let credit = booking("t1", "tagesgeld", 4, 100.0);
+        let pairs = find_transfers(&[hinted, worded, credit], &accounts, &TransferSettings::default());
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].outgoing, "g1");
+    }
+}
@@ -0,0 +1,46 @@
+//! Saved views: named filter expressions (see [`crate::filter`]) that act
+//! like smart folders. Each view belongs to one user; the totals, monthly
+//! series and exports of a view are computed on every request, so they
+//! always reflect the current bookings and categories.
+
+use crate::service::{Query, ServiceError, TransactionFilter};
+use actix_web::http::header::HeaderMap;
+use entity::entities::{account_transactions, saved_views};
+use sea_orm::DbConn;
+
+/// Header carrying the authenticated user, set by the proxy in front of the
+/// web app. It must not be passed through from clients.
+pub const OWNER_HEADER: &str = "X-Forwarded-User";
+/// Owner of the views of single-user installations and of the CLI.
+pub const DEFAULT_OWNER: &str = "default";
+
+/// The user a request acts for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Owner(pub String);
+
+impl Default for Owner {
+    fn default() -> Self {
+        Owner(DEFAULT_OWNER.to_string())
+    }
+}
+
+impl Owner {
+    pub fn from_headers(headers: &HeaderMap) -> Owner {
+        headers
+            .get(OWNER_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| Owner(value.to_string()))
+            .unwrap_or_default()
+    }
+}
+
+/// Transactions of `view` within `filter`.
+pub async fn transactions(
+    conn: &DbConn,
+    view: &saved_views::Model,
+    filter: TransactionFilter,
+) -> Result<Vec<account_transactions::Model>, ServiceError> {
+    Query::transactions(conn, &filter.with_view(view)?).await
+}